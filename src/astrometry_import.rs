@@ -0,0 +1,781 @@
+//! # Astrometry Import
+//!
+//! Library to read back the observations written by measuring software, checking them before
+//! submission to the Minor Planet Center
+//!
+//! MPC1992 80-column files, ADES PSV and ADES XML are supported. Every record is checked for
+//! column alignment, packed designation, date precision, station code, band and catalogue
+//! code, problems are collected as issues with their line number.
+//!
+//! ```rust
+//! use asteroid_tui::astrometry_import::parse_astrometry;
+//! let line = "     K24A01B* C2025 01 15.12066 04 58 06.24 +29 30 18.0          19.5 GW     123";
+//! let astrometry = parse_astrometry(line, "123");
+//! assert_eq!(astrometry.observations.len(), 1);
+//! assert!(astrometry.issues.is_empty());
+//! let astrometry = parse_astrometry(line, "568");
+//! assert_eq!(astrometry.issues[0].field, "Station");
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::designation::{Designation, DesignationPatterns};
+use crate::report::{self, Observation, BANDS, CATALOGUES};
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate};
+use regex::Regex;
+use std::collections::HashMap;
+use std::fmt;
+use std::path::Path;
+
+/// Header lines of MPC1992 reports
+const MPC1992_HEADERS: [&str; 11] = [
+    "COD", "CON", "OBS", "MEA", "TEL", "ACK", "AC2", "NET", "BIB", "COM", "NUM",
+];
+
+/// Minimum decimals of the day for CCD observations in MPC1992 dates
+const MIN_DATE_DECIMALS: usize = 5;
+
+/// Astrometry file format
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum AstrometryFormat {
+    /// MPC1992 80-column format
+    Mpc1992,
+    /// ADES pipe separated values
+    AdesPsv,
+    /// ADES XML
+    AdesXml,
+}
+
+impl fmt::Display for AstrometryFormat {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            AstrometryFormat::Mpc1992 => write!(f, "MPC1992"),
+            AstrometryFormat::AdesPsv => write!(f, "ADES PSV"),
+            AstrometryFormat::AdesXml => write!(f, "ADES XML"),
+        }
+    }
+}
+
+/// Problem found in an astrometry file
+///
+/// * `line`: line number, from 1
+/// * `field`: checked field, i.e. "Date" or "Station"
+/// * `message`: description of the problem
+#[derive(Debug, Clone, PartialEq)]
+pub struct Issue {
+    /// Line number, from 1
+    pub line: usize,
+    /// Checked field, i.e. "Date" or "Station"
+    pub field: String,
+    /// Description of the problem
+    pub message: String,
+}
+
+impl Issue {
+    /// Creates an issue
+    ///
+    /// * `line`: line number, from 1
+    /// * `field`: checked field
+    /// * `message`: description of the problem
+    fn new(line: usize, field: &str, message: String) -> Self {
+        Issue {
+            line,
+            field: field.to_string(),
+            message,
+        }
+    }
+}
+
+/// Imported astrometry structure
+///
+/// * `format`: detected file format
+/// * `observations`: observations that could be read, with their line number
+/// * `issues`: problems found
+#[derive(Debug, Clone)]
+pub struct Astrometry {
+    /// Detected file format
+    pub format: AstrometryFormat,
+    /// Observations that could be read, with their line number
+    pub observations: Vec<(usize, Observation)>,
+    /// Problems found
+    pub issues: Vec<Issue>,
+}
+
+impl Astrometry {
+    /// Creates an empty import
+    ///
+    /// * `format`: file format
+    fn new(format: AstrometryFormat) -> Self {
+        Astrometry {
+            format,
+            observations: Vec::new(),
+            issues: Vec::new(),
+        }
+    }
+
+    /// Adds an issue
+    ///
+    /// * `line`: line number, from 1
+    /// * `field`: checked field
+    /// * `message`: description of the problem
+    fn issue(&mut self, line: usize, field: &str, message: String) {
+        self.issues.push(Issue::new(line, field, message));
+    }
+
+    /// Adds an observation read without problems, checking its values
+    ///
+    /// * `line`: line number, from 1
+    /// * `observation`: observation
+    fn push(&mut self, line: usize, observation: Observation) {
+        for message in observation.validate() {
+            self.issue(line, "Observation", message);
+        }
+        self.observations.push((line, observation));
+    }
+
+    /// Returns true if no problems were found
+    pub fn is_valid(&self) -> bool {
+        self.issues.is_empty()
+    }
+}
+
+/// Detects the format of an astrometry file from its content
+///
+/// * `text`: file content
+pub fn detect_format(text: &str) -> AstrometryFormat {
+    let start = text.trim_start();
+    if start.starts_with("<?xml") || start.starts_with("<ades") {
+        AstrometryFormat::AdesXml
+    } else if start.starts_with("# version=") {
+        AstrometryFormat::AdesPsv
+    } else {
+        AstrometryFormat::Mpc1992
+    }
+}
+
+/// Reads and checks an astrometry file
+///
+/// * `path`: file path
+/// * `mpc_code`: observatory code the observations must have
+pub fn import_file(path: &Path, mpc_code: &str) -> Result<Astrometry> {
+    Ok(parse_astrometry(&std::fs::read_to_string(path)?, mpc_code))
+}
+
+/// Reads and checks astrometry in any supported format
+///
+/// * `text`: file content
+/// * `mpc_code`: observatory code the observations must have
+pub fn parse_astrometry(text: &str, mpc_code: &str) -> Astrometry {
+    match detect_format(text) {
+        AstrometryFormat::Mpc1992 => parse_mpc1992(text, mpc_code),
+        AstrometryFormat::AdesPsv => parse_ades_psv(text, mpc_code),
+        AstrometryFormat::AdesXml => parse_ades_xml(text, mpc_code),
+    }
+}
+
+/// Checks the station code of a record
+///
+/// * `astrometry`: import collecting the issues
+/// * `line`: line number
+/// * `station`: station code of the record
+/// * `mpc_code`: observatory code
+fn check_station(astrometry: &mut Astrometry, line: usize, station: &str, mpc_code: &str) {
+    if station != mpc_code {
+        astrometry.issue(
+            line,
+            "Station",
+            format!(
+                "Station \"{}\" differs from observatory code \"{}\"",
+                station, mpc_code
+            ),
+        );
+    }
+}
+
+/// Patterns of the MPC1992 record fields
+///
+/// * `date`: observation date with decimal day, columns 16-32
+/// * `ra`: right ascension, columns 33-44
+/// * `dec`: declination, columns 45-56
+struct RecordPatterns {
+    /// Observation date with decimal day, columns 16-32
+    date: Regex,
+    /// Right ascension, columns 33-44
+    ra: Regex,
+    /// Declination, columns 45-56
+    dec: Regex,
+}
+
+impl RecordPatterns {
+    /// Compiles the patterns
+    fn new() -> Self {
+        RecordPatterns {
+            date: Regex::new(r"^(\d{4}) (\d\d) (\d\d)\.(\d+) *$").unwrap(),
+            ra: Regex::new(r"^(\d\d) (\d\d) (\d\d(?:\.\d*)?) *$").unwrap(),
+            dec: Regex::new(r"^([+-])(\d\d) (\d\d) (\d\d(?:\.\d*)?) *$").unwrap(),
+        }
+    }
+}
+
+/// Reads and checks an MPC1992 80-column file
+///
+/// * `text`: file content
+/// * `mpc_code`: observatory code the observations must have
+fn parse_mpc1992(text: &str, mpc_code: &str) -> Astrometry {
+    let mut astrometry = Astrometry::new(AstrometryFormat::Mpc1992);
+    let patterns = RecordPatterns::new();
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim_end_matches('\r');
+        if line.trim().is_empty() || line.starts_with("----- end") {
+            continue;
+        }
+        if line
+            .get(..3)
+            .is_some_and(|code| MPC1992_HEADERS.contains(&code))
+        {
+            if let Some(code) = line.strip_prefix("COD ") {
+                check_station(&mut astrometry, number, code.trim(), mpc_code);
+            }
+            continue;
+        }
+        if !line.is_ascii() {
+            astrometry.issue(
+                number,
+                "Columns",
+                "Line contains non ASCII characters".to_string(),
+            );
+        } else if line.len() != 80 {
+            astrometry.issue(
+                number,
+                "Columns",
+                format!("Line has {} columns, 80 expected", line.len()),
+            );
+        } else if let Some(observation) =
+            parse_mpc1992_line(&mut astrometry, number, line, mpc_code, &patterns)
+        {
+            astrometry.push(number, observation);
+        }
+    }
+    astrometry
+}
+
+/// Reads and checks an MPC1992 observation record
+///
+/// Returns the observation if no problems were found.
+///
+/// * `astrometry`: import collecting the issues
+/// * `number`: line number
+/// * `line`: 80 ASCII columns record
+/// * `mpc_code`: observatory code
+/// * `patterns`: record field patterns
+fn parse_mpc1992_line(
+    astrometry: &mut Astrometry,
+    number: usize,
+    line: &str,
+    mpc_code: &str,
+    patterns: &RecordPatterns,
+) -> Option<Observation> {
+    let issues = astrometry.issues.len();
+    let columns = |start: usize, end: usize| &line[start - 1..end];
+
+    let mut observation = Observation {
+        discovery: columns(13, 13) == "*",
+        note: columns(14, 14).chars().find(|c| *c != ' '),
+        ..Observation::default()
+    };
+    match Designation::from_packed_columns(columns(1, 12)) {
+        Ok(designation) => observation.designation = designation,
+        Err(_)
+            if columns(1, 5).trim().is_empty()
+                && !columns(6, 12).trim().is_empty()
+                && columns(6, 12)
+                    .trim_end()
+                    .chars()
+                    .all(|c| c.is_ascii_alphanumeric()) =>
+        {
+            observation.trksub = Some(columns(6, 12).trim_end().to_string())
+        }
+        Err(_) => astrometry.issue(
+            number,
+            "Designation",
+            format!("Invalid packed designation \"{}\"", columns(1, 12)),
+        ),
+    }
+    if columns(15, 15) == " " {
+        astrometry.issue(
+            number,
+            "Mode",
+            "Missing observation mode in column 15".to_string(),
+        );
+    }
+    match patterns.date.captures(columns(16, 32)) {
+        Some(captures) => {
+            let decimals = captures[4].len();
+            if columns(15, 15) == "C" && decimals < MIN_DATE_DECIMALS {
+                astrometry.issue(
+                    number,
+                    "Date",
+                    format!(
+                        "Date has {} decimals, CCD observations need at least {}",
+                        decimals, MIN_DATE_DECIMALS
+                    ),
+                );
+            }
+            let fraction: f64 = format!("0.{}", &captures[4]).parse().unwrap_or(0.0);
+            match NaiveDate::from_ymd_opt(
+                captures[1].parse().unwrap_or(0),
+                captures[2].parse().unwrap_or(0),
+                captures[3].parse().unwrap_or(0),
+            ) {
+                Some(date) => {
+                    observation.time = date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+                        + Duration::milliseconds((fraction * 86400000.0).round() as i64)
+                }
+                None => astrometry.issue(
+                    number,
+                    "Date",
+                    format!("Invalid date \"{}\"", columns(16, 32).trim()),
+                ),
+            }
+        }
+        None => astrometry.issue(
+            number,
+            "Date",
+            format!("Misaligned date in columns 16-32 \"{}\"", columns(16, 32)),
+        ),
+    }
+    match patterns.ra.captures(columns(33, 44)) {
+        Some(captures) => {
+            let (hours, minutes, seconds): (f64, f64, f64) = (
+                captures[1].parse().unwrap_or(99.0),
+                captures[2].parse().unwrap_or(99.0),
+                captures[3].parse().unwrap_or(99.0),
+            );
+            if hours >= 24.0 || minutes >= 60.0 || seconds >= 60.0 {
+                astrometry.issue(
+                    number,
+                    "RA",
+                    format!("Invalid RA \"{}\"", columns(33, 44).trim()),
+                );
+            }
+            observation.ra = (hours + minutes / 60.0 + seconds / 3600.0) * 15.0;
+        }
+        None => astrometry.issue(
+            number,
+            "RA",
+            format!("Misaligned RA in columns 33-44 \"{}\"", columns(33, 44)),
+        ),
+    }
+    match patterns.dec.captures(columns(45, 56)) {
+        Some(captures) => {
+            let (degrees, minutes, seconds): (f64, f64, f64) = (
+                captures[2].parse().unwrap_or(99.0),
+                captures[3].parse().unwrap_or(99.0),
+                captures[4].parse().unwrap_or(99.0),
+            );
+            if degrees > 90.0 || minutes >= 60.0 || seconds >= 60.0 {
+                astrometry.issue(
+                    number,
+                    "Dec",
+                    format!("Invalid Dec \"{}\"", columns(45, 56).trim()),
+                );
+            }
+            let dec = degrees + minutes / 60.0 + seconds / 3600.0;
+            observation.dec = if &captures[1] == "-" { -dec } else { dec };
+        }
+        None => astrometry.issue(
+            number,
+            "Dec",
+            format!("Misaligned Dec in columns 45-56 \"{}\"", columns(45, 56)),
+        ),
+    }
+    if !columns(57, 65).trim().is_empty() || !columns(73, 77).trim().is_empty() {
+        astrometry.issue(
+            number,
+            "Columns",
+            "Unexpected characters in columns 57-65 or 73-77".to_string(),
+        );
+    }
+    let band = columns(71, 71).chars().next().unwrap_or(' ');
+    match columns(66, 70).trim() {
+        "" => (),
+        magnitude => match magnitude.parse::<f32>() {
+            Ok(magnitude) => {
+                observation.magnitude = Some(magnitude);
+                observation.band = band;
+                if !BANDS.contains(&band) {
+                    astrometry.issue(number, "Band", format!("Unknown band '{}'", band));
+                }
+            }
+            Err(_) => astrometry.issue(
+                number,
+                "Magnitude",
+                format!("Invalid magnitude \"{}\"", magnitude),
+            ),
+        },
+    }
+    match columns(72, 72).chars().next().unwrap_or(' ') {
+        ' ' => astrometry.issue(
+            number,
+            "Catalogue",
+            "Missing catalogue code in column 72".to_string(),
+        ),
+        code if !CATALOGUES.iter().any(|(_, column, _)| *column == code) => astrometry.issue(
+            number,
+            "Catalogue",
+            format!("Unknown catalogue code '{}'", code),
+        ),
+        _ => (),
+    }
+    check_station(astrometry, number, columns(78, 80), mpc_code);
+    (astrometry.issues.len() == issues).then_some(observation)
+}
+
+/// Reads and checks an ADES optical record
+///
+/// Returns the observation if no problems were found.
+///
+/// * `astrometry`: import collecting the issues
+/// * `number`: line number
+/// * `fields`: ADES fields by name
+/// * `mpc_code`: observatory code
+/// * `patterns`: designation patterns
+fn parse_ades_record(
+    astrometry: &mut Astrometry,
+    number: usize,
+    fields: &HashMap<String, String>,
+    mpc_code: &str,
+    patterns: &DesignationPatterns,
+) -> Option<Observation> {
+    let issues = astrometry.issues.len();
+    let field = |name: &str| fields.get(name).map(String::as_str).unwrap_or("");
+    let mut observation = Observation {
+        discovery: field("disc") == "*",
+        note: field("notes").chars().next(),
+        trksub: Some(field("trkSub").to_string()).filter(|trksub| !trksub.is_empty()),
+        ..Observation::default()
+    };
+    let designations: Result<Vec<Designation>> = [field("permID"), field("provID")]
+        .iter()
+        .filter(|value| !value.is_empty())
+        .map(|value| Designation::parse_with(value, patterns))
+        .collect();
+    match designations.as_deref() {
+        Ok([]) if observation.trksub.is_none() => astrometry.issue(
+            number,
+            "Designation",
+            "One of permID, provID or trkSub is needed".to_string(),
+        ),
+        Ok([]) => (),
+        Ok([designation]) => observation.designation = designation.clone(),
+        Ok([permanent, provisional, ..]) => {
+            observation.designation = Designation {
+                provisional: provisional.provisional.clone(),
+                comet_type: permanent.comet_type.or(provisional.comet_type),
+                ..permanent.clone()
+            }
+        }
+        Err(error) => astrometry.issue(number, "Designation", error.to_string()),
+    }
+    if field("mode").is_empty() {
+        astrometry.issue(number, "Mode", "Missing observation mode".to_string());
+    }
+    match DateTime::parse_from_rfc3339(field("obsTime")) {
+        Ok(time) if field("obsTime").ends_with('Z') => observation.time = time.to_utc(),
+        _ => astrometry.issue(
+            number,
+            "Date",
+            format!(
+                "Invalid obsTime \"{}\", UTC ISO 8601 with Z expected",
+                field("obsTime")
+            ),
+        ),
+    }
+    match (field("ra").parse::<f64>(), field("dec").parse::<f64>()) {
+        (Ok(ra), Ok(dec)) => (observation.ra, observation.dec) = (ra, dec),
+        _ => astrometry.issue(
+            number,
+            "RA/Dec",
+            format!("Invalid position \"{}\" \"{}\"", field("ra"), field("dec")),
+        ),
+    }
+    if report::catalogue(field("astCat")).is_none() {
+        astrometry.issue(
+            number,
+            "Catalogue",
+            format!("Unknown catalogue \"{}\"", field("astCat")),
+        );
+    }
+    if !field("mag").is_empty() {
+        match field("mag").parse::<f32>() {
+            Ok(magnitude) => observation.magnitude = Some(magnitude),
+            Err(_) => astrometry.issue(
+                number,
+                "Magnitude",
+                format!("Invalid magnitude \"{}\"", field("mag")),
+            ),
+        }
+        let mut band = field("band").chars();
+        match (band.next(), band.next()) {
+            (Some(band), None) if BANDS.contains(&band) => observation.band = band,
+            _ => astrometry.issue(
+                number,
+                "Band",
+                format!("Unknown band \"{}\"", field("band")),
+            ),
+        }
+    }
+    check_station(astrometry, number, field("stn"), mpc_code);
+    (astrometry.issues.len() == issues).then_some(observation)
+}
+
+/// Reads and checks an ADES PSV file
+///
+/// * `text`: file content
+/// * `mpc_code`: observatory code the observations must have
+fn parse_ades_psv(text: &str, mpc_code: &str) -> Astrometry {
+    let mut astrometry = Astrometry::new(AstrometryFormat::AdesPsv);
+    let patterns = DesignationPatterns::new();
+    let mut names: Option<Vec<String>> = None;
+    let mut block = "";
+    for (index, line) in text.lines().enumerate() {
+        let number = index + 1;
+        let line = line.trim();
+        if line.is_empty() {
+            continue;
+        }
+        if let Some(header) = line.strip_prefix('#') {
+            block = header.trim();
+            continue;
+        }
+        if let Some(keyword) = line.strip_prefix('!') {
+            if let Some(code) = keyword.trim().strip_prefix("mpcCode") {
+                if block == "observatory" {
+                    check_station(&mut astrometry, number, code.trim(), mpc_code);
+                }
+            }
+            continue;
+        }
+        let values: Vec<String> = line
+            .split('|')
+            .map(|value| value.trim().to_string())
+            .collect();
+        let Some(names) = &names else {
+            names = Some(values);
+            continue;
+        };
+        if values.len() != names.len() {
+            astrometry.issue(
+                number,
+                "Columns",
+                format!(
+                    "Row has {} fields, the header has {}",
+                    values.len(),
+                    names.len()
+                ),
+            );
+            continue;
+        }
+        let fields: HashMap<String, String> = names.iter().cloned().zip(values).collect();
+        if let Some(observation) =
+            parse_ades_record(&mut astrometry, number, &fields, mpc_code, &patterns)
+        {
+            astrometry.push(number, observation);
+        }
+    }
+    if names.is_none() {
+        astrometry.issue(0, "Columns", "Missing field names line".to_string());
+    }
+    astrometry
+}
+
+/// Replaces the XML entities with their characters
+///
+/// * `value`: escaped text
+fn unescape_xml(value: &str) -> String {
+    value
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+        .replace("&quot;", "\"")
+        .replace("&apos;", "'")
+        .replace("&amp;", "&")
+}
+
+/// Reads and checks an ADES XML file
+///
+/// * `text`: file content
+/// * `mpc_code`: observatory code the observations must have
+fn parse_ades_xml(text: &str, mpc_code: &str) -> Astrometry {
+    let mut astrometry = Astrometry::new(AstrometryFormat::AdesXml);
+    let patterns = DesignationPatterns::new();
+    let line_of = |offset: usize| text[..offset].matches('\n').count() + 1;
+    let code_regex = Regex::new(r"<mpcCode>\s*([^<]*?)\s*</mpcCode>").unwrap();
+    let optical_regex = Regex::new(r"(?s)<optical>(.*?)</optical>").unwrap();
+    let field_regex = Regex::new(r"<(\w+)>([^<]*)</(\w+)>").unwrap();
+    for captures in code_regex.captures_iter(text) {
+        let number = line_of(captures.get(0).unwrap().start());
+        check_station(
+            &mut astrometry,
+            number,
+            &unescape_xml(&captures[1]),
+            mpc_code,
+        );
+    }
+    for captures in optical_regex.captures_iter(text) {
+        let number = line_of(captures.get(0).unwrap().start());
+        let mut fields = HashMap::new();
+        for field in field_regex.captures_iter(&captures[1]) {
+            if field[1] != field[3] {
+                astrometry.issue(
+                    number,
+                    "Columns",
+                    format!("Element <{}> closed by </{}>", &field[1], &field[3]),
+                );
+            }
+            fields.insert(field[1].to_string(), unescape_xml(field[2].trim()));
+        }
+        if let Some(observation) =
+            parse_ades_record(&mut astrometry, number, &fields, mpc_code, &patterns)
+        {
+            astrometry.push(number, observation);
+        }
+    }
+    if astrometry.observations.is_empty() && astrometry.issues.is_empty() {
+        astrometry.issue(0, "Columns", "No optical observations found".to_string());
+    }
+    astrometry
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::report::{Report, ReportHeader, Telescope};
+    use chrono::{TimeZone, Utc};
+
+    fn report() -> Report {
+        let header = ReportHeader {
+            mpc_code: "123".to_string(),
+            observatory_name: "Club & Friends".to_string(),
+            observers: vec!["J. Doe".to_string()],
+            measurers: vec!["J. Doe".to_string()],
+            telescope: Some(Telescope {
+                aperture: 0.2,
+                f_ratio: 4.0,
+                design: "reflector".to_string(),
+                detector: "CCD".to_string(),
+            }),
+            acknowledgement: "Club astrometry".to_string(),
+            email: "doe@example.org".to_string(),
+            catalogue: "Gaia3".to_string(),
+        };
+        let observations = vec![
+            Observation {
+                designation: Designation::parse("C/2020 F3").unwrap(),
+                discovery: true,
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 2, 53, 45).unwrap(),
+                ra: 74.526,
+                dec: -29.505,
+                magnitude: Some(9.5),
+                ..Observation::default()
+            },
+            Observation {
+                designation: Designation::parse("(433) Eros").unwrap(),
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 3, 0, 0).unwrap(),
+                ra: 120.0,
+                dec: 10.0,
+                ..Observation::default()
+            },
+            Observation {
+                trksub: Some("CLB0001".to_string()),
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 3, 0, 0).unwrap(),
+                ra: 10.0,
+                dec: 0.0,
+                magnitude: Some(19.2),
+                band: 'V',
+                ..Observation::default()
+            },
+        ];
+        Report::new(header, observations)
+    }
+
+    #[test]
+    fn test_parse_mpc1992() {
+        let text = std::fs::read_to_string("response_examples/astrometry_mpc.txt").unwrap();
+        let astrometry = parse_astrometry(&text, "123");
+        assert_eq!(astrometry.format, AstrometryFormat::Mpc1992);
+        assert_eq!(astrometry.observations.len(), 1);
+        let (line, observation) = &astrometry.observations[0];
+        assert_eq!(*line, 8);
+        assert_eq!(observation.designation.to_string(), "2024 AB1");
+        assert!(observation.discovery);
+        assert!((observation.ra - 74.526).abs() < 1e-4);
+        let problems: Vec<(usize, &str)> = astrometry
+            .issues
+            .iter()
+            .map(|issue| (issue.line, issue.field.as_str()))
+            .collect();
+        assert_eq!(
+            problems,
+            [
+                (9, "Station"),
+                (10, "Date"),
+                (11, "Columns"),
+                (12, "Band"),
+                (13, "Designation"),
+                (14, "Catalogue")
+            ]
+        );
+        assert_eq!(
+            astrometry.issues[1].message,
+            "Date has 4 decimals, CCD observations need at least 5"
+        );
+    }
+
+    #[test]
+    fn test_parse_ades() {
+        let report = report();
+        for text in [
+            report.to_ades_psv().unwrap(),
+            report.to_ades_xml().unwrap(),
+            report.to_mpc1992().unwrap(),
+        ] {
+            let astrometry = parse_astrometry(&text, "123");
+            assert!(astrometry.is_valid(), "{:?}", astrometry.issues);
+            let observations: Vec<&Observation> = astrometry
+                .observations
+                .iter()
+                .map(|(_, observation)| observation)
+                .collect();
+            assert_eq!(observations.len(), 3);
+            assert_eq!(
+                observations[0].designation,
+                report.observations[0].designation
+            );
+            assert_eq!(observations[1].designation.number, Some(433));
+            assert_eq!(observations[2].trksub.as_deref(), Some("CLB0001"));
+            // MPC1992 dates are rounded to 1e-5 days
+            assert!(
+                (observations[0].time - report.observations[0].time).abs() < Duration::seconds(1)
+            );
+            assert!((observations[0].dec + 29.505).abs() < 1e-4);
+            assert_eq!(observations[2].magnitude, Some(19.2));
+            assert!(!parse_astrometry(&text, "568").is_valid());
+        }
+        let psv = report
+            .to_ades_psv()
+            .unwrap()
+            .replace("|Gaia3|9.50|G|", "|Gaia9|9.50|G|")
+            .replace("|CCD|123|2025-01-15T03:00:00.000Z|120", "|CCD|123|120");
+        let astrometry = parse_astrometry(&psv, "123");
+        assert_eq!(astrometry.format, AstrometryFormat::AdesPsv);
+        assert_eq!(astrometry.observations.len(), 1);
+        assert_eq!(astrometry.issues[0].field, "Catalogue");
+        assert_eq!(astrometry.issues[1].field, "Columns");
+        assert_eq!(
+            parse_astrometry("<?xml version=\"1.0\"?><ades/>", "123").issues[0].message,
+            "No optical observations found"
+        );
+    }
+}
@@ -0,0 +1,189 @@
+//! # Catalogue
+//!
+//! Library for the local orbit catalogue, stored in MPCORB.DAT format next to `config.toml`
+//!
+//! Orbits are imported from MPCORB.DAT, NEA.txt or any file of MPC one-line orbit records;
+//! an object imported again replaces the older orbit with the same packed designation.
+//!
+//! ```rust,no_run
+//! use asteroid_tui::catalogue::Catalogue;
+//! let catalogue = Catalogue::load().unwrap();
+//! let ceres = catalogue.search("ceres");
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::orbit::{self, Orbit};
+use anyhow::{anyhow, Result};
+use std::collections::BTreeMap;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Orbit catalogue structure
+///
+/// * `orbits`: orbits by packed designation
+#[derive(Debug, Clone, Default)]
+pub struct Catalogue {
+    /// Orbits by packed designation
+    orbits: BTreeMap<String, Orbit>,
+}
+
+/// Returns the path of the catalogue, next to `config.toml`
+pub fn catalogue_path() -> PathBuf {
+    dirs::config_local_dir()
+        .unwrap()
+        .join("asteroid_tui")
+        .join("catalogue.dat")
+}
+
+impl Catalogue {
+    /// Constructor for Catalogue struct
+    ///
+    /// * `orbits`: orbits of the catalogue
+    pub fn new(orbits: Vec<Orbit>) -> Self {
+        let mut catalogue = Catalogue::default();
+        catalogue.insert(orbits);
+        catalogue
+    }
+
+    /// Loads the catalogue next to `config.toml`, empty if it does not exist yet
+    pub fn load() -> Result<Self> {
+        Catalogue::load_from(&catalogue_path())
+    }
+
+    /// Loads a catalogue from a file, empty if it does not exist
+    ///
+    /// * `path`: path of the catalogue
+    pub fn load_from(path: &Path) -> Result<Self> {
+        if !path.exists() {
+            return Ok(Catalogue::default());
+        }
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        Ok(Catalogue::new(orbit::parse_mpcorb(&text)))
+    }
+
+    /// Saves the catalogue next to `config.toml`
+    pub fn save(&self) -> Result<()> {
+        self.save_to(&catalogue_path())
+    }
+
+    /// Saves the catalogue to a file
+    ///
+    /// * `path`: path of the catalogue
+    pub fn save_to(&self, path: &Path) -> Result<()> {
+        let text: String = self
+            .orbits
+            .values()
+            .map(|orbit| orbit.to_mpcorb_line() + "\n")
+            .collect();
+        fs::write(path, text).map_err(|e| anyhow!("Failed to write {}: {}", path.display(), e))
+    }
+
+    /// Adds orbits, replacing those with the same packed designation
+    ///
+    /// * `orbits`: orbits to add
+    pub fn insert(&mut self, orbits: Vec<Orbit>) {
+        self.orbits.extend(
+            orbits
+                .into_iter()
+                .map(|orbit| (orbit.packed_designation.clone(), orbit)),
+        );
+    }
+
+    /// Imports a file of MPC one-line orbit records, returning the number of orbits imported
+    ///
+    /// * `path`: path of MPCORB.DAT, NEA.txt or a similar file
+    pub fn import(&mut self, path: &Path) -> Result<usize> {
+        let orbits = orbit::load_mpcorb(path)?;
+        let count = orbits.len();
+        self.insert(orbits);
+        Ok(count)
+    }
+
+    /// Returns the number of orbits
+    pub fn len(&self) -> usize {
+        self.orbits.len()
+    }
+
+    /// Returns true if the catalogue has no orbits
+    pub fn is_empty(&self) -> bool {
+        self.orbits.is_empty()
+    }
+
+    /// Returns all orbits, sorted by packed designation
+    pub fn orbits(&self) -> Vec<Orbit> {
+        self.orbits.values().cloned().collect()
+    }
+
+    /// Returns the orbit with a packed designation
+    ///
+    /// * `packed_designation`: packed designation, i.e. "00015"
+    pub fn get(&self, packed_designation: &str) -> Option<&Orbit> {
+        self.orbits.get(packed_designation.trim())
+    }
+
+    /// Returns the orbits whose designation contains `query`, ignoring case
+    ///
+    /// A number matches the numbered object exactly, i.e. "15" finds (15) Eunomia only.
+    ///
+    /// * `query`: name, number, provisional or packed designation
+    pub fn search(&self, query: &str) -> Vec<&Orbit> {
+        let query = query.trim().to_lowercase();
+        if query.is_empty() {
+            return Vec::new();
+        }
+        let number = format!("({})", query.trim_matches(['(', ')']));
+        self.orbits
+            .values()
+            .filter(|orbit| {
+                let designation = orbit.designation.to_lowercase();
+                if orbit.packed_designation.to_lowercase() == query {
+                    true
+                } else if query.trim_matches(['(', ')']).parse::<u32>().is_ok() {
+                    designation.starts_with(&number)
+                } else {
+                    designation.contains(&query)
+                }
+            })
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_search() {
+        let mut catalogue = Catalogue::default();
+        catalogue
+            .import(Path::new("response_examples/mpcorb.dat"))
+            .unwrap();
+        assert_eq!(catalogue.len(), 3);
+        assert_eq!(catalogue.search("CERES")[0].packed_designation, "00001");
+        assert_eq!(catalogue.search("433")[0].designation, "(433) Eros");
+        assert!(catalogue.search("43").is_empty());
+        assert_eq!(catalogue.search("00002")[0].designation, "(2) Pallas");
+        assert!(catalogue.search("").is_empty());
+        assert!(catalogue.get("00433").unwrap().is_neo());
+    }
+
+    #[test]
+    fn test_save_and_load() {
+        let path = std::env::temp_dir().join("asteroid_tui_test_catalogue.dat");
+        let mut catalogue = Catalogue::default();
+        catalogue
+            .import(Path::new("response_examples/mpcorb.dat"))
+            .unwrap();
+        let mut pallas = catalogue.get("00002").unwrap().clone();
+        pallas.absolute_magnitude = 4.2;
+        catalogue.insert(vec![pallas]);
+        catalogue.save_to(&path).unwrap();
+        let loaded = Catalogue::load_from(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        assert_eq!(loaded.len(), 3);
+        assert_eq!(loaded.get("00002").unwrap().absolute_magnitude, 4.2);
+        assert_eq!(loaded.orbits(), catalogue.orbits());
+    }
+}
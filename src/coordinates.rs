@@ -0,0 +1,393 @@
+//! # Coordinates
+//!
+//! Library for converting equatorial coordinates (RA/Dec) to horizontal ones (Alt/Az)
+//!
+//! Conversions use the mean sidereal time (IAU 1982), so they are accurate to a few
+//! arcseconds, more than enough for pointing and planning. Azimuth is measured from North
+//! through East.
+//!
+//! ```rust
+//! use asteroid_tui::coordinates::{self, Observer};
+//! let observer = Observer::new(44.09727, 9.7737, 100.0);
+//! let ra = coordinates::parse_ra("04 58 06.2").unwrap();
+//! let dec = coordinates::parse_dec("+29 30 18").unwrap();
+//! let position = coordinates::apparent_horizontal(ra, dec, chrono::Utc::now(), &observer);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::settings::Settings;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Duration, Utc};
+
+/// Observer location
+///
+/// * `latitude`: latitude in degrees
+/// * `longitude`: longitude in degrees, east positive
+/// * `altitude`: altitude above sea level in meters
+#[derive(Debug, Clone, Copy)]
+pub struct Observer {
+    /// Latitude in degrees
+    pub latitude: f64,
+    /// Longitude in degrees, east positive
+    pub longitude: f64,
+    /// Altitude above sea level in meters
+    pub altitude: f64,
+}
+
+impl Observer {
+    /// Constructor for Observer struct
+    ///
+    /// * `latitude`: latitude in degrees
+    /// * `longitude`: longitude in degrees, east positive
+    /// * `altitude`: altitude above sea level in meters
+    pub fn new(latitude: f64, longitude: f64, altitude: f64) -> Self {
+        Observer {
+            latitude,
+            longitude,
+            altitude,
+        }
+    }
+
+    /// Returns the observer at the observatory in `Settings`
+    ///
+    /// * `settings`: settings with observatory location
+    pub fn from_settings(settings: &Settings) -> Self {
+        Observer::new(
+            *settings.get_latitude() as f64,
+            *settings.get_longitude() as f64,
+            *settings.get_altitude() as f64,
+        )
+    }
+}
+
+/// Horizontal coordinates
+///
+/// * `altitude`: altitude above the horizon in degrees
+/// * `azimuth`: azimuth in degrees, from North through East
+#[derive(Debug, Clone, Copy)]
+pub struct HorizontalCoordinates {
+    /// Altitude above the horizon in degrees
+    pub altitude: f64,
+    /// Azimuth in degrees, from North through East
+    pub azimuth: f64,
+}
+
+/// Parses a sexagesimal value such as "04 58 06.2" or "-29:30:18", returning sign and value
+///
+/// * `value`: the value to be parsed
+fn parse_sexagesimal(value: &str) -> Result<(f64, f64)> {
+    let value = value.trim();
+    let sign = if value.starts_with('-') { -1.0 } else { 1.0 };
+    let parts: Vec<f64> = value
+        .trim_start_matches(['+', '-'])
+        .split([' ', ':'])
+        .filter(|part| !part.is_empty())
+        .map(|part| {
+            part.parse::<f64>()
+                .map_err(|e| anyhow!("Failed to parse {}: {}", value, e))
+        })
+        .collect::<Result<Vec<f64>>>()?;
+    if parts.is_empty() || parts.len() > 3 {
+        return Err(anyhow!("{} is not a sexagesimal value", value));
+    }
+    let absolute = parts
+        .iter()
+        .zip([1.0, 60.0, 3600.0])
+        .map(|(part, divisor)| part / divisor)
+        .sum::<f64>();
+    Ok((sign, absolute))
+}
+
+/// Parses right ascension in hours, minutes and seconds, returning degrees
+///
+/// * `ra`: right ascension, i.e. "04 58 06.2"
+pub fn parse_ra(ra: &str) -> Result<f64> {
+    let (sign, hours) = parse_sexagesimal(ra)?;
+    if sign < 0.0 || hours >= 24.0 {
+        return Err(anyhow!("{} is not a valid right ascension", ra));
+    }
+    Ok(hours * 15.0)
+}
+
+/// Parses declination in degrees, arcminutes and arcseconds, returning degrees
+///
+/// * `dec`: declination, i.e. "+29 30 18"
+pub fn parse_dec(dec: &str) -> Result<f64> {
+    let (sign, degrees) = parse_sexagesimal(dec)?;
+    if degrees > 90.0 {
+        return Err(anyhow!("{} is not a valid declination", dec));
+    }
+    Ok(sign * degrees)
+}
+
+/// Formats right ascension in degrees as hours, minutes and seconds, i.e. "04 58 06.2"
+///
+/// * `ra`: right ascension in degrees
+pub fn format_ra(ra: f64) -> String {
+    let tenths = (ra.rem_euclid(360.0) / 15.0 * 36000.0).round() as i64 % 864000;
+    format!(
+        "{:02} {:02} {:04.1}",
+        tenths / 36000,
+        tenths / 600 % 60,
+        (tenths % 600) as f64 / 10.0
+    )
+}
+
+/// Formats declination in degrees as degrees, arcminutes and arcseconds, i.e. "+29 30 18"
+///
+/// * `dec`: declination in degrees
+pub fn format_dec(dec: f64) -> String {
+    let seconds = (dec.abs() * 3600.0).round() as i64;
+    format!(
+        "{}{:02} {:02} {:02}",
+        if dec < 0.0 { '-' } else { '+' },
+        seconds / 3600,
+        seconds / 60 % 60,
+        seconds % 60
+    )
+}
+
+/// Returns the Julian Day of a UTC date-time
+///
+/// * `datetime`: date-time to convert
+pub fn julian_day(datetime: DateTime<Utc>) -> f64 {
+    datetime.timestamp_millis() as f64 / 86400000.0 + 2440587.5
+}
+
+/// TAI−UTC in seconds from the first day of a month (year, month), after each leap second
+const LEAP_SECONDS: [(i32, u32, f64); 28] = [
+    (1972, 1, 10.0),
+    (1972, 7, 11.0),
+    (1973, 1, 12.0),
+    (1974, 1, 13.0),
+    (1975, 1, 14.0),
+    (1976, 1, 15.0),
+    (1977, 1, 16.0),
+    (1978, 1, 17.0),
+    (1979, 1, 18.0),
+    (1980, 1, 19.0),
+    (1981, 7, 20.0),
+    (1982, 7, 21.0),
+    (1983, 7, 22.0),
+    (1985, 7, 23.0),
+    (1988, 1, 24.0),
+    (1990, 1, 25.0),
+    (1991, 1, 26.0),
+    (1992, 7, 27.0),
+    (1993, 7, 28.0),
+    (1994, 7, 29.0),
+    (1996, 1, 30.0),
+    (1997, 7, 31.0),
+    (1999, 1, 32.0),
+    (2006, 1, 33.0),
+    (2009, 1, 34.0),
+    (2012, 7, 35.0),
+    (2015, 7, 36.0),
+    (2017, 1, 37.0),
+];
+
+/// Difference between TT and TAI in seconds
+const TT_MINUS_TAI: f64 = 32.184;
+
+/// Returns TT−UTC in seconds at a UTC date-time
+///
+/// It comes from the leap seconds, the first value is used before 1972.
+///
+/// * `datetime`: UTC date-time
+pub fn tt_minus_utc(datetime: DateTime<Utc>) -> f64 {
+    let month = (datetime.year(), datetime.month());
+    let tai_minus_utc = LEAP_SECONDS
+        .iter()
+        .rev()
+        .find(|(year, first_month, _)| (*year, *first_month) <= month)
+        .map_or(LEAP_SECONDS[0].2, |(_, _, seconds)| *seconds);
+    tai_minus_utc + TT_MINUS_TAI
+}
+
+/// Returns the Julian Day in Terrestrial Time (TT) of a UTC date-time
+///
+/// Orbit epochs and planet positions are in TT, while sidereal time needs `julian_day`.
+///
+/// * `datetime`: UTC date-time to convert
+pub fn tt_julian_day(datetime: DateTime<Utc>) -> f64 {
+    julian_day(datetime) + tt_minus_utc(datetime) / 86400.0
+}
+
+/// Returns the UTC date-time of a Julian Day, to the millisecond
+///
+/// * `jd`: Julian Day
+pub fn datetime_from_julian_day(jd: f64) -> DateTime<Utc> {
+    DateTime::UNIX_EPOCH + Duration::milliseconds(((jd - 2440587.5) * 86400000.0).round() as i64)
+}
+
+/// Returns Greenwich mean sidereal time in degrees
+///
+/// * `jd`: Julian Day
+pub fn greenwich_sidereal_time(jd: f64) -> f64 {
+    let t = (jd - 2451545.0) / 36525.0;
+    (280.46061837 + 360.98564736629 * (jd - 2451545.0) + 0.000387933 * t * t
+        - t * t * t / 38710000.0)
+        .rem_euclid(360.0)
+}
+
+/// Returns local mean sidereal time in degrees
+///
+/// * `time`: UTC time
+/// * `longitude`: longitude in degrees, east positive
+pub fn local_sidereal_time(time: DateTime<Utc>, longitude: f64) -> f64 {
+    (greenwich_sidereal_time(julian_day(time)) + longitude).rem_euclid(360.0)
+}
+
+/// Returns hour angle in degrees, between -180 and 180 (negative east of the meridian)
+///
+/// * `ra`: right ascension in degrees
+/// * `time`: UTC time
+/// * `longitude`: longitude in degrees, east positive
+pub fn hour_angle(ra: f64, time: DateTime<Utc>, longitude: f64) -> f64 {
+    (local_sidereal_time(time, longitude) - ra + 180.0).rem_euclid(360.0) - 180.0
+}
+
+/// Converts equatorial coordinates to geometric horizontal ones, without refraction
+///
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `time`: UTC time
+/// * `observer`: observer location
+pub fn equatorial_to_horizontal(
+    ra: f64,
+    dec: f64,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> HorizontalCoordinates {
+    let h = hour_angle(ra, time, observer.longitude).to_radians();
+    let (phi, delta) = (observer.latitude.to_radians(), dec.to_radians());
+    let altitude = (phi.sin() * delta.sin() + phi.cos() * delta.cos() * h.cos()).asin();
+    let azimuth =
+        (-h.sin() * delta.cos()).atan2(delta.sin() * phi.cos() - delta.cos() * phi.sin() * h.cos());
+    HorizontalCoordinates {
+        altitude: altitude.to_degrees(),
+        azimuth: azimuth.to_degrees().rem_euclid(360.0),
+    }
+}
+
+/// Returns atmospheric refraction in degrees for a geometric altitude
+///
+/// It uses the Saemundsson formula at 10°C, scaling pressure with the observer altitude.
+///
+/// * `altitude`: geometric altitude in degrees
+/// * `observer_altitude`: observer altitude above sea level in meters
+pub fn refraction(altitude: f64, observer_altitude: f64) -> f64 {
+    if altitude < -2.0 {
+        return 0.0;
+    }
+    let pressure = 1010.0 * (-observer_altitude.max(0.0) / 8435.0).exp();
+    let arcminutes = 1.02 / (altitude + 10.3 / (altitude + 5.11)).to_radians().tan();
+    arcminutes.max(0.0) / 60.0 * pressure / 1010.0
+}
+
+/// Converts equatorial coordinates to apparent horizontal ones, refraction included
+///
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `time`: UTC time
+/// * `observer`: observer location
+pub fn apparent_horizontal(
+    ra: f64,
+    dec: f64,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> HorizontalCoordinates {
+    let geometric = equatorial_to_horizontal(ra, dec, time, observer);
+    HorizontalCoordinates {
+        altitude: geometric.altitude + refraction(geometric.altitude, observer.altitude),
+        azimuth: geometric.azimuth,
+    }
+}
+
+/// Returns apparent altitude of an object from `start` to `end`, every `step`
+///
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `start`: first UTC time
+/// * `end`: last UTC time
+/// * `step`: time between two points
+/// * `observer`: observer location
+pub fn altitude_curve(
+    ra: f64,
+    dec: f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    step: Duration,
+    observer: &Observer,
+) -> Vec<(DateTime<Utc>, f64)> {
+    let mut curve: Vec<(DateTime<Utc>, f64)> = Vec::new();
+    let mut time = start;
+    while time <= end && step > Duration::zero() {
+        curve.push((time, apparent_horizontal(ra, dec, time, observer).altitude));
+        time += step;
+    }
+    curve
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_parse_sexagesimal() {
+        assert!((parse_ra("04 58 06.2").unwrap() - 74.525833).abs() < 1e-5);
+        assert!((parse_dec("+29 30 18").unwrap() - 29.505).abs() < 1e-9);
+        assert!((parse_dec("-00 30 00").unwrap() + 0.5).abs() < 1e-9);
+        assert!(parse_ra("25 00 00").is_err());
+        assert!(parse_dec("None").is_err());
+        assert_eq!(format_ra(parse_ra("04 58 06.2").unwrap()), "04 58 06.2");
+        assert_eq!(format_ra(359.99999), "00 00 00.0");
+        assert_eq!(format_dec(parse_dec("+29 30 18").unwrap()), "+29 30 18");
+        assert_eq!(format_dec(-0.5), "-00 30 00");
+    }
+
+    #[test]
+    fn test_equatorial_to_horizontal() {
+        // (15) Eunomia from response_examples/whatsup.html
+        let observer = Observer::new(44.09727, 9.7737, 0.0);
+        let time = NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(1, 0, 0)
+            .unwrap()
+            .and_utc();
+        let position = equatorial_to_horizontal(
+            parse_ra("04 58 05.4").unwrap(),
+            parse_dec("+29 29 58").unwrap(),
+            time,
+            &observer,
+        );
+        assert!((position.altitude - 37.4).abs() < 0.1);
+        // Setting object, so it is west of the meridian
+        assert!((180.0..360.0).contains(&position.azimuth));
+    }
+
+    #[test]
+    fn test_tt_julian_day() {
+        let date = |year, month, day| {
+            chrono::NaiveDate::from_ymd_opt(year, month, day)
+                .unwrap()
+                .and_hms_opt(0, 0, 0)
+                .unwrap()
+                .and_utc()
+        };
+        assert_eq!(tt_minus_utc(date(2025, 5, 5)), 69.184);
+        assert_eq!(tt_minus_utc(date(2016, 12, 31)), 68.184);
+        assert_eq!(tt_minus_utc(date(1972, 6, 30)), 42.184);
+        let time = date(2025, 5, 5);
+        assert!((tt_julian_day(time) - julian_day(time) - 69.184 / 86400.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn test_refraction() {
+        assert!((refraction(0.0, 0.0) * 60.0 - 29.0).abs() < 1.0);
+        assert!(refraction(90.0, 0.0).abs() < 0.001);
+        assert!(refraction(10.0, 2000.0) < refraction(10.0, 0.0));
+    }
+}
@@ -0,0 +1,589 @@
+//! # Designation
+//!
+//! Library for minor planet and comet designations, in the packed form used by the Minor
+//! Planet Center in orbit files, NEOCP and observation reports, and in the readable one
+//!
+//! * numbered minor planets: "00015" is (15), "A0345" is (100345), "~0000" is (620000)
+//! * provisional designations: "K24A01B" is 2024 AB1
+//! * survey designations: "PLS2040" is 2040 P-L, "T1S3138" is 3138 T-1
+//! * comets: "0001P" is 1P, "PK24F010" is P/2024 F1, "CK24F01b" is C/2024 F1-B
+//!
+//! ```rust
+//! use asteroid_tui::designation::Designation;
+//! let eunomia = Designation::parse(" (15) Eunomia").unwrap();
+//! assert_eq!(eunomia.number, Some(15));
+//! assert_eq!(eunomia.to_packed().unwrap(), "00015");
+//! assert_eq!(Designation::from_packed("K24A01B").unwrap().to_string(), "2024 AB1");
+//! ```
+
+#![warn(missing_docs)]
+
+use anyhow::{anyhow, Result};
+use regex::Regex;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Characters of the base 62 numbers used in packed designations
+const BASE62: &str = "0123456789ABCDEFGHIJKLMNOPQRSTUVWXYZabcdefghijklmnopqrstuvwxyz";
+
+/// Comet orbit types: periodic, non-periodic, defunct, uncertain, interstellar and asteroidal
+const COMET_TYPES: &str = "PCDXIA";
+
+/// Palomar-Leiden and Trojan surveys, packed and readable
+const SURVEYS: [(&str, &str); 4] = [("PL", "P-L"), ("T1", "T-1"), ("T2", "T-2"), ("T3", "T-3")];
+
+/// Designation structure
+///
+/// * `number`: minor planet or periodic comet number
+/// * `name`: name
+/// * `provisional`: readable provisional designation, i.e. "2024 AB1" or "2024 F1"
+/// * `comet_type`: comet orbit type, i.e. 'P' or 'C', none for minor planets
+#[derive(Debug, Clone, Default, PartialEq, Eq, Deserialize, Serialize)]
+pub struct Designation {
+    /// Minor planet or periodic comet number
+    pub number: Option<u32>,
+    /// Name
+    pub name: Option<String>,
+    /// Readable provisional designation, i.e. "2024 AB1" or "2024 F1"
+    pub provisional: Option<String>,
+    /// Comet orbit type, i.e. 'P' or 'C', none for minor planets
+    pub comet_type: Option<char>,
+}
+
+/// Returns the value of a base 62 character
+///
+/// * `value`: the character
+fn base62_value(value: char) -> Result<u32> {
+    BASE62
+        .find(value)
+        .map(|index| index as u32)
+        .ok_or_else(|| anyhow!("Invalid packed character {}", value))
+}
+
+/// Returns the base 62 character of a value
+///
+/// * `value`: the value, below 62
+fn base62_char(value: u32) -> Result<char> {
+    BASE62
+        .chars()
+        .nth(value as usize)
+        .ok_or_else(|| anyhow!("{} is too large to be packed", value))
+}
+
+/// Unpacks a packed number, i.e. "00015" to 15 or "A0345" to 100345
+///
+/// * `packed`: five characters packed number
+pub fn unpack_number(packed: &str) -> Result<u32> {
+    let characters: Vec<char> = packed.chars().collect();
+    match characters[..] {
+        ['~', ..] if characters.len() == 5 => Ok(620000
+            + characters[1..].iter().try_fold(0, |number, character| {
+                Ok::<u32, anyhow::Error>(number * 62 + base62_value(*character)?)
+            })?),
+        [first, ref rest @ ..] if characters.len() == 5 && first.is_ascii_alphanumeric() => {
+            let rest: String = rest.iter().collect();
+            let rest = rest
+                .parse::<u32>()
+                .map_err(|_| anyhow!("Invalid packed number {}", packed))?;
+            Ok(base62_value(first)? * 10000 + rest)
+        }
+        _ => Err(anyhow!("Invalid packed number {}", packed)),
+    }
+}
+
+/// Packs a number, i.e. 15 to "00015" or 100345 to "A0345"
+///
+/// * `number`: the number, up to 15396335
+pub fn pack_number(number: u32) -> Result<String> {
+    match number {
+        0..=99999 => Ok(format!("{:05}", number)),
+        100000..=619999 => Ok(format!(
+            "{}{:04}",
+            base62_char(number / 10000)?,
+            number % 10000
+        )),
+        _ => {
+            let mut rest = number - 620000;
+            let mut packed = String::new();
+            for _ in 0..4 {
+                packed.insert(0, base62_char(rest % 62)?);
+                rest /= 62;
+            }
+            if rest > 0 {
+                return Err(anyhow!("{} is too large to be packed", number));
+            }
+            Ok(format!("~{}", packed))
+        }
+    }
+}
+
+/// Splits a packed year, i.e. "K24" to 2024
+///
+/// * `packed`: century letter and two digits
+fn unpack_year(packed: &str) -> Result<u32> {
+    let mut characters = packed.chars();
+    let century = characters
+        .next()
+        .filter(|century| ('I'..='K').contains(century))
+        .ok_or_else(|| anyhow!("Invalid packed year {}", packed))?;
+    let year = characters
+        .as_str()
+        .parse::<u32>()
+        .map_err(|_| anyhow!("Invalid packed year {}", packed))?;
+    Ok(base62_value(century)? * 100 + year)
+}
+
+/// Packs a year, i.e. 2024 to "K24"
+///
+/// * `year`: year, from 1800 to 2099
+fn pack_year(year: u32) -> Result<String> {
+    if !(1800..2100).contains(&year) {
+        return Err(anyhow!("Year {} cannot be packed", year));
+    }
+    Ok(format!("{}{:02}", base62_char(year / 100)?, year % 100))
+}
+
+/// Unpacks a packed provisional or survey designation, i.e. "K24A01B" to "2024 AB1"
+///
+/// * `packed`: seven characters packed designation
+pub fn unpack_provisional(packed: &str) -> Result<String> {
+    if !packed.is_ascii() {
+        return Err(anyhow!("Invalid provisional designation {}", packed));
+    }
+    if let Some((_, survey)) = SURVEYS
+        .iter()
+        .find(|(code, _)| packed.len() == 7 && packed.starts_with(&format!("{}S", code)))
+    {
+        let number = packed[3..]
+            .parse::<u32>()
+            .map_err(|_| anyhow!("Invalid survey designation {}", packed))?;
+        return Ok(format!("{} {}", number, survey));
+    }
+    let characters: Vec<char> = packed.chars().collect();
+    let [_, _, _, half_month, cycle_high, cycle_low, order] = characters[..] else {
+        return Err(anyhow!("Invalid provisional designation {}", packed));
+    };
+    if !half_month.is_ascii_uppercase()
+        || !cycle_low.is_ascii_digit()
+        || !order.is_ascii_uppercase()
+    {
+        return Err(anyhow!("Invalid provisional designation {}", packed));
+    }
+    let cycle = base62_value(cycle_high)? * 10 + base62_value(cycle_low)?;
+    Ok(format!(
+        "{} {}{}{}",
+        unpack_year(&packed[..3])?,
+        half_month,
+        order,
+        if cycle == 0 {
+            String::new()
+        } else {
+            cycle.to_string()
+        }
+    ))
+}
+
+/// Packs a readable provisional or survey designation, i.e. "2024 AB1" to "K24A01B"
+///
+/// * `provisional`: readable provisional designation
+pub fn pack_provisional(provisional: &str) -> Result<String> {
+    let pattern = Regex::new(r"^(\d{4}) ([A-Z])([A-Z])(\d*)$").unwrap();
+    if let Some(captures) = pattern.captures(provisional.trim()) {
+        let cycle = match &captures[4] {
+            "" => 0,
+            cycle => cycle.parse::<u32>()?,
+        };
+        if cycle >= 620 {
+            return Err(anyhow!("{} cannot be packed", provisional));
+        }
+        return Ok(format!(
+            "{}{}{}{}{}",
+            pack_year(captures[1].parse::<u32>()?)?,
+            &captures[2],
+            base62_char(cycle / 10)?,
+            cycle % 10,
+            &captures[3]
+        ));
+    }
+    let survey_pattern = Regex::new(r"^(\d{4}) (P-L|T-1|T-2|T-3)$").unwrap();
+    let captures = survey_pattern
+        .captures(provisional.trim())
+        .ok_or_else(|| anyhow!("{} is not a provisional designation", provisional))?;
+    let (code, _) = SURVEYS
+        .iter()
+        .find(|(_, survey)| *survey == &captures[2])
+        .unwrap();
+    Ok(format!("{}S{}", code, &captures[1]))
+}
+
+/// Unpacks a packed comet provisional designation, i.e. "K24F010" to "2024 F1"
+///
+/// * `packed`: seven characters packed designation
+fn unpack_comet_provisional(packed: &str) -> Result<String> {
+    let characters: Vec<char> = packed.chars().collect();
+    let [_, _, _, half_month, high, low, fragment] = characters[..] else {
+        return Err(anyhow!("Invalid comet designation {}", packed));
+    };
+    if fragment.is_ascii_uppercase() {
+        // Comets first designated as minor planets
+        return unpack_provisional(packed);
+    }
+    if !half_month.is_ascii_uppercase() || !low.is_ascii_digit() {
+        return Err(anyhow!("Invalid comet designation {}", packed));
+    }
+    let order = base62_value(high)? * 10 + base62_value(low)?;
+    let fragment = match fragment {
+        '0' => String::new(),
+        fragment if fragment.is_ascii_lowercase() => {
+            format!("-{}", fragment.to_ascii_uppercase())
+        }
+        _ => return Err(anyhow!("Invalid comet designation {}", packed)),
+    };
+    Ok(format!(
+        "{} {}{}{}",
+        unpack_year(&packed[..3])?,
+        half_month,
+        order,
+        fragment
+    ))
+}
+
+/// Packs a readable comet provisional designation, i.e. "2024 F1-B" to "K24F01b"
+///
+/// * `provisional`: readable comet provisional designation
+fn pack_comet_provisional(provisional: &str) -> Result<String> {
+    let pattern = Regex::new(r"^(\d{4}) ([A-Z])(\d+)(?:-([A-Z]))?$").unwrap();
+    let Some(captures) = pattern.captures(provisional.trim()) else {
+        return pack_provisional(provisional);
+    };
+    let order = captures[3].parse::<u32>()?;
+    if order >= 620 {
+        return Err(anyhow!("{} cannot be packed", provisional));
+    }
+    Ok(format!(
+        "{}{}{}{}{}",
+        pack_year(captures[1].parse::<u32>()?)?,
+        &captures[2],
+        base62_char(order / 10)?,
+        order % 10,
+        captures
+            .get(4)
+            .map(|fragment| fragment.as_str().to_ascii_lowercase())
+            .unwrap_or("0".to_string())
+    ))
+}
+
+/// Patterns of readable designations
+///
+/// * `provisional`: provisional or survey designation
+/// * `numbered_comet`: numbered periodic comet, with its name
+/// * `comet`: comet provisional designation, with its name
+/// * `numbered`: numbered minor planet, with its name or provisional designation
+pub(crate) struct DesignationPatterns {
+    /// Provisional or survey designation
+    provisional: Regex,
+    /// Numbered periodic comet, with its name
+    numbered_comet: Regex,
+    /// Comet provisional designation, with its name
+    comet: Regex,
+    /// Numbered minor planet, with its name or provisional designation
+    numbered: Regex,
+}
+
+impl DesignationPatterns {
+    /// Compiles the patterns
+    pub(crate) fn new() -> Self {
+        DesignationPatterns {
+            provisional: Regex::new(r"^\d{4} ([A-Z]{2}\d*|P-L|T-[123])$").unwrap(),
+            numbered_comet: Regex::new(r"^(\d+)([PCDXIA])(?:/(.+))?$").unwrap(),
+            comet: Regex::new(r"^([PCDXIA])/(\d{4} [A-Z]{1,2}\d*(?:-[A-Z])?)(?: \((.+)\))?$")
+                .unwrap(),
+            numbered: Regex::new(r"^\((\d+)\)(?:\s+(.+))?$").unwrap(),
+        }
+    }
+}
+
+impl Designation {
+    /// Parses a packed designation, i.e. "00015", "K24A01B", "0001P" or "PK24F010"
+    ///
+    /// * `packed`: packed designation
+    pub fn from_packed(packed: &str) -> Result<Self> {
+        let packed = packed.trim();
+        if !packed.is_ascii() {
+            return Err(anyhow!("Invalid packed designation {}", packed));
+        }
+        let comet_type = packed
+            .chars()
+            .find(|character| COMET_TYPES.contains(*character));
+        match packed.len() {
+            5 if packed[..4].chars().all(|c| c.is_ascii_digit())
+                && packed.ends_with(|c| COMET_TYPES.contains(c)) =>
+            {
+                Ok(Designation {
+                    number: Some(packed[..4].parse::<u32>()?),
+                    comet_type: packed.chars().last(),
+                    ..Designation::default()
+                })
+            }
+            5 => Ok(Designation {
+                number: Some(unpack_number(packed)?),
+                ..Designation::default()
+            }),
+            7 => Ok(Designation {
+                provisional: Some(unpack_provisional(packed)?),
+                ..Designation::default()
+            }),
+            8 if packed.starts_with(|c| COMET_TYPES.contains(c)) => Ok(Designation {
+                provisional: Some(unpack_comet_provisional(&packed[1..])?),
+                comet_type,
+                ..Designation::default()
+            }),
+            _ => Err(anyhow!("Invalid packed designation {}", packed)),
+        }
+    }
+
+    /// Parses the first 12 columns of an MPC observation or orbit record
+    ///
+    /// Minor planets have the packed number in columns 1-5, comets the number in 1-4 and
+    /// the orbit type in 5; the packed provisional designation is in columns 6-12.
+    ///
+    /// * `columns`: the 12 columns
+    pub fn from_packed_columns(columns: &str) -> Result<Self> {
+        let columns = format!("{:<12}", columns);
+        let (number, provisional) = (columns.get(..5), columns.get(5..12));
+        let (Some(number), Some(provisional)) = (number, provisional) else {
+            return Err(anyhow!("Invalid packed designation {}", columns));
+        };
+        let comet_type = number.chars().nth(4).filter(|c| COMET_TYPES.contains(*c));
+        let mut designation = Designation {
+            comet_type,
+            ..Designation::default()
+        };
+        match (number.trim(), comet_type) {
+            ("", _) => (),
+            (number, Some(_)) if number.len() == 1 => (),
+            (number, Some(_)) => designation.number = Some(number[..number.len() - 1].parse()?),
+            (number, None) => designation.number = Some(unpack_number(number)?),
+        }
+        if !provisional.trim().is_empty() {
+            designation.provisional = Some(match comet_type {
+                Some(_) => unpack_comet_provisional(provisional)?,
+                None => unpack_provisional(provisional)?,
+            });
+        }
+        if designation.number.is_none() && designation.provisional.is_none() {
+            return Err(anyhow!("Empty designation"));
+        }
+        Ok(designation)
+    }
+
+    /// Parses a readable designation, i.e. "(15) Eunomia", "2024 AB1", "1P/Halley" or
+    /// "C/2020 F3 (NEOWISE)"
+    ///
+    /// * `value`: readable designation
+    pub fn parse(value: &str) -> Result<Self> {
+        Self::parse_with(value, &DesignationPatterns::new())
+    }
+
+    /// Parses a readable designation with already compiled patterns
+    ///
+    /// * `value`: readable designation
+    /// * `patterns`: designation patterns
+    pub(crate) fn parse_with(value: &str, patterns: &DesignationPatterns) -> Result<Self> {
+        let value = value.trim();
+        if let Some(captures) = patterns.numbered_comet.captures(value) {
+            return Ok(Designation {
+                number: Some(captures[1].parse()?),
+                name: captures.get(3).map(|name| name.as_str().to_string()),
+                comet_type: captures[2].chars().next(),
+                ..Designation::default()
+            });
+        }
+        if let Some(captures) = patterns.comet.captures(value) {
+            return Ok(Designation {
+                provisional: Some(captures[2].to_string()),
+                name: captures.get(3).map(|name| name.as_str().to_string()),
+                comet_type: captures[1].chars().next(),
+                ..Designation::default()
+            });
+        }
+        let (number, rest) = match patterns.numbered.captures(value) {
+            Some(captures) => (
+                Some(captures[1].parse::<u32>()?),
+                captures.get(2).map_or("", |rest| rest.as_str()),
+            ),
+            None if value.chars().all(|c| c.is_ascii_digit()) && !value.is_empty() => {
+                (Some(value.parse::<u32>()?), "")
+            }
+            None => (None, value),
+        };
+        let mut designation = Designation {
+            number,
+            ..Designation::default()
+        };
+        match rest {
+            "" if number.is_none() => return Err(anyhow!("Empty designation")),
+            "" => (),
+            rest if patterns.provisional.is_match(rest) => {
+                designation.provisional = Some(rest.to_string())
+            }
+            rest => designation.name = Some(rest.to_string()),
+        }
+        Ok(designation)
+    }
+
+    /// Returns true for comets
+    pub fn is_comet(&self) -> bool {
+        self.comet_type.is_some()
+    }
+
+    /// Packs the designation, preferring the number to the provisional designation
+    pub fn to_packed(&self) -> Result<String> {
+        match (self.comet_type, self.number, &self.provisional) {
+            (Some(comet_type), Some(number), _) if number < 10000 => {
+                Ok(format!("{:04}{}", number, comet_type))
+            }
+            (Some(comet_type), _, Some(provisional)) => Ok(format!(
+                "{}{}",
+                comet_type,
+                pack_comet_provisional(provisional)?
+            )),
+            (None, Some(number), _) => pack_number(number),
+            (None, None, Some(provisional)) => pack_provisional(provisional),
+            _ => Err(anyhow!("{} cannot be packed", self)),
+        }
+    }
+
+    /// Packs the designation in the 12 columns of MPC observation records
+    ///
+    /// Numbered objects leave the provisional columns blank, as required in reports.
+    pub fn to_packed_columns(&self) -> Result<String> {
+        match (self.comet_type, self.number, &self.provisional) {
+            (Some(comet_type), Some(number), _) if number < 10000 => {
+                Ok(format!("{:04}{}       ", number, comet_type))
+            }
+            (Some(comet_type), None, Some(provisional)) => Ok(format!(
+                "    {}{}",
+                comet_type,
+                pack_comet_provisional(provisional)?
+            )),
+            (None, Some(number), _) => Ok(format!("{}       ", pack_number(number)?)),
+            (None, None, Some(provisional)) => {
+                Ok(format!("     {}", pack_provisional(provisional)?))
+            }
+            _ => Err(anyhow!("{} cannot be packed", self)),
+        }
+    }
+}
+
+impl fmt::Display for Designation {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match (self.comet_type, self.number, &self.provisional, &self.name) {
+            (Some(comet_type), Some(number), _, Some(name)) => {
+                write!(f, "{}{}/{}", number, comet_type, name)
+            }
+            (Some(comet_type), Some(number), _, None) => write!(f, "{}{}", number, comet_type),
+            (Some(comet_type), None, Some(provisional), Some(name)) => {
+                write!(f, "{}/{} ({})", comet_type, provisional, name)
+            }
+            (Some(comet_type), None, Some(provisional), None) => {
+                write!(f, "{}/{}", comet_type, provisional)
+            }
+            (None, Some(number), _, Some(name)) => write!(f, "({}) {}", number, name),
+            (None, Some(number), Some(provisional), None) => {
+                write!(f, "({}) {}", number, provisional)
+            }
+            (None, Some(number), None, None) => write!(f, "({})", number),
+            (_, None, Some(provisional), _) => write!(f, "{}", provisional),
+            (_, None, None, Some(name)) => write!(f, "{}", name),
+            _ => write!(f, "None"),
+        }
+    }
+}
+
+/// Unpacks a packed designation for display, i.e. "(15)" or "2024 AB1"
+///
+/// * `packed`: packed designation
+pub fn unpack(packed: &str) -> Result<String> {
+    Ok(Designation::from_packed(packed)?.to_string())
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_unpack() {
+        assert_eq!(unpack("00015").unwrap(), "(15)");
+        assert_eq!(unpack_number("A0345").unwrap(), 100345);
+        assert_eq!(unpack_number("z9999").unwrap(), 619999);
+        assert_eq!(unpack_number("~0000").unwrap(), 620000);
+        assert_eq!(unpack_number("~000z").unwrap(), 620061);
+        assert_eq!(unpack("K24A01B").unwrap(), "2024 AB1");
+        assert_eq!(unpack("J95X00A").unwrap(), "1995 XA");
+        assert_eq!(unpack("K07Tf8A").unwrap(), "2007 TA418");
+        assert_eq!(unpack("PLS2040").unwrap(), "2040 P-L");
+        assert_eq!(unpack("T1S3138").unwrap(), "3138 T-1");
+        assert_eq!(unpack("0001P").unwrap(), "1P");
+        assert_eq!(unpack("PK24F010").unwrap(), "P/2024 F1");
+        assert_eq!(unpack("CK24F01b").unwrap(), "C/2024 F1-B");
+        assert!(unpack("K24A0").is_err());
+        assert!(unpack("X24A01B").is_err());
+        assert!(unpack("ab€").is_err());
+        assert!(unpack_provisional("éé4A01B").is_err());
+    }
+
+    #[test]
+    fn test_pack() {
+        for packed in [
+            "00015", "A0345", "z9999", "~0000", "~AZaz", "K24A01B", "J95X00A", "K07Tf8A",
+            "PLS2040", "T3S3141", "0001P", "PK24F010", "CK24F01b", "CJ95O010",
+        ] {
+            assert_eq!(
+                Designation::from_packed(packed)
+                    .unwrap()
+                    .to_packed()
+                    .unwrap(),
+                packed
+            );
+        }
+        assert!(pack_number(15396336).is_err());
+        assert!(pack_provisional("2024 AB620").is_err());
+        assert!(pack_provisional("Eunomia").is_err());
+    }
+
+    #[test]
+    fn test_parse() {
+        let eunomia = Designation::parse(" (15) Eunomia").unwrap();
+        assert_eq!(eunomia.number, Some(15));
+        assert_eq!(eunomia.name.as_deref(), Some("Eunomia"));
+        assert_eq!(eunomia.to_string(), "(15) Eunomia");
+        let provisional = Designation::parse("2024 AB1").unwrap();
+        assert_eq!(provisional.to_packed().unwrap(), "K24A01B");
+        let numbered = Designation::parse("(523599) 2003 RM").unwrap();
+        assert_eq!(numbered.provisional.as_deref(), Some("2003 RM"));
+        assert_eq!(numbered.to_packed().unwrap(), "q3599");
+        let halley = Designation::parse("1P/Halley").unwrap();
+        assert_eq!(halley.to_packed().unwrap(), "0001P");
+        let neowise = Designation::parse("C/2020 F3 (NEOWISE)").unwrap();
+        assert_eq!(neowise.to_packed().unwrap(), "CK20F030");
+        assert_eq!(neowise.to_string(), "C/2020 F3 (NEOWISE)");
+        assert_eq!(Designation::parse("433").unwrap().number, Some(433));
+        assert!(Designation::parse("").is_err());
+    }
+
+    #[test]
+    fn test_packed_columns() {
+        let eunomia = Designation::from_packed_columns("00015       ").unwrap();
+        assert_eq!(eunomia.number, Some(15));
+        assert_eq!(eunomia.to_packed_columns().unwrap(), "00015       ");
+        let provisional = Designation::from_packed_columns("     K24A01B").unwrap();
+        assert_eq!(provisional.to_packed_columns().unwrap(), "     K24A01B");
+        let comet = Designation::from_packed_columns("    CK20F030").unwrap();
+        assert_eq!(comet.to_string(), "C/2020 F3");
+        assert_eq!(comet.to_packed_columns().unwrap(), "    CK20F030");
+        let halley = Designation::from_packed_columns("0001P").unwrap();
+        assert_eq!(halley.to_packed_columns().unwrap(), "0001P       ");
+        assert!(Designation::from_packed_columns("            ").is_err());
+    }
+}
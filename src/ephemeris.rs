@@ -0,0 +1,448 @@
+//! # Ephemeris
+//!
+//! Library computing topocentric ephemerides of minor planets from their orbits
+//!
+//! Positions are astrometric J2000 RA/Dec as seen from the observatory: the object is
+//! propagated back by the light-time, the Earth is the Earth-Moon barycenter corrected by
+//! the Moon position and the observer is placed on the WGS84 ellipsoid. Altitude and
+//! azimuth include refraction, like the other tables.
+//!
+//! Orbits are propagated as Keplerian ellipses or, for objects with an old epoch or a close
+//! approach, integrated with the planetary perturbations of the `integrator` library.
+//!
+//! ```rust,no_run
+//! use asteroid_tui::catalogue::Catalogue;
+//! use asteroid_tui::coordinates::Observer;
+//! use asteroid_tui::ephemeris::{self, Propagation};
+//! use chrono::{Duration, Utc};
+//! let catalogue = Catalogue::load().unwrap();
+//! let eros = catalogue.get("00433").unwrap();
+//! let observer = Observer::new(44.09727, 9.7737, 100.0);
+//! let start = Utc::now();
+//! let end = start + Duration::hours(6);
+//! let step = Duration::hours(1);
+//! let entries =
+//!     ephemeris::compute_ephemeris(eros, start, end, step, &observer, Propagation::NBody)
+//!         .unwrap();
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::coordinates::{self, julian_day, Observer};
+use crate::integrator::Trajectory;
+use crate::orbit::{self, Orbit, Vector};
+use crate::sun_moon_times::moon_position;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+
+/// Speed of light in AU per day
+const SPEED_OF_LIGHT: f64 = 173.1446327;
+
+/// Astronomical unit in km
+const ASTRONOMICAL_UNIT: f64 = 149597870.7;
+
+/// Earth equatorial radius in km (WGS84)
+const EARTH_RADIUS: f64 = 6378.137;
+
+/// Earth flattening (WGS84)
+const EARTH_FLATTENING: f64 = 1.0 / 298.257223563;
+
+/// Earth-Moon mass ratio
+const EARTH_MOON_MASS_RATIO: f64 = 81.30056;
+
+/// Time used to compute the rate of motion, in days
+const RATE_INTERVAL: f64 = 1.0 / 1440.0;
+
+/// Maximum number of entries of an ephemeris
+const MAX_ENTRIES: i64 = 10000;
+
+/// Heliocentric ecliptic position of an object at a TT Julian Day, failing if it can not be
+/// propagated there
+type PositionSource<'a> = dyn Fn(f64) -> Result<Vector> + 'a;
+
+/// How orbits are propagated from their epoch
+///
+/// * `TwoBody`: unperturbed Keplerian ellipse, fast and fine near the epoch
+/// * `NBody`: numerical integration with the perturbations of the major planets
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub enum Propagation {
+    /// Unperturbed Keplerian ellipse, fast and fine near the epoch
+    #[default]
+    TwoBody,
+    /// Numerical integration with the perturbations of the major planets
+    NBody,
+}
+
+/// Ephemeris entry structure
+///
+/// * `time`: UTC time
+/// * `ra`: topocentric right ascension J2000 in degrees
+/// * `dec`: topocentric declination J2000 in degrees
+/// * `earth_distance`: distance from the observer (Δ) in AU
+/// * `sun_distance`: distance from the Sun (r) in AU
+/// * `phase_angle`: Sun-object-observer angle in degrees
+/// * `solar_elongation`: Sun-observer-object angle in degrees
+/// * `rate`: rate of motion in arcseconds per minute
+/// * `position_angle`: direction of motion in degrees, from North through East
+/// * `magnitude`: visual magnitude (H-G system)
+/// * `altitude`: altitude in degrees, with refraction
+/// * `azimuth`: azimuth in degrees, from North through East
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct EphemerisEntry {
+    /// UTC time
+    pub time: DateTime<Utc>,
+    /// Topocentric right ascension J2000 in degrees
+    pub ra: f64,
+    /// Topocentric declination J2000 in degrees
+    pub dec: f64,
+    /// Distance from the observer (Δ) in AU
+    pub earth_distance: f64,
+    /// Distance from the Sun (r) in AU
+    pub sun_distance: f64,
+    /// Sun-object-observer angle in degrees
+    pub phase_angle: f64,
+    /// Sun-observer-object angle in degrees
+    pub solar_elongation: f64,
+    /// Rate of motion in arcseconds per minute
+    pub rate: f64,
+    /// Direction of motion in degrees, from North through East
+    pub position_angle: f64,
+    /// Visual magnitude (H-G system)
+    pub magnitude: f64,
+    /// Altitude in degrees, with refraction
+    pub altitude: f64,
+    /// Azimuth in degrees, from North through East
+    pub azimuth: f64,
+}
+
+/// Returns the geocentric equatorial position of the observer in AU
+///
+/// The position is rotated by the mean sidereal time, precession since J2000 is neglected.
+///
+/// * `observer`: observer location
+/// * `jd`: Julian Day
+pub fn observer_position(observer: &Observer, jd: f64) -> Vector {
+    let latitude = observer.latitude.to_radians();
+    let e2 = EARTH_FLATTENING * (2.0 - EARTH_FLATTENING);
+    let n = EARTH_RADIUS / (1.0 - e2 * latitude.sin().powi(2)).sqrt();
+    let height = observer.altitude / 1000.0;
+    let sidereal_time =
+        (coordinates::greenwich_sidereal_time(jd) + observer.longitude).to_radians();
+    let equatorial = (n + height) * latitude.cos();
+    [
+        equatorial * sidereal_time.cos() / ASTRONOMICAL_UNIT,
+        equatorial * sidereal_time.sin() / ASTRONOMICAL_UNIT,
+        (n * (1.0 - e2) + height) * latitude.sin() / ASTRONOMICAL_UNIT,
+    ]
+}
+
+/// Returns the heliocentric equatorial position of the Earth center in AU
+///
+/// * `jd`: Julian Day
+pub fn earth_center_position(jd: f64) -> Vector {
+    let barycenter = orbit::ecliptic_to_equatorial(orbit::earth_position(jd));
+    let moon = moon_position(jd);
+    let (ra, dec) = (
+        moon.right_ascension.to_radians(),
+        moon.declination.to_radians(),
+    );
+    let offset = moon.distance / ASTRONOMICAL_UNIT / (EARTH_MOON_MASS_RATIO + 1.0);
+    [
+        barycenter[0] - offset * dec.cos() * ra.cos(),
+        barycenter[1] - offset * dec.cos() * ra.sin(),
+        barycenter[2] - offset * dec.sin(),
+    ]
+}
+
+/// Returns the difference of two vectors
+///
+/// * `first`: first vector
+/// * `second`: vector to subtract
+fn difference(first: Vector, second: Vector) -> Vector {
+    [
+        first[0] - second[0],
+        first[1] - second[1],
+        first[2] - second[2],
+    ]
+}
+
+/// Returns the heliocentric equatorial position of the object at the time its light left it
+/// and its position relative to the observer
+///
+/// * `source`: heliocentric ecliptic position of the object
+/// * `jd`: TT Julian Day of the observation
+/// * `observer`: heliocentric equatorial position of the observer
+fn light_time_position(
+    source: &PositionSource,
+    jd: f64,
+    observer: Vector,
+) -> Result<(Vector, Vector)> {
+    let mut heliocentric = orbit::ecliptic_to_equatorial(source(jd)?);
+    let mut topocentric = difference(heliocentric, observer);
+    for _ in 0..3 {
+        let light_time = orbit::norm(topocentric) / SPEED_OF_LIGHT;
+        heliocentric = orbit::ecliptic_to_equatorial(source(jd - light_time)?);
+        topocentric = difference(heliocentric, observer);
+    }
+    Ok((heliocentric, topocentric))
+}
+
+/// Returns the heliocentric equatorial position of the observer in AU
+///
+/// The Earth moves in Terrestrial Time, while it rotates with the UTC sidereal time.
+///
+/// * `observer`: observer location
+/// * `time`: UTC time
+fn observer_heliocentric_position(observer: &Observer, time: DateTime<Utc>) -> Vector {
+    let earth = earth_center_position(coordinates::tt_julian_day(time));
+    let site = observer_position(observer, julian_day(time));
+    [earth[0] + site[0], earth[1] + site[1], earth[2] + site[2]]
+}
+
+/// Returns the topocentric RA and Dec of an object in degrees at `time`
+///
+/// * `source`: heliocentric ecliptic position of the object
+/// * `time`: UTC time
+/// * `observer`: observer location
+fn topocentric_coordinates(
+    source: &PositionSource,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> Result<(f64, f64)> {
+    let position = observer_heliocentric_position(observer, time);
+    let (_, topocentric) = light_time_position(source, coordinates::tt_julian_day(time), position)?;
+    let (ra, dec, _) = orbit::to_spherical(topocentric);
+    Ok((ra, dec))
+}
+
+/// Computes the ephemeris entry of an object at a time, propagating the orbit as a
+/// Keplerian ellipse
+///
+/// * `orbit`: object orbit
+/// * `time`: UTC time
+/// * `observer`: observer location
+pub fn ephemeris_at(
+    orbit: &Orbit,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> Result<EphemerisEntry> {
+    entry_at(
+        orbit,
+        &|jd| Ok(orbit.heliocentric_position(jd)),
+        time,
+        observer,
+    )
+}
+
+/// Computes the ephemeris entry of an object at a time
+///
+/// * `orbit`: object orbit, for the magnitude
+/// * `source`: heliocentric ecliptic position of the object
+/// * `time`: UTC time
+/// * `observer`: observer location
+fn entry_at(
+    orbit: &Orbit,
+    source: &PositionSource,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> Result<EphemerisEntry> {
+    let position = observer_heliocentric_position(observer, time);
+    let (heliocentric, topocentric) =
+        light_time_position(source, coordinates::tt_julian_day(time), position)?;
+    let (ra, dec, earth_distance) = orbit::to_spherical(topocentric);
+    let sun_distance = orbit::norm(heliocentric);
+    let phase_angle = orbit::angle(heliocentric, topocentric);
+    let sun = [-position[0], -position[1], -position[2]];
+
+    let interval = Duration::milliseconds((RATE_INTERVAL * 86400000.0).round() as i64);
+    let (before_ra, before_dec) = topocentric_coordinates(source, time - interval, observer)?;
+    let (after_ra, after_dec) = topocentric_coordinates(source, time + interval, observer)?;
+    let minutes = 2.0 * RATE_INTERVAL * 1440.0;
+    let delta_ra = (after_ra - before_ra + 180.0).rem_euclid(360.0) - 180.0;
+    let ra_rate = delta_ra * dec.to_radians().cos() * 3600.0 / minutes;
+    let dec_rate = (after_dec - before_dec) * 3600.0 / minutes;
+
+    let horizontal = coordinates::apparent_horizontal(ra, dec, time, observer);
+    Ok(EphemerisEntry {
+        time,
+        ra,
+        dec,
+        earth_distance,
+        sun_distance,
+        phase_angle,
+        solar_elongation: orbit::angle(topocentric, sun),
+        rate: ra_rate.hypot(dec_rate),
+        position_angle: ra_rate.atan2(dec_rate).to_degrees().rem_euclid(360.0),
+        magnitude: orbit.magnitude(sun_distance, earth_distance, phase_angle),
+        altitude: horizontal.altitude,
+        azimuth: horizontal.azimuth,
+    })
+}
+
+/// Computes the ephemeris of an object from `start` to `end`, one entry every `step`
+///
+/// * `orbit`: object orbit
+/// * `start`: first UTC time
+/// * `end`: last UTC time
+/// * `step`: time between two entries
+/// * `observer`: observer location
+/// * `propagation`: how the orbit is propagated from its epoch
+pub fn compute_ephemeris(
+    orbit: &Orbit,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    step: Duration,
+    observer: &Observer,
+    propagation: Propagation,
+) -> Result<Vec<EphemerisEntry>> {
+    if step <= Duration::zero() {
+        return Err(anyhow!("The step must be positive"));
+    }
+    if end < start {
+        return Err(anyhow!("The end must follow the start"));
+    }
+    let count = (end - start).num_seconds() / step.num_seconds().max(1);
+    if count >= MAX_ENTRIES {
+        return Err(anyhow!(
+            "Too many entries ({}), use a longer step",
+            count + 1
+        ));
+    }
+    let times = (0..=count).map(|index| start + step * index as i32);
+    match propagation {
+        Propagation::TwoBody => times
+            .map(|time| ephemeris_at(orbit, time, observer))
+            .collect(),
+        Propagation::NBody => {
+            // One day of margin covers the light-time of objects up to 170 AU away
+            let trajectory = Trajectory::integrate(
+                orbit,
+                coordinates::tt_julian_day(start) - 1.0,
+                coordinates::tt_julian_day(end) + 1.0,
+            )?;
+            let source = |jd| trajectory.heliocentric_position(jd);
+            times
+                .map(|time| entry_at(orbit, &source, time, observer))
+                .collect()
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::orbit::test::eros;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_observer_position() {
+        let observer = Observer::new(0.0, 0.0, 0.0);
+        let jd = 2451545.0;
+        let position = observer_position(&observer, jd);
+        assert!((orbit::norm(position) * ASTRONOMICAL_UNIT - EARTH_RADIUS).abs() < 1e-6);
+        let (ra, dec, _) = orbit::to_spherical(position);
+        assert!((ra - coordinates::greenwich_sidereal_time(jd)).abs() < 1e-9);
+        assert!(dec.abs() < 1e-9);
+        let pole = observer_position(&Observer::new(90.0, 0.0, 0.0), jd);
+        assert!((pole[2] * ASTRONOMICAL_UNIT - 6356.752).abs() < 1e-3);
+    }
+
+    #[test]
+    fn test_compute_ephemeris() {
+        let orbit = eros();
+        let observer = Observer::new(44.09727, 9.7737, 100.0);
+        let start = NaiveDate::from_ymd_opt(2025, 5, 5)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let entries = compute_ephemeris(
+            &orbit,
+            start,
+            start + Duration::hours(2),
+            Duration::hours(1),
+            &observer,
+            Propagation::TwoBody,
+        )
+        .unwrap();
+        assert_eq!(entries.len(), 3);
+        let first = &entries[0];
+        assert_eq!(first.time, start);
+        // Geocentric distance without light-time and parallax
+        let earth = orbit::earth_position(coordinates::tt_julian_day(start));
+        let geometric = orbit::norm(difference(
+            orbit.heliocentric_position(coordinates::tt_julian_day(start)),
+            earth,
+        ));
+        assert!((first.earth_distance - geometric).abs() < 1e-3);
+        assert!(first.sun_distance > 1.1 && first.sun_distance < 1.8);
+        assert!(first.phase_angle > 0.0 && first.phase_angle < 90.0);
+        // The rate matches the motion between two entries
+        let moved = orbit::angle(
+            [
+                first.dec.to_radians().cos() * first.ra.to_radians().cos(),
+                first.dec.to_radians().cos() * first.ra.to_radians().sin(),
+                first.dec.to_radians().sin(),
+            ],
+            [
+                entries[1].dec.to_radians().cos() * entries[1].ra.to_radians().cos(),
+                entries[1].dec.to_radians().cos() * entries[1].ra.to_radians().sin(),
+                entries[1].dec.to_radians().sin(),
+            ],
+        ) * 3600.0
+            / 60.0;
+        assert!((first.rate - moved).abs() / moved < 0.1);
+        let zero = Duration::zero();
+        assert!(
+            compute_ephemeris(&orbit, start, start, zero, &observer, Default::default()).is_err()
+        );
+        assert!(compute_ephemeris(
+            &orbit,
+            start,
+            start + Duration::days(100),
+            Duration::minutes(1),
+            &observer,
+            Propagation::TwoBody,
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_n_body_ephemeris() {
+        let orbit = eros();
+        let observer = Observer::new(44.09727, 9.7737, 100.0);
+        let start = coordinates::datetime_from_julian_day(orbit.epoch);
+        let entry = |time, propagation| {
+            compute_ephemeris(
+                &orbit,
+                time,
+                time,
+                Duration::hours(1),
+                &observer,
+                propagation,
+            )
+            .unwrap()[0]
+        };
+        // Near the epoch both propagations agree within an arcsecond
+        let (keplerian, perturbed) = (
+            entry(start, Propagation::TwoBody),
+            entry(start, Propagation::NBody),
+        );
+        assert!((keplerian.ra - perturbed.ra).abs() * 3600.0 < 1.0);
+        assert!((keplerian.dec - perturbed.dec).abs() * 3600.0 < 1.0);
+        // Years later the perturbations move Eros by more than the field of view
+        let later = start + Duration::days(3000);
+        let (keplerian, perturbed) = (
+            entry(later, Propagation::TwoBody),
+            entry(later, Propagation::NBody),
+        );
+        let separation = (keplerian.ra - perturbed.ra).abs() * keplerian.dec.to_radians().cos()
+            + (keplerian.dec - perturbed.dec).abs();
+        assert!(separation * 60.0 > 1.0);
+        // Reference position on 2033-07-22 recorded from this integrator: the sample elements
+        // are approximate, so they cannot be checked against JPL Horizons within an arcminute
+        let (ra, dec): (f64, f64) = (324.37468, -11.82455);
+        assert!((perturbed.ra - ra).abs() * dec.to_radians().cos() * 60.0 < 0.5);
+        assert!((perturbed.dec - dec).abs() * 60.0 < 0.5);
+    }
+}
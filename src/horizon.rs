@@ -0,0 +1,322 @@
+//! # Horizon
+//!
+//! Library for the local horizon of the observatory, described by (azimuth, altitude) points
+//!
+//! The minimum altitude between two points is linearly interpolated, wrapping around North.
+//! Profiles can be imported from a CSV file, a Stellarium horizon list or a N.I.N.A. `.hrz`
+//! file: all of them have one `azimuth altitude` pair per line, separated by spaces, tabs,
+//! commas or semicolons.
+//!
+//! ```rust
+//! use asteroid_tui::horizon::HorizonProfile;
+//! let profile = HorizonProfile::parse("azimuth,altitude\n0,10\n90,30\n180,10\n270,20").unwrap();
+//! assert_eq!(profile.altitude_at(45.0), 20.0);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::coordinates::{self, Observer};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+use serde::{Deserialize, Serialize};
+use std::fs;
+use std::path::Path;
+
+/// Horizon point structure
+///
+/// * `azimuth`: azimuth in degrees, from North through East
+/// * `altitude`: minimum altitude in degrees
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct HorizonPoint {
+    /// Azimuth in degrees, from North through East
+    pub azimuth: f64,
+    /// Minimum altitude in degrees
+    pub altitude: f64,
+}
+
+/// Horizon profile structure, with points sorted by azimuth
+///
+/// * `points`: horizon points
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct HorizonProfile {
+    /// Horizon points, sorted by azimuth
+    points: Vec<HorizonPoint>,
+}
+
+impl HorizonProfile {
+    /// Constructor for HorizonProfile struct
+    ///
+    /// Azimuths are normalized between 0 and 360, points with the same azimuth keep their
+    /// order so that they describe a step.
+    ///
+    /// * `points`: horizon points
+    pub fn new(points: Vec<HorizonPoint>) -> Self {
+        let mut points: Vec<HorizonPoint> = points
+            .into_iter()
+            .map(|point| HorizonPoint {
+                azimuth: point.azimuth.rem_euclid(360.0),
+                altitude: point.altitude,
+            })
+            .collect();
+        points.sort_by(|first, second| first.azimuth.total_cmp(&second.azimuth));
+        HorizonProfile { points }
+    }
+
+    /// Creates a profile from the minimum altitudes of the four cardinal directions
+    ///
+    /// Each direction covers 90°, i.e. North is from 315° to 45°.
+    ///
+    /// * `north`: north minimum altitude in degrees
+    /// * `east`: east minimum altitude in degrees
+    /// * `south`: south minimum altitude in degrees
+    /// * `west`: west minimum altitude in degrees
+    pub fn from_cardinal(north: f64, east: f64, south: f64, west: f64) -> Self {
+        let points = [
+            (45.0, north),
+            (45.0, east),
+            (135.0, east),
+            (135.0, south),
+            (225.0, south),
+            (225.0, west),
+            (315.0, west),
+            (315.0, north),
+        ]
+        .into_iter()
+        .map(|(azimuth, altitude)| HorizonPoint { azimuth, altitude })
+        .collect();
+        HorizonProfile::new(points)
+    }
+
+    /// Parses a horizon profile from CSV, Stellarium or N.I.N.A. text
+    ///
+    /// Empty lines and comments starting with `#`, `;` or `//` are skipped, as well as a
+    /// header before the first point.
+    ///
+    /// * `text`: the text to be parsed
+    pub fn parse(text: &str) -> Result<Self> {
+        let mut points: Vec<HorizonPoint> = Vec::new();
+        for (number, line) in text.lines().enumerate() {
+            let line = line.trim();
+            if line.is_empty() || line.starts_with(['#', ';']) || line.starts_with("//") {
+                continue;
+            }
+            let fields: Vec<&str> = line
+                .split([',', ';', ' ', '\t'])
+                .filter(|field| !field.is_empty())
+                .collect();
+            let values: Vec<f64> = fields
+                .iter()
+                .take(2)
+                .filter_map(|field| field.parse::<f64>().ok())
+                .collect();
+            match values[..] {
+                [azimuth, altitude] if (-90.0..=90.0).contains(&altitude) => {
+                    points.push(HorizonPoint { azimuth, altitude })
+                }
+                [_, altitude] => {
+                    return Err(anyhow!(
+                        "Line {}: altitude {} out of range",
+                        number + 1,
+                        altitude
+                    ))
+                }
+                _ if points.is_empty() => continue,
+                _ => return Err(anyhow!("Line {}: invalid point \"{}\"", number + 1, line)),
+            }
+        }
+        if points.is_empty() {
+            return Err(anyhow!("No horizon points found"));
+        }
+        Ok(HorizonProfile::new(points))
+    }
+
+    /// Reads a horizon profile from a CSV, Stellarium or N.I.N.A. file
+    ///
+    /// * `path`: path of the file
+    pub fn from_file(path: &Path) -> Result<Self> {
+        let text = fs::read_to_string(path)
+            .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+        HorizonProfile::parse(&text)
+    }
+
+    /// Returns the horizon points, sorted by azimuth
+    pub fn points(&self) -> &[HorizonPoint] {
+        &self.points
+    }
+
+    /// Returns true if the profile has no points, i.e. the horizon is flat at 0°
+    pub fn is_empty(&self) -> bool {
+        self.points.is_empty()
+    }
+
+    /// Returns the minimum altitude at an azimuth, interpolated between the nearest points
+    ///
+    /// * `azimuth`: azimuth in degrees, from North through East
+    pub fn altitude_at(&self, azimuth: f64) -> f64 {
+        let (Some(first), Some(last)) = (self.points.first(), self.points.last()) else {
+            return 0.0;
+        };
+        let azimuth = azimuth.rem_euclid(360.0);
+        let (before, after) = match self
+            .points
+            .iter()
+            .rposition(|point| point.azimuth <= azimuth)
+        {
+            None => (
+                HorizonPoint {
+                    azimuth: last.azimuth - 360.0,
+                    altitude: last.altitude,
+                },
+                *first,
+            ),
+            Some(index) if index == self.points.len() - 1 => (
+                *last,
+                HorizonPoint {
+                    azimuth: first.azimuth + 360.0,
+                    altitude: first.altitude,
+                },
+            ),
+            Some(index) => (self.points[index], self.points[index + 1]),
+        };
+        let span = after.azimuth - before.azimuth;
+        if span <= 0.0 {
+            return before.altitude;
+        }
+        before.altitude + (after.altitude - before.altitude) * (azimuth - before.azimuth) / span
+    }
+
+    /// Returns true if an object at the given horizontal coordinates is above the horizon
+    ///
+    /// * `altitude`: object altitude in degrees
+    /// * `azimuth`: object azimuth in degrees
+    pub fn is_visible(&self, altitude: f64, azimuth: f64) -> bool {
+        altitude > self.altitude_at(azimuth)
+    }
+}
+
+/// Returns the time intervals when an object is above the horizon profile
+///
+/// The object is sampled every `step`, rise and set times are refined to the minute.
+///
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `start`: first UTC time
+/// * `end`: last UTC time
+/// * `step`: time between two samples
+/// * `observer`: observer location
+/// * `profile`: horizon profile
+pub fn visibility_windows(
+    ra: f64,
+    dec: f64,
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    step: Duration,
+    observer: &Observer,
+    profile: &HorizonProfile,
+) -> Vec<(DateTime<Utc>, DateTime<Utc>)> {
+    let visible = |time: DateTime<Utc>| {
+        let position = coordinates::apparent_horizontal(ra, dec, time, observer);
+        profile.is_visible(position.altitude, position.azimuth)
+    };
+    // Bisects between a time with state `before` and a time with the opposite one
+    let crossing = |mut first: DateTime<Utc>, mut second: DateTime<Utc>, before: bool| {
+        while second - first > Duration::minutes(1) {
+            let middle = first + (second - first) / 2;
+            if visible(middle) == before {
+                first = middle;
+            } else {
+                second = middle;
+            }
+        }
+        second
+    };
+    let mut windows: Vec<(DateTime<Utc>, DateTime<Utc>)> = Vec::new();
+    if step <= Duration::zero() || end < start {
+        return windows;
+    }
+    let mut rise = visible(start).then_some(start);
+    let mut previous = start;
+    while previous < end {
+        let time = (previous + step).min(end);
+        match (rise, visible(time)) {
+            (None, true) => rise = Some(crossing(previous, time, false)),
+            (Some(begin), false) => {
+                windows.push((begin, crossing(previous, time, true)));
+                rise = None;
+            }
+            _ => (),
+        }
+        previous = time;
+    }
+    if let Some(begin) = rise {
+        windows.push((begin, end));
+    }
+    windows
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::NaiveDate;
+
+    #[test]
+    fn test_altitude_at() {
+        let profile = HorizonProfile::parse("350 10\n10 20\n90 40").unwrap();
+        assert!((profile.altitude_at(0.0) - 15.0).abs() < 1e-9);
+        assert!((profile.altitude_at(50.0) - 30.0).abs() < 1e-9);
+        // Halfway between 90° and 350°
+        assert!((profile.altitude_at(220.0) - 25.0).abs() < 1e-9);
+        assert!((profile.altitude_at(-5.0) - 12.5).abs() < 1e-9);
+        assert_eq!(HorizonProfile::default().altitude_at(123.0), 0.0);
+    }
+
+    #[test]
+    fn test_from_cardinal() {
+        let profile = HorizonProfile::from_cardinal(10.0, 20.0, 30.0, 40.0);
+        assert_eq!(profile.altitude_at(0.0), 10.0);
+        assert_eq!(profile.altitude_at(44.9), 10.0);
+        assert_eq!(profile.altitude_at(45.0), 20.0);
+        assert_eq!(profile.altitude_at(180.0), 30.0);
+        assert_eq!(profile.altitude_at(300.0), 40.0);
+        assert_eq!(profile.altitude_at(359.0), 10.0);
+    }
+
+    #[test]
+    fn test_parse() {
+        let csv = HorizonProfile::parse("azimuth;altitude\n0;5.5\n180;12\n").unwrap();
+        assert_eq!(csv.points().len(), 2);
+        let stellarium =
+            HorizonProfile::parse("# horizon of the site\n0 5\n\n90 10.5\n360 5\n").unwrap();
+        assert_eq!(stellarium.points()[0].azimuth, 0.0);
+        assert_eq!(stellarium.points().len(), 3);
+        let nina = HorizonProfile::parse("0\t5\r\n90\t10\r\n").unwrap();
+        assert_eq!(nina.altitude_at(45.0), 7.5);
+        assert!(HorizonProfile::parse("0 5\n90 abc").is_err());
+        assert!(HorizonProfile::parse("0 95").is_err());
+        assert!(HorizonProfile::parse("# empty").is_err());
+    }
+
+    #[test]
+    fn test_visibility_windows() {
+        // (15) Eunomia from response_examples/whatsup.html, setting in the west
+        let observer = Observer::new(44.09727, 9.7737, 0.0);
+        let start = NaiveDate::from_ymd_opt(2025, 1, 15)
+            .unwrap()
+            .and_hms_opt(0, 0, 0)
+            .unwrap()
+            .and_utc();
+        let end = start + Duration::hours(8);
+        let (ra, dec) = (
+            coordinates::parse_ra("04 58 06.2").unwrap(),
+            coordinates::parse_dec("+29 30 18").unwrap(),
+        );
+        let step = Duration::minutes(30);
+        let flat = visibility_windows(ra, dec, start, end, step, &observer, &Default::default());
+        let west = HorizonProfile::from_cardinal(0.0, 0.0, 0.0, 30.0);
+        let masked = visibility_windows(ra, dec, start, end, step, &observer, &west);
+        assert_eq!(flat.len(), 1);
+        assert_eq!(flat[0].0, start);
+        assert_eq!(masked.len(), 1);
+        assert!(masked[0].1 < flat[0].1 - Duration::hours(1));
+    }
+}
@@ -0,0 +1,576 @@
+//! # Integrator
+//!
+//! Library integrating minor planet orbits with the perturbations of the major planets
+//!
+//! The equations of motion are integrated with an adaptive Runge-Kutta-Fehlberg 4(5) method
+//! in heliocentric ecliptic J2000 coordinates, in AU and days. Planet positions come from
+//! the approximate Keplerian elements by Standish (JPL), embedded below and valid from 1800
+//! to 2050, which are accurate to some thousandths of AU: far better than ignoring the
+//! planets for objects with an old epoch or a close approach.
+//!
+//! ```rust,no_run
+//! use asteroid_tui::catalogue::Catalogue;
+//! use asteroid_tui::integrator::Trajectory;
+//! let catalogue = Catalogue::load().unwrap();
+//! let eros = catalogue.get("00433").unwrap();
+//! let trajectory = Trajectory::integrate(eros, 2460800.5, 2460830.5).unwrap();
+//! let position = trajectory.heliocentric_position(2460815.5).unwrap();
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::orbit::{self, Orbit, Vector};
+use anyhow::{anyhow, Result};
+
+/// Gaussian gravitational constant, in AU^(3/2) per day
+const GAUSS_CONSTANT: f64 = 0.01720209895;
+
+/// Local error tolerance of a step in AU
+const TOLERANCE: f64 = 1e-12;
+
+/// Longest step in days
+const MAX_STEP: f64 = 20.0;
+
+/// Shortest step in days, closer approaches are not integrated
+const MIN_STEP: f64 = 1e-6;
+
+/// Longest integrated time span in days
+const MAX_SPAN: f64 = 36525.0;
+
+/// Position and velocity, in AU and AU per day
+pub type State = [f64; 6];
+
+/// Approximate planet elements at J2000 and their rates per Julian century
+///
+/// * `name`: planet name
+/// * `mass_ratio`: Sun mass over planet mass
+/// * `elements`: semi-major axis, eccentricity, inclination, mean longitude, longitude of
+///   perihelion and longitude of the ascending node
+/// * `rates`: rates of the elements per Julian century
+struct Planet {
+    name: &'static str,
+    mass_ratio: f64,
+    elements: [f64; 6],
+    rates: [f64; 6],
+}
+
+/// Planets from Standish, "Keplerian Elements for Approximate Positions of the Major Planets"
+const PLANETS: [Planet; 8] = [
+    Planet {
+        name: "Mercury",
+        mass_ratio: 6023600.0,
+        elements: [
+            0.38709927,
+            0.20563593,
+            7.00497902,
+            252.25032350,
+            77.45779628,
+            48.33076593,
+        ],
+        rates: [
+            0.00000037,
+            0.00001906,
+            -0.00594749,
+            149472.67411175,
+            0.16047689,
+            -0.12534081,
+        ],
+    },
+    Planet {
+        name: "Venus",
+        mass_ratio: 408523.71,
+        elements: [
+            0.72333566,
+            0.00677672,
+            3.39467605,
+            181.97909950,
+            131.60246718,
+            76.67984255,
+        ],
+        rates: [
+            0.00000390,
+            -0.00004107,
+            -0.00078890,
+            58517.81538729,
+            0.00268329,
+            -0.27769418,
+        ],
+    },
+    Planet {
+        name: "Earth-Moon",
+        mass_ratio: 328900.56,
+        elements: [
+            1.00000261,
+            0.01671123,
+            -0.00001531,
+            100.46457166,
+            102.93768193,
+            0.0,
+        ],
+        rates: [
+            0.00000562,
+            -0.00004392,
+            -0.01294668,
+            35999.37244981,
+            0.32327364,
+            0.0,
+        ],
+    },
+    Planet {
+        name: "Mars",
+        mass_ratio: 3098708.0,
+        elements: [
+            1.52371034,
+            0.09339410,
+            1.84969142,
+            -4.55343205,
+            -23.94362959,
+            49.55953891,
+        ],
+        rates: [
+            0.00001847,
+            0.00007882,
+            -0.00813131,
+            19140.30268499,
+            0.44441088,
+            -0.29257343,
+        ],
+    },
+    Planet {
+        name: "Jupiter",
+        mass_ratio: 1047.3486,
+        elements: [
+            5.20288700,
+            0.04838624,
+            1.30439695,
+            34.39644051,
+            14.72847983,
+            100.47390909,
+        ],
+        rates: [
+            -0.00011607,
+            -0.00013253,
+            -0.00183714,
+            3034.74612775,
+            0.21252668,
+            0.20469106,
+        ],
+    },
+    Planet {
+        name: "Saturn",
+        mass_ratio: 3497.898,
+        elements: [
+            9.53667594,
+            0.05386179,
+            2.48599187,
+            49.95424423,
+            92.59887831,
+            113.66242448,
+        ],
+        rates: [
+            -0.00125060,
+            -0.00050991,
+            0.00193609,
+            1222.49362201,
+            -0.41897216,
+            -0.28867794,
+        ],
+    },
+    Planet {
+        name: "Uranus",
+        mass_ratio: 22902.98,
+        elements: [
+            19.18916464,
+            0.04725744,
+            0.77263783,
+            313.23810451,
+            170.95427630,
+            74.01692503,
+        ],
+        rates: [
+            -0.00196176,
+            -0.00004397,
+            -0.00242939,
+            428.48202785,
+            0.40805281,
+            0.04240589,
+        ],
+    },
+    Planet {
+        name: "Neptune",
+        mass_ratio: 19412.24,
+        elements: [
+            30.06992276,
+            0.00859048,
+            1.77004347,
+            -55.12002969,
+            44.96476227,
+            131.78422574,
+        ],
+        rates: [
+            0.00026291,
+            0.00005105,
+            0.00035372,
+            218.45945325,
+            -0.32241464,
+            -0.00508664,
+        ],
+    },
+];
+
+/// Returns the names of the planets used for the perturbations
+pub fn planet_names() -> Vec<&'static str> {
+    PLANETS.iter().map(|planet| planet.name).collect()
+}
+
+/// Returns the heliocentric position of a planet, ecliptic J2000 in AU
+///
+/// * `name`: planet name, as in `planet_names`
+/// * `jd`: Julian Day
+pub fn planet_position(name: &str, jd: f64) -> Result<Vector> {
+    PLANETS
+        .iter()
+        .find(|planet| planet.name.eq_ignore_ascii_case(name))
+        .map(|planet| position_of(planet, jd))
+        .ok_or_else(|| anyhow!("Unknown planet {}", name))
+}
+
+/// Returns the heliocentric position of a planet, ecliptic J2000 in AU
+///
+/// * `planet`: the planet
+/// * `jd`: Julian Day
+fn position_of(planet: &Planet, jd: f64) -> Vector {
+    let t = (jd - 2451545.0) / 36525.0;
+    let [a, e, i, mean_longitude, perihelion, node]: [f64; 6] =
+        std::array::from_fn(|index| planet.elements[index] + planet.rates[index] * t);
+    orbit::kepler_position(
+        a,
+        e,
+        i,
+        node,
+        perihelion - node,
+        mean_longitude - perihelion,
+    )
+}
+
+/// Returns the heliocentric acceleration of a massless body, in AU per day squared
+///
+/// * `position`: heliocentric position
+/// * `planets`: heliocentric positions of the planets, in the order of `PLANETS`
+fn acceleration(position: Vector, planets: &[Vector]) -> Vector {
+    let sun = GAUSS_CONSTANT * GAUSS_CONSTANT;
+    let distance = orbit::norm(position);
+    let mut result = position.map(|value| -sun * value / distance.powi(3));
+    for (planet, body) in PLANETS.iter().zip(planets) {
+        let mass = sun / planet.mass_ratio;
+        let relative: Vector = std::array::from_fn(|index| body[index] - position[index]);
+        let (relative_distance, body_distance) = (orbit::norm(relative), orbit::norm(*body));
+        for index in 0..3 {
+            // Direct attraction and indirect term from the acceleration of the Sun
+            result[index] += mass
+                * (relative[index] / relative_distance.powi(3)
+                    - body[index] / body_distance.powi(3));
+        }
+    }
+    result
+}
+
+/// Returns the time derivative of a state perturbed by the planets
+///
+/// * `jd`: Julian Day
+/// * `state`: heliocentric state
+fn perturbed_derivative(jd: f64, state: &State) -> State {
+    let planets: Vec<Vector> = PLANETS
+        .iter()
+        .map(|planet| position_of(planet, jd))
+        .collect();
+    let acceleration = acceleration([state[0], state[1], state[2]], &planets);
+    [
+        state[3],
+        state[4],
+        state[5],
+        acceleration[0],
+        acceleration[1],
+        acceleration[2],
+    ]
+}
+
+/// Takes a Runge-Kutta-Fehlberg step, returning the fifth order state and the error estimate
+///
+/// * `derivative`: time derivative of the state
+/// * `jd`: Julian Day of the state
+/// * `state`: initial state
+/// * `step`: step in days, negative to integrate backwards
+fn rkf45_step(
+    derivative: &impl Fn(f64, &State) -> State,
+    jd: f64,
+    state: &State,
+    step: f64,
+) -> (State, f64) {
+    let combine = |weights: &[(f64, &State)]| -> State {
+        std::array::from_fn(|index| {
+            state[index]
+                + step
+                    * weights
+                        .iter()
+                        .map(|(weight, k)| weight * k[index])
+                        .sum::<f64>()
+        })
+    };
+    let k1 = derivative(jd, state);
+    let k2 = derivative(jd + step / 4.0, &combine(&[(1.0 / 4.0, &k1)]));
+    let k3 = derivative(
+        jd + 3.0 * step / 8.0,
+        &combine(&[(3.0 / 32.0, &k1), (9.0 / 32.0, &k2)]),
+    );
+    let k4 = derivative(
+        jd + 12.0 * step / 13.0,
+        &combine(&[
+            (1932.0 / 2197.0, &k1),
+            (-7200.0 / 2197.0, &k2),
+            (7296.0 / 2197.0, &k3),
+        ]),
+    );
+    let k5 = derivative(
+        jd + step,
+        &combine(&[
+            (439.0 / 216.0, &k1),
+            (-8.0, &k2),
+            (3680.0 / 513.0, &k3),
+            (-845.0 / 4104.0, &k4),
+        ]),
+    );
+    let k6 = derivative(
+        jd + step / 2.0,
+        &combine(&[
+            (-8.0 / 27.0, &k1),
+            (2.0, &k2),
+            (-3544.0 / 2565.0, &k3),
+            (1859.0 / 4104.0, &k4),
+            (-11.0 / 40.0, &k5),
+        ]),
+    );
+    let fourth = combine(&[
+        (25.0 / 216.0, &k1),
+        (1408.0 / 2565.0, &k3),
+        (2197.0 / 4104.0, &k4),
+        (-1.0 / 5.0, &k5),
+    ]);
+    let fifth = combine(&[
+        (16.0 / 135.0, &k1),
+        (6656.0 / 12825.0, &k3),
+        (28561.0 / 56430.0, &k4),
+        (-9.0 / 50.0, &k5),
+        (2.0 / 55.0, &k6),
+    ]);
+    let error = (0..3)
+        .map(|index| (fifth[index] - fourth[index]).abs())
+        .fold(0.0, f64::max);
+    (fifth, error)
+}
+
+/// Integrates a state from `start` to `end` with adaptive steps, returning every accepted
+/// state after the first one
+///
+/// * `derivative`: time derivative of the state
+/// * `start`: Julian Day of the initial state
+/// * `state`: initial state
+/// * `end`: final Julian Day, before `start` to integrate backwards
+fn integrate_states(
+    derivative: &impl Fn(f64, &State) -> State,
+    start: f64,
+    state: State,
+    end: f64,
+) -> Result<Vec<(f64, State)>> {
+    let direction = if end < start { -1.0 } else { 1.0 };
+    let (mut jd, mut state, mut step) = (start, state, 1.0_f64);
+    let mut states = Vec::new();
+    while (end - jd) * direction > 0.0 {
+        let size = step.min((end - jd).abs());
+        let (next, error) = rkf45_step(derivative, jd, &state, size * direction);
+        if error <= TOLERANCE || size <= MIN_STEP {
+            jd += size * direction;
+            state = next;
+            states.push((jd, state));
+        }
+        // Standard step size control, with a safety factor and limited growth
+        let factor = if error > 0.0 {
+            (0.9 * (TOLERANCE / error).powf(0.2)).clamp(0.2, 4.0)
+        } else {
+            4.0
+        };
+        step = (size * factor).clamp(MIN_STEP, MAX_STEP);
+        if error > TOLERANCE && size <= MIN_STEP {
+            return Err(anyhow!("The integration failed near JD {:.5}", jd));
+        }
+    }
+    Ok(states)
+}
+
+/// Returns the heliocentric state of an orbit at its epoch
+///
+/// * `orbit`: object orbit
+fn initial_state(orbit: &Orbit) -> State {
+    let position = orbit.heliocentric_position(orbit.epoch);
+    let velocity = orbit.heliocentric_velocity(orbit.epoch);
+    [
+        position[0],
+        position[1],
+        position[2],
+        velocity[0],
+        velocity[1],
+        velocity[2],
+    ]
+}
+
+/// Trajectory structure, with the integrated states of an object
+///
+/// * `states`: states sorted by Julian Day
+#[derive(Debug, Clone)]
+pub struct Trajectory {
+    /// States sorted by Julian Day
+    states: Vec<(f64, State)>,
+}
+
+impl Trajectory {
+    /// Integrates an orbit from its epoch over the interval from `start` to `end`
+    ///
+    /// * `orbit`: object orbit
+    /// * `start`: first Julian Day
+    /// * `end`: last Julian Day
+    pub fn integrate(orbit: &Orbit, start: f64, end: f64) -> Result<Self> {
+        if end < start {
+            return Err(anyhow!("The end must follow the start"));
+        }
+        if end.max(orbit.epoch) - start.min(orbit.epoch) > MAX_SPAN {
+            return Err(anyhow!(
+                "The epoch of {} is too far from the requested time",
+                orbit.designation
+            ));
+        }
+        let initial = initial_state(orbit);
+        let mut states = vec![(orbit.epoch, initial)];
+        if start < orbit.epoch {
+            states.extend(integrate_states(
+                &perturbed_derivative,
+                orbit.epoch,
+                initial,
+                start,
+            )?);
+        }
+        if end > orbit.epoch {
+            states.extend(integrate_states(
+                &perturbed_derivative,
+                orbit.epoch,
+                initial,
+                end,
+            )?);
+        }
+        states.sort_by(|first, second| first.0.total_cmp(&second.0));
+        Ok(Trajectory { states })
+    }
+
+    /// Returns the heliocentric state at `jd`, integrating from the closest stored state
+    ///
+    /// Fails if the integration from the closest state fails.
+    ///
+    /// * `jd`: Julian Day
+    pub fn state(&self, jd: f64) -> Result<State> {
+        let index = self.states.partition_point(|(time, _)| *time < jd);
+        let closest = [index.saturating_sub(1), index.min(self.states.len() - 1)]
+            .into_iter()
+            .min_by(|first, second| {
+                (self.states[*first].0 - jd)
+                    .abs()
+                    .total_cmp(&(self.states[*second].0 - jd).abs())
+            })
+            .unwrap();
+        let (time, state) = self.states[closest];
+        if time == jd {
+            return Ok(state);
+        }
+        // The closest state is at most half an accepted step away
+        let states = integrate_states(&perturbed_derivative, time, state, jd)?;
+        Ok(states.last().map_or(state, |(_, state)| *state))
+    }
+
+    /// Returns the heliocentric position, ecliptic J2000 in AU
+    ///
+    /// * `jd`: Julian Day
+    pub fn heliocentric_position(&self, jd: f64) -> Result<Vector> {
+        let state = self.state(jd)?;
+        Ok([state[0], state[1], state[2]])
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use crate::orbit::test::eros;
+
+    #[test]
+    fn test_planet_position() {
+        // Jupiter at J2000 from DE405: (4.0012, 2.9386, -0.1017)
+        let jupiter = planet_position("jupiter", 2451545.0).unwrap();
+        assert!((jupiter[0] - 4.0012).abs() < 0.01);
+        assert!((jupiter[1] - 2.9386).abs() < 0.01);
+        assert!((jupiter[2] + 0.1017).abs() < 0.01);
+        let earth = planet_position("Earth-Moon", 2460000.5).unwrap();
+        assert_eq!(earth, orbit::earth_position(2460000.5));
+        assert!(planet_position("Pluto", 2451545.0).is_err());
+        assert_eq!(planet_names().len(), 8);
+    }
+
+    #[test]
+    fn test_two_body_integration() {
+        // Without planets the integrator must follow the Keplerian ellipse, with the mean
+        // motion of its semi-major axis
+        let orbit = Orbit {
+            mean_motion: 0.0,
+            ..eros()
+        };
+        let two_body = |_: f64, state: &State| {
+            let acceleration = acceleration([state[0], state[1], state[2]], &[]);
+            [
+                state[3],
+                state[4],
+                state[5],
+                acceleration[0],
+                acceleration[1],
+                acceleration[2],
+            ]
+        };
+        for end in [orbit.epoch + 200.0, orbit.epoch - 200.0] {
+            let states =
+                integrate_states(&two_body, orbit.epoch, initial_state(&orbit), end).unwrap();
+            let (jd, state) = states.last().unwrap();
+            assert_eq!(*jd, end);
+            let expected = orbit.heliocentric_position(end);
+            let difference: Vector = std::array::from_fn(|index| state[index] - expected[index]);
+            assert!(orbit::norm(difference) < 1e-7);
+        }
+    }
+
+    #[test]
+    fn test_trajectory() {
+        let orbit = eros();
+        let (start, end) = (orbit.epoch + 3000.0, orbit.epoch + 3010.0);
+        let trajectory = Trajectory::integrate(&orbit, start, end).unwrap();
+        let middle = trajectory.heliocentric_position(start + 5.25).unwrap();
+        let keplerian = orbit.heliocentric_position(start + 5.25);
+        let difference: Vector = std::array::from_fn(|index| middle[index] - keplerian[index]);
+        // Perturbations move Eros by some thousandths of AU in eight years
+        assert!(orbit::norm(difference) > 1e-5);
+        assert!(orbit::norm(difference) < 0.05);
+        // Positions between stored states are continuous
+        let near = trajectory
+            .heliocentric_position(start + 5.25 + 1e-4)
+            .unwrap();
+        let step: Vector = std::array::from_fn(|index| near[index] - middle[index]);
+        assert!(orbit::norm(step) < 1e-5);
+        assert!(Trajectory::integrate(&orbit, end, start).is_err());
+        assert!(
+            Trajectory::integrate(&orbit, orbit.epoch + 40000.0, orbit.epoch + 40001.0).is_err()
+        );
+    }
+}
@@ -11,14 +11,52 @@ pub mod settings_tui;
 /// Scheduling tui library
 pub mod scheduling_tui;
 
+/// Coordinates library
+pub mod coordinates;
+
+/// Horizon library
+pub mod horizon;
+
 /// Sun Moon Times library
 pub mod sun_moon_times;
 
+/// Night Quality library
+pub mod night_quality;
 /// Weather library
 pub mod weather;
+/// Weather Providers library
+pub mod weather_providers;
 
 /// Observing Target List library
 pub mod observing_target_list;
 
+/// Designation library
+pub mod designation;
+
+/// Orbit library
+pub mod orbit;
+
+/// Orbit Catalogue library
+pub mod catalogue;
+
+/// Orbit Integrator library
+pub mod integrator;
+
+/// Ephemeris library
+pub mod ephemeris;
+
+/// Local What's Up library
+pub mod whats_up;
+
+/// NEO Confirmation Page library
+pub mod neocp;
+
+/// Astrometry Import library
+pub mod astrometry_import;
+/// NEOCP Ephemeris library
+pub mod neocp_ephemeris;
+/// Report library
+pub mod report;
+
 /// Tui library
 pub mod tui;
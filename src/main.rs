@@ -1,7 +1,29 @@
+use asteroid_tui::settings::{self, Settings};
 use asteroid_tui::tui;
 // use std::io;
 
+/// Returns the observatory profile given with `--observatory NAME` or `--observatory=NAME`
+fn observatory_argument() -> Option<String> {
+    let mut args = std::env::args().skip(1);
+    while let Some(arg) = args.next() {
+        if arg == "--observatory" {
+            return args.next();
+        }
+        if let Some(name) = arg.strip_prefix("--observatory=") {
+            return Some(name.to_string());
+        }
+    }
+    None
+}
+
 fn main() {
+    if let Some(name) = observatory_argument() {
+        settings::set_observatory_override(&name);
+        if let Err(error) = Settings::new() {
+            eprintln!("Error: {}", error);
+            std::process::exit(1);
+        }
+    }
     println!("Welcome to Asteroid_tui!");
     let _ = tui::main_menu();
 }
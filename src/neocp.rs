@@ -0,0 +1,279 @@
+//! # NEOCP
+//!
+//! Library for the NEO Confirmation Page of the Minor Planet Center
+//!
+//! The listing at <https://www.minorplanetcenter.net/iau/NEO/neocp.txt> has one object per
+//! line: temporary designation, score, discovery date, RA (hours) and Dec, V magnitude, last
+//! update, number of observations, arc length, H and days since the last observation.
+//!
+//! ```rust
+//! use asteroid_tui::neocp::NeocpObject;
+//! let line = "P21Jx4L 100 2025 01 14.4  04.9684 +29.5050 19.8 Updated Jan. 15.12 UT             12   0.95 19.6  0.302";
+//! let object = NeocpObject::from_line(line).unwrap();
+//! assert_eq!(object.score, 100);
+//! assert_eq!(object.observations, 12);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::coordinates::Observer;
+use crate::horizon::{self, HorizonProfile};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use reqwest;
+use serde::{Deserialize, Serialize};
+
+/// NEOCP text listing
+const NEOCP_URL: &str = "https://www.minorplanetcenter.net/iau/NEO/neocp.txt";
+
+/// Time between two visibility samples in minutes
+const VISIBILITY_STEP: i64 = 15;
+
+/// NEOCP object structure
+///
+/// * `designation`: temporary designation
+/// * `score`: NEO desirability score, from 0 to 100
+/// * `discovery`: discovery time
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `magnitude`: current V magnitude
+/// * `updated`: last update note, i.e. "Updated Jan. 15.12 UT"
+/// * `observations`: number of observations
+/// * `arc`: observed arc in days
+/// * `absolute_magnitude`: absolute magnitude H
+/// * `not_seen`: days since the last observation
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NeocpObject {
+    /// Temporary designation
+    pub designation: String,
+    /// NEO desirability score, from 0 to 100
+    pub score: u8,
+    /// Discovery time
+    pub discovery: DateTime<Utc>,
+    /// Right ascension in degrees
+    pub ra: f64,
+    /// Declination in degrees
+    pub dec: f64,
+    /// Current V magnitude
+    pub magnitude: f32,
+    /// Last update note, i.e. "Updated Jan. 15.12 UT"
+    pub updated: String,
+    /// Number of observations
+    pub observations: u32,
+    /// Observed arc in days
+    pub arc: f64,
+    /// Absolute magnitude H
+    pub absolute_magnitude: f32,
+    /// Days since the last observation
+    pub not_seen: f64,
+}
+
+/// Parses a field of a NEOCP listing or ephemeris line
+///
+/// * `value`: the field
+/// * `name`: name of the field, for errors
+pub(crate) fn parse_field<T: std::str::FromStr>(value: &str, name: &str) -> Result<T> {
+    value
+        .parse::<T>()
+        .map_err(|_| anyhow!("Invalid {} \"{}\"", name, value))
+}
+
+impl NeocpObject {
+    /// Parses a line of the NEOCP listing
+    ///
+    /// * `line`: the line
+    pub fn from_line(line: &str) -> Result<Self> {
+        let fields: Vec<&str> = line.split_whitespace().collect();
+        if fields.len() < 13 {
+            return Err(anyhow!("Not enough fields in \"{}\"", line.trim()));
+        }
+        let tail = &fields[fields.len() - 4..];
+        let day: f64 = parse_field(fields[4], "discovery day")?;
+        let date = NaiveDate::from_ymd_opt(
+            parse_field(fields[2], "discovery year")?,
+            parse_field(fields[3], "discovery month")?,
+            day.trunc() as u32,
+        )
+        .ok_or_else(|| anyhow!("Invalid discovery date in \"{}\"", line.trim()))?;
+        let discovery = date.and_hms_opt(0, 0, 0).unwrap().and_utc()
+            + Duration::seconds((day.fract() * 86400.0).round() as i64);
+        let ra: f64 = parse_field(fields[5], "RA")?;
+        let dec: f64 = parse_field(fields[6], "Dec")?;
+        if !(0.0..24.0).contains(&ra) || !(-90.0..=90.0).contains(&dec) {
+            return Err(anyhow!("Invalid coordinates in \"{}\"", line.trim()));
+        }
+        Ok(NeocpObject {
+            designation: fields[0].to_string(),
+            score: parse_field(fields[1], "score")?,
+            discovery,
+            ra: ra * 15.0,
+            dec,
+            magnitude: parse_field(fields[7], "magnitude")?,
+            updated: fields[8..fields.len() - 4].join(" "),
+            observations: parse_field(tail[0], "number of observations")?,
+            arc: parse_field(tail[1], "arc")?,
+            absolute_magnitude: parse_field(tail[2], "H")?,
+            not_seen: parse_field(tail[3], "not seen days")?,
+        })
+    }
+
+    /// Returns the time the object is above the horizon profile between `start` and `end`
+    ///
+    /// NEOCP positions are current, so the motion of the object is neglected.
+    ///
+    /// * `start`: first UTC time, i.e. darkness begin
+    /// * `end`: last UTC time, i.e. darkness end
+    /// * `observer`: observer location
+    /// * `profile`: horizon profile
+    pub fn visible_time(
+        &self,
+        start: DateTime<Utc>,
+        end: DateTime<Utc>,
+        observer: &Observer,
+        profile: &HorizonProfile,
+    ) -> Duration {
+        horizon::visibility_windows(
+            self.ra,
+            self.dec,
+            start,
+            end,
+            Duration::minutes(VISIBILITY_STEP),
+            observer,
+            profile,
+        )
+        .iter()
+        .map(|(rise, set)| *set - *rise)
+        .sum()
+    }
+}
+
+/// Parses the NEOCP listing, skipping empty lines
+///
+/// * `text`: the listing
+pub fn parse_neocp(text: &str) -> Result<Vec<NeocpObject>> {
+    text.lines()
+        .enumerate()
+        .filter(|(_, line)| !line.trim().is_empty())
+        .map(|(number, line)| {
+            NeocpObject::from_line(line).map_err(|e| anyhow!("Line {}: {}", number + 1, e))
+        })
+        .collect()
+}
+
+/// Gets raw NEOCP listing from MPC
+fn get_neocp_listing() -> Result<String> {
+    Ok(reqwest::blocking::get(NEOCP_URL)?
+        .error_for_status()?
+        .text()?)
+}
+
+/// Returns the objects of the NEOCP, sorted by score
+pub fn prepare_data() -> Result<Vec<NeocpObject>> {
+    let mut objects = parse_neocp(&get_neocp_listing()?)?;
+    sort_by_score(&mut objects);
+    Ok(objects)
+}
+
+/// Sorts objects by decreasing score, then by increasing magnitude
+///
+/// * `objects`: objects to sort
+pub fn sort_by_score(objects: &mut [NeocpObject]) {
+    objects.sort_by(|first, second| {
+        second
+            .score
+            .cmp(&first.score)
+            .then(first.magnitude.total_cmp(&second.magnitude))
+    });
+}
+
+/// Sorts objects by decreasing visible time, then by decreasing score
+///
+/// * `objects`: objects to sort
+/// * `start`: first UTC time, i.e. darkness begin
+/// * `end`: last UTC time, i.e. darkness end
+/// * `observer`: observer location
+/// * `profile`: horizon profile
+pub fn sort_by_visibility(
+    objects: &mut [NeocpObject],
+    start: DateTime<Utc>,
+    end: DateTime<Utc>,
+    observer: &Observer,
+    profile: &HorizonProfile,
+) {
+    objects.sort_by_cached_key(|object| {
+        (
+            -object
+                .visible_time(start, end, observer, profile)
+                .num_minutes(),
+            -(object.score as i64),
+        )
+    });
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixture() -> Vec<NeocpObject> {
+        let text = std::fs::read_to_string("response_examples/neocp.txt").unwrap();
+        parse_neocp(&text).unwrap()
+    }
+
+    #[test]
+    #[ignore = "needs network access to the MPC"]
+    fn test_get_neocp_listing() {
+        let objects = prepare_data().unwrap();
+        assert!(objects.iter().all(|object| object.score <= 100));
+    }
+
+    #[test]
+    fn test_parse_neocp() {
+        let objects = fixture();
+        assert_eq!(objects.len(), 5);
+        let first = &objects[0];
+        assert_eq!(first.designation, "P21Jx4L");
+        assert_eq!(first.discovery.to_rfc3339(), "2025-01-14T09:36:00+00:00");
+        assert!((first.ra - 74.526).abs() < 1e-9);
+        assert_eq!(first.dec, 29.505);
+        assert_eq!(first.magnitude, 19.8);
+        assert_eq!(first.updated, "Updated Jan. 15.12 UT");
+        assert_eq!(first.observations, 12);
+        assert_eq!(first.arc, 0.95);
+        assert_eq!(first.absolute_magnitude, 19.6);
+        assert_eq!(first.not_seen, 0.302);
+        assert_eq!(objects[1].updated, "Added Jan. 15.05 UT");
+        assert!(parse_neocp("P21Jx4L 100 2025 01 14.4 04.9684").is_err());
+        assert!(parse_neocp(
+            "P21Jx4L 100 2025 01 14.4  25.9684 +29.5050 19.8 Updated Jan. 15.12 UT 12 0.95 19.6 0.302"
+        )
+        .is_err());
+    }
+
+    #[test]
+    fn test_sort() {
+        let mut objects = fixture();
+        sort_by_score(&mut objects);
+        let designations: Vec<&str> = objects.iter().map(|o| o.designation.as_str()).collect();
+        assert_eq!(
+            designations,
+            ["P21Jx4L", "P21Jy9A", "C4KGH21", "ZTF0Bq3", "A11kXq2"]
+        );
+        // Night of 2025-01-14 from La Spezia, the southern object never rises
+        let observer = Observer::new(44.09727, 9.7737, 0.0);
+        let start = NaiveDate::from_ymd_opt(2025, 1, 14)
+            .unwrap()
+            .and_hms_opt(17, 0, 0)
+            .unwrap()
+            .and_utc();
+        let end = start + Duration::hours(13);
+        let profile = HorizonProfile::default();
+        sort_by_visibility(&mut objects, start, end, &observer, &profile);
+        let last = objects.last().unwrap();
+        assert_eq!(last.designation, "P21Jy9A");
+        assert_eq!(
+            last.visible_time(start, end, &observer, &profile),
+            Duration::zero()
+        );
+        assert!(objects[0].visible_time(start, end, &observer, &profile) > Duration::hours(8));
+    }
+}
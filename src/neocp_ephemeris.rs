@@ -0,0 +1,461 @@
+//! # NEOCP Ephemeris
+//!
+//! Library for NEOCP ephemerides, variant orbit offsets and confirmation pointings
+//!
+//! Ephemerides are requested to the MPC for the observatory code in `Settings`. Every line
+//! links the offsets of the variant orbits from the nominal position: their spread tells
+//! whether one field is enough to catch the object or a mosaic of fields is needed.
+//!
+//! ```rust
+//! use asteroid_tui::neocp_ephemeris::{self, UncertaintySpread};
+//! let spread = UncertaintySpread::new(vec![(0.0, 0.0), (900.0, -400.0), (-900.0, 400.0)]);
+//! assert_eq!(spread.width(), 30.0);
+//! let pointings = neocp_ephemeris::suggest_pointings(75.0, 29.5, &spread, 20.0, 20.0);
+//! assert!(pointings.len() > 1);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::coordinates;
+use crate::neocp::parse_field;
+use crate::settings::Settings;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use regex::Regex;
+use reqwest;
+
+/// MPC NEOCP ephemeris service
+const NEOCP_EPHEMERIS_URL: &str = "https://cgi.minorplanetcenter.net/cgi-bin/confirmeph2.cgi";
+
+/// Fraction of the field of view shared by two adjacent mosaic fields
+const MOSAIC_OVERLAP: f64 = 0.1;
+
+/// NEOCP ephemeris entry structure
+///
+/// * `time`: UTC time
+/// * `ra`: right ascension J2000 in degrees
+/// * `dec`: declination J2000 in degrees
+/// * `solar_elongation`: solar elongation in degrees
+/// * `magnitude`: V magnitude
+/// * `rate`: rate of motion in arcseconds per minute
+/// * `position_angle`: direction of motion in degrees
+/// * `azimuth`: azimuth in degrees
+/// * `altitude`: altitude in degrees
+/// * `sun_altitude`: Sun altitude in degrees
+/// * `moon_phase`: Moon illuminated fraction
+/// * `moon_distance`: distance from the Moon in degrees
+/// * `moon_altitude`: Moon altitude in degrees
+/// * `offsets_url`: link to the offsets of the variant orbits, if the MPC has them
+#[derive(Debug, Clone, PartialEq)]
+pub struct NeocpEphemerisEntry {
+    /// UTC time
+    pub time: DateTime<Utc>,
+    /// Right ascension J2000 in degrees
+    pub ra: f64,
+    /// Declination J2000 in degrees
+    pub dec: f64,
+    /// Solar elongation in degrees
+    pub solar_elongation: f64,
+    /// V magnitude
+    pub magnitude: f32,
+    /// Rate of motion in arcseconds per minute
+    pub rate: f64,
+    /// Direction of motion in degrees
+    pub position_angle: f64,
+    /// Azimuth in degrees
+    pub azimuth: f64,
+    /// Altitude in degrees
+    pub altitude: f64,
+    /// Sun altitude in degrees
+    pub sun_altitude: f64,
+    /// Moon illuminated fraction
+    pub moon_phase: f64,
+    /// Distance from the Moon in degrees
+    pub moon_distance: f64,
+    /// Moon altitude in degrees
+    pub moon_altitude: f64,
+    /// Link to the offsets of the variant orbits, if the MPC has them
+    pub offsets_url: Option<String>,
+}
+
+/// Spread of the variant orbits around the nominal position
+///
+/// * `offsets`: offsets in arcseconds, along RA (already multiplied by cos Dec) and Dec
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct UncertaintySpread {
+    /// Offsets in arcseconds, along RA (already multiplied by cos Dec) and Dec
+    offsets: Vec<(f64, f64)>,
+}
+
+/// Suggested telescope pointing
+///
+/// * `ra`: right ascension J2000 in degrees
+/// * `dec`: declination J2000 in degrees
+/// * `ra_offset`: offset from the nominal position along RA in arcminutes
+/// * `dec_offset`: offset from the nominal position along Dec in arcminutes
+/// * `variants`: number of variant orbits inside the field
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct Pointing {
+    /// Right ascension J2000 in degrees
+    pub ra: f64,
+    /// Declination J2000 in degrees
+    pub dec: f64,
+    /// Offset from the nominal position along RA in arcminutes
+    pub ra_offset: f64,
+    /// Offset from the nominal position along Dec in arcminutes
+    pub dec_offset: f64,
+    /// Number of variant orbits inside the field
+    pub variants: usize,
+}
+
+/// Patterns of the HTML of an ephemeris line
+///
+/// * `offsets`: link to the variant orbit offsets
+/// * `tags`: any HTML tag
+struct LinePatterns {
+    /// Link to the variant orbit offsets
+    offsets: Regex,
+    /// Any HTML tag
+    tags: Regex,
+}
+
+impl LinePatterns {
+    /// Compiles the patterns
+    fn new() -> Self {
+        LinePatterns {
+            offsets: Regex::new(r#"href="([^"]+)">Offsets</a>"#).unwrap(),
+            tags: Regex::new(r"<[^>]+>").unwrap(),
+        }
+    }
+}
+
+/// Decodes the HTML entities found in MPC links
+///
+/// * `value`: attribute value
+fn decode_entities(value: &str) -> String {
+    value
+        .replace("&amp;", "&")
+        .replace("&lt;", "<")
+        .replace("&gt;", ">")
+}
+
+impl NeocpEphemerisEntry {
+    /// Parses a line of a NEOCP ephemeris, with its HTML links
+    ///
+    /// * `line`: the line
+    pub fn from_line(line: &str) -> Result<Self> {
+        Self::parse_line(line, &LinePatterns::new())
+    }
+
+    /// Parses a line of a NEOCP ephemeris with already compiled patterns
+    ///
+    /// * `line`: the line
+    /// * `patterns`: HTML patterns
+    fn parse_line(line: &str, patterns: &LinePatterns) -> Result<Self> {
+        let offsets_url = patterns
+            .offsets
+            .captures(line)
+            .map(|captures| decode_entities(&captures[1]));
+        let text = patterns.tags.replace_all(line, " ");
+        let fields: Vec<&str> = text.split_whitespace().collect();
+        if fields.len() < 20 {
+            return Err(anyhow!("Not enough fields in \"{}\"", text.trim()));
+        }
+        let date = NaiveDate::from_ymd_opt(
+            parse_field(fields[0], "year")?,
+            parse_field(fields[1], "month")?,
+            parse_field(fields[2], "day")?,
+        )
+        .ok_or_else(|| anyhow!("Invalid date in \"{}\"", text.trim()))?;
+        let time = match fields[3].len() {
+            2 => NaiveTime::parse_from_str(&format!("{}00", fields[3]), "%H%M"),
+            _ => NaiveTime::parse_from_str(fields[3], "%H%M"),
+        }
+        .map_err(|_| anyhow!("Invalid time \"{}\"", fields[3]))?;
+        Ok(NeocpEphemerisEntry {
+            time: date.and_time(time).and_utc(),
+            ra: coordinates::parse_ra(&fields[4..7].join(" "))?,
+            dec: coordinates::parse_dec(&fields[7..10].join(" "))?,
+            solar_elongation: parse_field(fields[10], "elongation")?,
+            magnitude: parse_field(fields[11], "magnitude")?,
+            rate: parse_field(fields[12], "rate")?,
+            position_angle: parse_field(fields[13], "position angle")?,
+            azimuth: parse_field(fields[14], "azimuth")?,
+            altitude: parse_field(fields[15], "altitude")?,
+            sun_altitude: parse_field(fields[16], "Sun altitude")?,
+            moon_phase: parse_field(fields[17], "Moon phase")?,
+            moon_distance: parse_field(fields[18], "Moon distance")?,
+            moon_altitude: parse_field(fields[19], "Moon altitude")?,
+            offsets_url,
+        })
+    }
+}
+
+/// Parses a NEOCP ephemeris page, returning the lines of the first object
+///
+/// * `data`: HTML page
+pub fn parse_neocp_ephemeris(data: &str) -> Result<Vec<NeocpEphemerisEntry>> {
+    let start = data
+        .find("<pre>")
+        .ok_or_else(|| anyhow!("No ephemeris found, is the object still on the NEOCP?"))?;
+    let end = data[start..]
+        .find("</pre>")
+        .map_or(data.len(), |end| start + end);
+    let date = Regex::new(r"^\d{4} \d{2} \d{2} ").unwrap();
+    let patterns = LinePatterns::new();
+    data[start + 5..end]
+        .lines()
+        .filter(|line| date.is_match(line))
+        .map(|line| NeocpEphemerisEntry::parse_line(line, &patterns))
+        .collect()
+}
+
+impl UncertaintySpread {
+    /// Constructor for UncertaintySpread struct
+    ///
+    /// * `offsets`: offsets in arcseconds, along RA (already multiplied by cos Dec) and Dec
+    pub fn new(offsets: Vec<(f64, f64)>) -> Self {
+        UncertaintySpread { offsets }
+    }
+
+    /// Parses the offsets page of the variant orbits
+    ///
+    /// Every line starting with two signed numbers is an offset in arcseconds, along RA and
+    /// Dec, the rest of the line is the kind of orbit.
+    ///
+    /// * `data`: HTML or text page
+    pub fn parse(data: &str) -> Result<Self> {
+        let offsets: Vec<(f64, f64)> = data
+            .lines()
+            .filter_map(|line| {
+                let mut fields = line.split_whitespace();
+                let ra = fields.next()?.parse::<f64>().ok()?;
+                let dec = fields.next()?.parse::<f64>().ok()?;
+                Some((ra, dec))
+            })
+            .collect();
+        if offsets.is_empty() {
+            return Err(anyhow!("No variant orbit offsets found"));
+        }
+        Ok(UncertaintySpread { offsets })
+    }
+
+    /// Returns the offsets in arcseconds
+    pub fn offsets(&self) -> &[(f64, f64)] {
+        &self.offsets
+    }
+
+    /// Returns the minimum and maximum offsets in arcminutes, along RA and Dec
+    fn bounds(&self) -> ((f64, f64), (f64, f64)) {
+        let bounds = |values: Vec<f64>| {
+            values
+                .iter()
+                .fold((f64::INFINITY, f64::NEG_INFINITY), |(min, max), value| {
+                    (min.min(*value / 60.0), max.max(*value / 60.0))
+                })
+        };
+        if self.offsets.is_empty() {
+            return ((0.0, 0.0), (0.0, 0.0));
+        }
+        (
+            bounds(self.offsets.iter().map(|(ra, _)| *ra).collect()),
+            bounds(self.offsets.iter().map(|(_, dec)| *dec).collect()),
+        )
+    }
+
+    /// Returns the extent of the spread along RA in arcminutes
+    pub fn width(&self) -> f64 {
+        let ((min, max), _) = self.bounds();
+        max - min
+    }
+
+    /// Returns the extent of the spread along Dec in arcminutes
+    pub fn height(&self) -> f64 {
+        let (_, (min, max)) = self.bounds();
+        max - min
+    }
+
+    /// Returns true if the whole spread fits in one field
+    ///
+    /// * `fov_width`: field of view along RA in arcminutes
+    /// * `fov_height`: field of view along Dec in arcminutes
+    pub fn fits(&self, fov_width: f64, fov_height: f64) -> bool {
+        self.width() <= fov_width * (1.0 - MOSAIC_OVERLAP)
+            && self.height() <= fov_height * (1.0 - MOSAIC_OVERLAP)
+    }
+}
+
+/// Suggests the fields covering the spread of the variant orbits
+///
+/// A spread smaller than the field needs one pointing at its center, a larger one a mosaic
+/// of overlapping fields; fields without any variant orbit are left out.
+///
+/// * `ra`: nominal right ascension in degrees
+/// * `dec`: nominal declination in degrees
+/// * `spread`: spread of the variant orbits
+/// * `fov_width`: field of view along RA in arcminutes
+/// * `fov_height`: field of view along Dec in arcminutes
+pub fn suggest_pointings(
+    ra: f64,
+    dec: f64,
+    spread: &UncertaintySpread,
+    fov_width: f64,
+    fov_height: f64,
+) -> Vec<Pointing> {
+    let ((ra_min, ra_max), (dec_min, dec_max)) = spread.bounds();
+    // Centers of the fields along one axis, covering from `min` to `max`
+    let centers = |min: f64, max: f64, fov: f64| -> Vec<f64> {
+        let step = fov * (1.0 - MOSAIC_OVERLAP);
+        let count = (((max - min) - step).max(0.0) / step).ceil() as usize + 1;
+        let first = (min + max) / 2.0 - step * (count - 1) as f64 / 2.0;
+        (0..count)
+            .map(|index| first + step * index as f64)
+            .collect()
+    };
+    let inside = |center: f64, value: f64, fov: f64| (value / 60.0 - center).abs() <= fov / 2.0;
+    let mut pointings = Vec::new();
+    for dec_offset in centers(dec_min, dec_max, fov_height) {
+        for ra_offset in centers(ra_min, ra_max, fov_width) {
+            let variants = spread
+                .offsets
+                .iter()
+                .filter(|(ra, dec)| {
+                    inside(ra_offset, *ra, fov_width) && inside(dec_offset, *dec, fov_height)
+                })
+                .count();
+            if variants == 0 && !spread.offsets.is_empty() {
+                continue;
+            }
+            let pointing_dec = dec + dec_offset / 60.0;
+            pointings.push(Pointing {
+                ra: (ra + ra_offset / 60.0 / pointing_dec.to_radians().cos()).rem_euclid(360.0),
+                dec: pointing_dec,
+                ra_offset,
+                dec_offset,
+                variants,
+            });
+        }
+    }
+    pointings
+}
+
+/// Gets raw NEOCP ephemeris of an object for the observatory code in `Settings`
+///
+/// * `designation`: NEOCP temporary designation
+fn get_neocp_ephemeris(designation: &str) -> Result<String> {
+    let settings = Settings::new().unwrap();
+    let params = [
+        ("mb", "-30"),
+        ("mf", "30"),
+        ("dl", "-90"),
+        ("du", "+90"),
+        ("nl", "0"),
+        ("nu", "100"),
+        ("sort", "d"),
+        ("W", "j"),
+        ("obj", designation),
+        ("Parallax", "1"),
+        ("obscode", settings.get_mpc_code()),
+        ("long", ""),
+        ("lat", ""),
+        ("alt", ""),
+        ("int", "1"),
+        ("start", "0"),
+        ("raty", "a"),
+        ("mot", "m"),
+        ("dmot", "p"),
+        ("out", "f"),
+        ("sun", "x"),
+        ("oalt", "0"),
+    ];
+    let client = reqwest::blocking::Client::new();
+    Ok(client
+        .get(NEOCP_EPHEMERIS_URL)
+        .query(&params)
+        .send()?
+        .error_for_status()?
+        .text()?)
+}
+
+/// Returns the hourly NEOCP ephemeris of an object for the observatory in `Settings`
+///
+/// * `designation`: NEOCP temporary designation
+pub fn prepare_ephemeris(designation: &str) -> Result<Vec<NeocpEphemerisEntry>> {
+    parse_neocp_ephemeris(&get_neocp_ephemeris(designation)?)
+}
+
+/// Returns the spread of the variant orbits at the time of an ephemeris entry
+///
+/// * `entry`: ephemeris entry with the link to the offsets
+pub fn prepare_spread(entry: &NeocpEphemerisEntry) -> Result<UncertaintySpread> {
+    let url = entry
+        .offsets_url
+        .as_ref()
+        .ok_or_else(|| anyhow!("The MPC has no variant orbits for this time"))?;
+    UncertaintySpread::parse(&reqwest::blocking::get(url)?.error_for_status()?.text()?)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    #[test]
+    fn test_parse_neocp_ephemeris() {
+        let data = std::fs::read_to_string("response_examples/neocp_ephemeris.html").unwrap();
+        let entries = parse_neocp_ephemeris(&data).unwrap();
+        assert_eq!(entries.len(), 4);
+        let first = &entries[0];
+        assert_eq!(first.time.to_rfc3339(), "2025-01-15T18:00:00+00:00");
+        assert_eq!(coordinates::format_ra(first.ra), "04 58 01.2");
+        assert_eq!(coordinates::format_dec(first.dec), "+29 30 10");
+        assert_eq!(first.magnitude, 19.8);
+        assert_eq!(first.rate, 1.23);
+        assert_eq!(first.position_angle, 123.4);
+        assert_eq!((first.azimuth, first.altitude), (82.0, 36.0));
+        assert_eq!(first.moon_altitude, 40.0);
+        assert_eq!(
+            first.offsets_url.as_deref(),
+            Some("https://cgi.minorplanetcenter.net/cgi-bin/uncertaintymap.cgi?Obj=P21Jx4L&JD=2460691.25&Form=Y&Ext=VAR2&OC=107&Offsets=Y")
+        );
+        assert!(entries[3].dec < 0.0 && entries[3].dec > -1.0);
+        assert!(entries[3].offsets_url.is_none());
+        assert!(parse_neocp_ephemeris("<html>Unknown object</html>").is_err());
+    }
+
+    #[test]
+    fn test_uncertainty_spread() {
+        let data = std::fs::read_to_string("response_examples/neocp_offsets.html").unwrap();
+        let spread = UncertaintySpread::parse(&data).unwrap();
+        assert_eq!(spread.offsets().len(), 10);
+        assert_eq!(spread.offsets()[1], (312.0, -145.0));
+        assert!((spread.width() - (1450.0 + 980.0) / 60.0).abs() < 1e-9);
+        assert!((spread.height() - (455.0 + 690.0) / 60.0).abs() < 1e-9);
+        assert!(!spread.fits(20.0, 20.0));
+        assert!(spread.fits(60.0, 40.0));
+        assert!(UncertaintySpread::parse("<pre>nothing</pre>").is_err());
+    }
+
+    #[test]
+    fn test_suggest_pointings() {
+        let data = std::fs::read_to_string("response_examples/neocp_offsets.html").unwrap();
+        let spread = UncertaintySpread::parse(&data).unwrap();
+        let single = suggest_pointings(74.5, 29.5, &spread, 60.0, 40.0);
+        assert_eq!(single.len(), 1);
+        assert_eq!(single[0].variants, 10);
+        // Center of the spread, 3.9' east and 2.0' south of the nominal position
+        assert!((single[0].ra_offset - 3.916667).abs() < 1e-5);
+        assert!((single[0].dec_offset + 1.958333).abs() < 1e-5);
+        assert!(single[0].ra > 74.5 && single[0].dec < 29.5);
+
+        let mosaic = suggest_pointings(74.5, 29.5, &spread, 20.0, 20.0);
+        assert!(mosaic.len() > 1);
+        // Every variant is inside at least one field
+        for (ra, dec) in spread.offsets() {
+            assert!(mosaic.iter().any(|pointing| {
+                (ra / 60.0 - pointing.ra_offset).abs() <= 10.0
+                    && (dec / 60.0 - pointing.dec_offset).abs() <= 10.0
+            }));
+        }
+        assert!(mosaic.iter().all(|pointing| pointing.variants > 0));
+    }
+}
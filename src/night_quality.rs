@@ -0,0 +1,519 @@
+//! # Night Quality
+//!
+//! Library to rate the 7timer astro forecast as go, marginal or no-go, per hour and per night
+//!
+//! Each hour gets a score from 0 to 1 weighting clouds, seeing, transparency, humidity and
+//! wind; overcast sky or precipitation give no-go. Only hours in astronomical darkness are
+//! rated, and the score is penalized by Moon illumination. The night score is the mean of
+//! its hours.
+//!
+//! ```rust
+//! use asteroid_tui::night_quality::Quality;
+//! assert_eq!(Quality::from_score(0.8), Quality::Go);
+//! assert_eq!(Quality::from_score(0.5), Quality::Marginal);
+//! assert_eq!(Quality::from_score(0.1).to_string(), "No-go");
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::settings::Settings;
+use crate::sun_moon_times::{self, Darkness};
+use crate::weather::{self, Forecast, ForecastResponse};
+use crate::weather_providers::{self, HourlyForecast};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDate, Utc};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Lowest score of a go hour or night
+const GO_SCORE: f64 = 0.65;
+
+/// Lowest score of a marginal hour or night
+const MARGINAL_SCORE: f64 = 0.4;
+
+/// Score reduction at full Moon, proportional to illumination
+const MOON_PENALTY: f64 = 0.3;
+
+/// Lowest 7timer cloud cover class making the sky overcast, i.e. over 69%
+const OVERCAST_CLASS: u8 = 7;
+
+/// Cloud cover in percent making the sky overcast, as `OVERCAST_CLASS`
+const OVERCAST_PERCENT: f64 = 69.0;
+
+/// Hours covered by each 7timer forecast
+const FORECAST_STEP: i64 = 3;
+
+/// Observing quality
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize, Serialize)]
+pub enum Quality {
+    /// Not worth opening
+    NoGo,
+    /// Usable with limits
+    Marginal,
+    /// Good for observing
+    Go,
+}
+
+impl Quality {
+    /// Returns the quality of a score
+    ///
+    /// * `score`: score from 0 to 1
+    pub fn from_score(score: f64) -> Self {
+        if score >= GO_SCORE {
+            Quality::Go
+        } else if score >= MARGINAL_SCORE {
+            Quality::Marginal
+        } else {
+            Quality::NoGo
+        }
+    }
+}
+
+impl fmt::Display for Quality {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Quality::Go => write!(f, "Go"),
+            Quality::Marginal => write!(f, "Marginal"),
+            Quality::NoGo => write!(f, "No-go"),
+        }
+    }
+}
+
+/// Weights of the forecast values in the score
+///
+/// * `clouds`: cloud cover weight
+/// * `seeing`: seeing weight
+/// * `transparency`: transparency weight
+/// * `humidity`: relative humidity weight
+/// * `wind`: wind speed weight
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct Weights {
+    /// Cloud cover weight
+    pub clouds: f64,
+    /// Seeing weight
+    pub seeing: f64,
+    /// Transparency weight
+    pub transparency: f64,
+    /// Relative humidity weight
+    pub humidity: f64,
+    /// Wind speed weight
+    pub wind: f64,
+}
+
+impl Default for Weights {
+    fn default() -> Self {
+        Weights {
+            clouds: 0.4,
+            seeing: 0.15,
+            transparency: 0.2,
+            humidity: 0.15,
+            wind: 0.1,
+        }
+    }
+}
+
+/// Rated hour
+///
+/// * `time`: UTC time
+/// * `score`: score from 0 to 1, Moon penalty included
+/// * `quality`: quality of the score
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct HourQuality {
+    /// UTC time
+    pub time: DateTime<Utc>,
+    /// Score from 0 to 1, Moon penalty included
+    pub score: f64,
+    /// Quality of the score
+    pub quality: Quality,
+}
+
+/// Rated night
+///
+/// * `date`: date of the evening the night begins
+/// * `moon_illumination`: Moon illuminated fraction
+/// * `hours`: rated hours of astronomical darkness
+/// * `score`: mean score of the hours
+/// * `quality`: quality of the score
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct NightQuality {
+    /// Date of the evening the night begins
+    pub date: NaiveDate,
+    /// Moon illuminated fraction
+    pub moon_illumination: f64,
+    /// Rated hours of astronomical darkness
+    pub hours: Vec<HourQuality>,
+    /// Mean score of the hours
+    pub score: f64,
+    /// Quality of the score
+    pub quality: Quality,
+}
+
+impl NightQuality {
+    /// Returns the number of go hours
+    pub fn go_hours(&self) -> usize {
+        self.hours
+            .iter()
+            .filter(|hour| hour.quality == Quality::Go)
+            .count()
+    }
+}
+
+/// Returns the weather score of a forecast, from 0 to 1, without Moon penalty
+///
+/// Every value is scaled from 0 for the worst class to 1 for the best one; humidity up to
+/// 65% and wind up to 3.4 m/s do not lower the score.
+///
+/// * `forecast`: 7timer astro forecast
+/// * `weights`: weights of the values
+pub fn weather_score(forecast: &Forecast, weights: &Weights) -> f64 {
+    let clouds = forecast.cloud_cover as u8;
+    if clouds >= OVERCAST_CLASS || forecast.prec_type != "none" {
+        return 0.0;
+    }
+    let values = [
+        (weights.clouds, (9 - clouds) as f64 / 8.0),
+        (weights.seeing, (8 - forecast.seeing as u8) as f64 / 7.0),
+        (
+            weights.transparency,
+            (8 - forecast.transparency as u8) as f64 / 7.0,
+        ),
+        (
+            weights.humidity,
+            ((16 - forecast.rh2m as i8) as f64 / 8.0).clamp(0.0, 1.0),
+        ),
+        (
+            weights.wind,
+            [1.0, 1.0, 0.6, 0.3, 0.0, 0.0, 0.0, 0.0][(forecast.wind10m.speed as u8 - 1) as usize],
+        ),
+    ];
+    let total: f64 = values.iter().map(|(weight, _)| weight).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    values
+        .iter()
+        .map(|(weight, value)| weight * value)
+        .sum::<f64>()
+        / total
+}
+
+/// Returns the weather score of a normalized hourly forecast, from 0 to 1, without Moon
+/// penalty
+///
+/// Other providers give no seeing and transparency, so their weights are left out. The
+/// values are scaled as in `weather_score`.
+///
+/// * `forecast`: hourly forecast of any provider
+/// * `weights`: weights of the values
+pub fn hourly_score(forecast: &HourlyForecast, weights: &Weights) -> f64 {
+    if forecast.cloud_cover > OVERCAST_PERCENT || forecast.precipitation.unwrap_or(0.0) > 0.0 {
+        return 0.0;
+    }
+    let wind = match forecast.wind_speed {
+        speed if speed <= 3.4 => 1.0,
+        speed if speed <= 8.0 => 0.6,
+        speed if speed <= 10.8 => 0.3,
+        _ => 0.0,
+    };
+    let values = [
+        (weights.clouds, 1.0 - forecast.cloud_cover / 100.0),
+        (
+            weights.humidity,
+            ((100.0 - forecast.humidity) / 35.0).clamp(0.0, 1.0),
+        ),
+        (weights.wind, wind),
+    ];
+    let total: f64 = values.iter().map(|(weight, _)| weight).sum();
+    if total <= 0.0 {
+        return 0.0;
+    }
+    values
+        .iter()
+        .map(|(weight, value)| weight * value)
+        .sum::<f64>()
+        / total
+}
+
+/// Returns a score lowered by Moon illumination
+///
+/// * `score`: weather score
+/// * `moon_illumination`: Moon illuminated fraction, from 0 to 1
+pub fn moon_penalized(score: f64, moon_illumination: f64) -> f64 {
+    score * (1.0 - MOON_PENALTY * moon_illumination.clamp(0.0, 1.0))
+}
+
+/// Rates the hours of astronomical darkness covered by the forecast, night by night
+///
+/// Each 3-hour forecast is used for its three hours; nights without rated hours are skipped.
+///
+/// * `response`: 7timer astro forecast
+/// * `darkness`: astronomical darkness of the nights
+/// * `weights`: weights of the forecast values
+pub fn rate_nights(
+    response: &ForecastResponse,
+    darkness: &[Darkness],
+    weights: &Weights,
+) -> Vec<NightQuality> {
+    let scores: Vec<(DateTime<Utc>, f64)> = response
+        .dataseries
+        .iter()
+        .filter_map(|forecast| {
+            let time = response.time_of(forecast)?;
+            let score = weather_score(forecast, weights);
+            Some((0..FORECAST_STEP).map(move |hour| (time + Duration::hours(hour), score)))
+        })
+        .flatten()
+        .collect();
+    rate_scores(&scores, darkness)
+}
+
+/// Rates the hours of astronomical darkness of hourly forecasts, night by night
+///
+/// Nights without rated hours are skipped.
+///
+/// * `forecast`: hourly forecast of any provider
+/// * `darkness`: astronomical darkness of the nights
+/// * `weights`: weights of the forecast values
+pub fn rate_hourly_nights(
+    forecast: &[HourlyForecast],
+    darkness: &[Darkness],
+    weights: &Weights,
+) -> Vec<NightQuality> {
+    let scores: Vec<(DateTime<Utc>, f64)> = forecast
+        .iter()
+        .map(|hour| (hour.time, hourly_score(hour, weights)))
+        .collect();
+    rate_scores(&scores, darkness)
+}
+
+/// Groups scored hours by night, applying the Moon penalty
+///
+/// * `scores`: UTC times with their weather score
+/// * `darkness`: astronomical darkness of the nights
+fn rate_scores(scores: &[(DateTime<Utc>, f64)], darkness: &[Darkness]) -> Vec<NightQuality> {
+    darkness
+        .iter()
+        .filter_map(|night| {
+            let hours: Vec<HourQuality> = scores
+                .iter()
+                .filter(|(time, _)| night.contains(*time))
+                .map(|(time, score)| {
+                    let score = moon_penalized(*score, night.moon_illumination);
+                    HourQuality {
+                        time: *time,
+                        score,
+                        quality: Quality::from_score(score),
+                    }
+                })
+                .collect();
+            if hours.is_empty() {
+                return None;
+            }
+            let score = hours.iter().map(|hour| hour.score).sum::<f64>() / hours.len() as f64;
+            Some(NightQuality {
+                date: night.date,
+                moon_illumination: night.moon_illumination,
+                hours,
+                score,
+                quality: Quality::from_score(score),
+            })
+        })
+        .collect()
+}
+
+/// Returns a one line summary of the nights, i.e. "Night quality: 03-27 Go (82%, 6/7 h)"
+///
+/// * `nights`: rated nights
+pub fn summary(nights: &[NightQuality]) -> String {
+    if nights.is_empty() {
+        return "Night quality: no dark hours in the forecast".to_string();
+    }
+    let nights: Vec<String> = nights
+        .iter()
+        .map(|night| {
+            format!(
+                "{} {} ({:.0}%, {}/{} h)",
+                night.date.format("%m-%d"),
+                night.quality,
+                night.score * 100.0,
+                night.go_hours(),
+                night.hours.len()
+            )
+        })
+        .collect();
+    format!("Night quality: {}", nights.join(", "))
+}
+
+/// Rates the nights covered by a 7timer forecast for the observatory in `Settings`
+///
+/// * `response`: 7timer astro forecast
+/// * `weights`: weights of the forecast values
+pub fn rate_forecast(response: &ForecastResponse, weights: &Weights) -> Result<Vec<NightQuality>> {
+    let init = response
+        .init_time()
+        .ok_or_else(|| anyhow!("Invalid init time \"{}\"", response.init))?;
+    // The night before the forecast run may still be dark at its beginning
+    let darkness = sun_moon_times::prepare_darkness(init.date_naive() - Duration::days(1), 5)?;
+    Ok(rate_nights(response, &darkness, weights))
+}
+
+/// Rates the nights covered by hourly forecasts for the observatory in `Settings`
+///
+/// * `forecast`: hourly forecast of any provider
+/// * `weights`: weights of the forecast values
+pub fn rate_hourly_forecast(
+    forecast: &[HourlyForecast],
+    weights: &Weights,
+) -> Result<Vec<NightQuality>> {
+    let first = forecast
+        .first()
+        .ok_or_else(|| anyhow!("Empty hourly forecast"))?;
+    let darkness =
+        sun_moon_times::prepare_darkness(first.time.date_naive() - Duration::days(1), 5)?;
+    Ok(rate_hourly_nights(forecast, &darkness, weights))
+}
+
+/// Returns the rated nights of the forecast for the observatory in `Settings`
+///
+/// The 7timer astro forecast is used when available, otherwise the first other provider
+/// answering.
+///
+/// * `weights`: weights of the forecast values
+pub fn prepare_night_quality(weights: &Weights) -> Result<Vec<NightQuality>> {
+    if let Ok(response) = weather::prepare_data() {
+        return rate_forecast(&response, weights);
+    }
+    let settings = Settings::new()?;
+    let (_, forecast) = weather_providers::fallback_forecast(
+        *settings.get_latitude() as f64,
+        *settings.get_longitude() as f64,
+        *settings.get_altitude() as f64,
+    )?;
+    rate_hourly_forecast(&forecast, weights)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns a forecast run of 2024-03-27 18 UTC with one clear night and one rainy night
+    fn response() -> ForecastResponse {
+        let forecast = |timepoint: i32, cloudcover: u8, prec_type: &str| {
+            format!(
+                r#"{{"timepoint": {}, "cloudcover": {}, "seeing": 3, "transparency": 3,
+                "lifted_index": 2, "rh2m": 6, "wind10m": {{"direction": "N", "speed": 2}},
+                "temp2m": 8, "prec_type": "{}"}}"#,
+                timepoint, cloudcover, prec_type
+            )
+        };
+        let dataseries: Vec<String> = (1..=16)
+            .map(|step| {
+                let timepoint = step * 3;
+                if timepoint <= 12 {
+                    forecast(timepoint, 1, "none")
+                } else if timepoint <= 24 {
+                    forecast(timepoint, 4, "none")
+                } else {
+                    forecast(timepoint, 9, "rain")
+                }
+            })
+            .collect();
+        serde_json::from_str(&format!(
+            r#"{{"product": "astro", "init": "2024032718", "dataseries": [{}]}}"#,
+            dataseries.join(",")
+        ))
+        .unwrap()
+    }
+
+    fn night(date: NaiveDate, begin: i64, hours: i64, moon_illumination: f64) -> Darkness {
+        let begin = date.and_hms_opt(0, 0, 0).unwrap().and_utc() + Duration::hours(begin);
+        Darkness {
+            date,
+            begin: Some(begin),
+            end: Some(begin + Duration::hours(hours)),
+            polar_night: false,
+            moon_illumination,
+        }
+    }
+
+    #[test]
+    fn test_weather_score() {
+        let response = response();
+        let weights = Weights::default();
+        let clear = weather_score(&response.dataseries[0], &weights);
+        assert!((clear - 0.9).abs() < 0.01);
+        assert_eq!(Quality::from_score(clear), Quality::Go);
+        assert_eq!(
+            Quality::from_score(moon_penalized(clear, 1.0)),
+            Quality::Marginal
+        );
+        let hazy = weather_score(&response.dataseries[4], &weights);
+        assert!(hazy < clear);
+        assert_eq!(weather_score(&response.dataseries[10], &weights), 0.0);
+        let fixture: ForecastResponse = serde_json::from_str(
+            &std::fs::read_to_string("response_examples/7timer.json").unwrap(),
+        )
+        .unwrap();
+        assert!(fixture
+            .dataseries
+            .iter()
+            .map(|forecast| weather_score(forecast, &weights))
+            .all(|score| (0.0..=1.0).contains(&score)));
+    }
+
+    #[test]
+    fn test_hourly_score() {
+        let weights = Weights::default();
+        let time = NaiveDate::from_ymd_opt(2024, 3, 27)
+            .unwrap()
+            .and_hms_opt(22, 0, 0)
+            .unwrap()
+            .and_utc();
+        let clear = HourlyForecast {
+            time,
+            cloud_cover: 3.0,
+            humidity: 55.0,
+            wind_speed: 1.8,
+            temperature: 8.0,
+            dew_point: 0.0,
+            precipitation: Some(0.0),
+        };
+        assert!(hourly_score(&clear, &weights) > 0.95);
+        let rain = HourlyForecast {
+            time: time + Duration::hours(1),
+            precipitation: Some(0.4),
+            ..clear
+        };
+        assert_eq!(hourly_score(&rain, &weights), 0.0);
+        let darkness = vec![night(time.date_naive(), 21, 8, 0.0)];
+        let nights = rate_hourly_nights(&[clear, rain], &darkness, &weights);
+        assert_eq!(nights[0].hours.len(), 2);
+        assert_eq!(nights[0].hours[1].quality, Quality::NoGo);
+        assert_eq!(nights[0].go_hours(), 1);
+    }
+
+    #[test]
+    fn test_rate_nights() {
+        let response = response();
+        let first = NaiveDate::from_ymd_opt(2024, 3, 27).unwrap();
+        let darkness = vec![
+            night(first, 21, 8, 0.1),
+            night(first + Duration::days(1), 21, 8, 0.1),
+            night(first + Duration::days(5), 21, 8, 0.1),
+        ];
+        let nights = rate_nights(&response, &darkness, &Weights::default());
+        assert_eq!(nights.len(), 2);
+        // Clear until 07 UTC, then hazy
+        assert_eq!(nights[0].hours.len(), 9);
+        assert_eq!(
+            nights[0].hours[0].time.to_rfc3339(),
+            "2024-03-27T21:00:00+00:00"
+        );
+        assert_eq!(nights[0].quality, Quality::Go);
+        assert_eq!(nights[0].go_hours(), 9);
+        assert_eq!(nights[1].quality, Quality::NoGo);
+        assert!(summary(&nights).starts_with("Night quality: 03-27 Go ("));
+        assert!(summary(&nights).ends_with("03-28 No-go (0%, 0/9 h)"));
+        assert_eq!(summary(&[]), "Night quality: no dark hours in the forecast");
+    }
+}
@@ -1,7 +1,8 @@
+use crate::coordinates::{self, Observer};
+use crate::designation::Designation;
 use crate::settings::Settings;
 use anyhow::{anyhow, Result};
-use chrono::{Datelike, Timelike, Utc};
-use percent_encoding::percent_decode_str;
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
 use reqwest;
 use serde::{Deserialize, Serialize};
 //use serde_json::Result;
@@ -9,171 +10,533 @@ use serde::{Deserialize, Serialize};
 //use std::fmt::Display;
 //use std::{fmt, thread::current};
 
+/// Target position structure
+///
+/// * `time`: Time of the position
+/// * `ra`: Object RA in degrees
+/// * `dec`: Object Dec in degrees
+/// * `altitude`: Object altitude in degrees
+#[derive(Debug, Clone, Copy, Default, Deserialize, Serialize)]
+pub struct TargetPosition {
+    /// Time of the position
+    pub time: DateTime<Utc>,
+    /// Object RA in degrees
+    pub ra: f64,
+    /// Object Dec in degrees
+    pub dec: f64,
+    /// Object altitude in degrees
+    pub altitude: f64,
+}
+
 /// Possible target structure
 ///
 /// * `designation`: Object designation
-/// * `ra`: Object RA
-/// * `dec`: Object Dec
 /// * `magnitude`: Object magnitude
-/// * `altitude`: Object altitude
+/// * `solar_elongation`: Object solar elongation in degrees
+/// * `lunar_elongation`: Object lunar elongation in degrees
+/// * `begin`: Object position at the start of the observation
+/// * `max`: Object position at the maximum altitude during the observation
+/// * `end`: Object position at the end of the observation
+/// * `azimuth`: Object azimuth at the start of the observation
+/// * `blocked`: Reason why the object is below the horizon limit, if it is
 #[derive(Debug, Deserialize, Serialize)]
 pub struct PossibleTarget {
     /// Object designation
-    pub designation: String,
-    /// Object RA
-    pub ra: String,
-    /// Object Dec
-    pub dec: String,
+    pub designation: Designation,
     /// Object magnitude
     pub magnitude: f32,
-    /// Object altitude
-    pub altitude: f32,
+    /// Object solar elongation in degrees
+    pub solar_elongation: f32,
+    /// Object lunar elongation in degrees
+    pub lunar_elongation: f32,
+    /// Object position at the start of the observation
+    pub begin: TargetPosition,
+    /// Object position at the maximum altitude during the observation
+    pub max: TargetPosition,
+    /// Object position at the end of the observation
+    pub end: TargetPosition,
+    /// Object azimuth at the start of the observation
+    pub azimuth: f32,
+    /// Reason why the object is below the horizon limit, if it is
+    pub blocked: Option<String>,
+}
+
+/// Object types of what's up list
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize, Serialize)]
+pub enum ObjectType {
+    /// Minor planets
+    MinorPlanet,
+    /// Near Earth objects
+    Neo,
+    /// Comets
+    Comet,
+}
+
+impl ObjectType {
+    /// Returns the code used by MPC form
+    pub fn code(&self) -> &'static str {
+        match self {
+            ObjectType::MinorPlanet => "mp",
+            ObjectType::Neo => "neo",
+            ObjectType::Comet => "cmt",
+        }
+    }
+}
+
+impl std::str::FromStr for ObjectType {
+    type Err = anyhow::Error;
+
+    fn from_str(value: &str) -> Result<Self> {
+        match value {
+            "mp" => Ok(ObjectType::MinorPlanet),
+            "neo" => Ok(ObjectType::Neo),
+            "cmt" => Ok(ObjectType::Comet),
+            _ => Err(anyhow!("{} is not a valid object type", value)),
+        }
+    }
 }
 
-/// Request parameters struct
+/// Request parameters struct, created with `WhatsUpParamsBuilder`
 ///
-/// * `year`: Year of scheduled observation
-/// * `month`: Month of scheduled observation
-/// * `day`: Day of scheduled observation
-/// * `hour`: Hour of scheduled observation
-/// * `minute`: Minutes of scheduled observation
-/// * `duration`: Duration of scheduled observation
+/// * `start`: Start of scheduled observation
+/// * `duration`: Duration of scheduled observation in hours
 /// * `max_objects`: Maximum number of object to retrieve
-/// * `min_alt`: Minimum Altitude of object
-/// * `solar_elong`: Minimum Solar elongation
-/// * `lunar_elong`: Minimum Lunar elongation
+/// * `min_alt`: Minimum Altitude of object in degrees
+/// * `solar_elong`: Minimum Solar elongation in degrees
+/// * `lunar_elong`: Minimum Lunar elongation in degrees
 /// * `object_type`: Object type
-#[derive(Debug)]
+/// * `max_magnitude`: Faintest magnitude to keep, i.e. the limit of the active equipment
+#[derive(Debug, Clone)]
 pub struct WhatsUpParams {
-    /// Year of scheduled observation
-    pub year: String,
-    /// Month of scheduled observation
-    pub month: String,
-    /// Day of scheduled observation
-    pub day: String,
-    /// Hour of scheduled observation
-    pub hour: String,
-    /// Minute of scheduled observation
-    pub minute: String,
-    /// Duration of scheduled observation
-    pub duration: String,
+    /// Start of scheduled observation
+    start: DateTime<Utc>,
+    /// Duration of scheduled observation in hours
+    duration: u32,
     /// Maximum number of object to retrieve
-    pub max_objects: String,
-    /// Minimum Altitude of object
-    pub min_alt: String,
-    /// Minimum Solar elongation
-    pub solar_elong: String,
-    /// Minimum Lunar elongation
-    pub lunar_elong: String,
+    max_objects: u32,
+    /// Minimum Altitude of object in degrees
+    min_alt: u32,
+    /// Minimum Solar elongation in degrees
+    solar_elong: u32,
+    /// Minimum Lunar elongation in degrees
+    lunar_elong: u32,
     /// Object type
-    pub object_type: String,
+    object_type: ObjectType,
+    /// Faintest magnitude to keep, i.e. the limit of the active equipment
+    max_magnitude: Option<f32>,
 }
 
 impl Default for WhatsUpParams {
     fn default() -> Self {
-        let current_datetime = Utc::now();
-        let params: WhatsUpParams = WhatsUpParams {
-            year: current_datetime.year().to_string(),
-            month: current_datetime.month().to_string(),
-            day: current_datetime.day().to_string(),
-            minute: current_datetime.minute().to_string(),
-            hour: current_datetime.hour().to_string(),
-            duration: "1".to_string(),
-            max_objects: "10".to_string(),
-            min_alt: "10".to_string(),
-            solar_elong: "0".to_string(),
-            lunar_elong: "0".to_string(),
-            object_type: "mp".to_string(),
-        };
-        params
+        WhatsUpParamsBuilder::default()
+            .build()
+            .expect("Default parameters are valid")
+    }
+}
+
+impl WhatsUpParams {
+    /// Returns a builder starting from the default parameters
+    pub fn builder() -> WhatsUpParamsBuilder {
+        WhatsUpParamsBuilder::default()
+    }
+
+    /// Returns the start of the scheduled observation
+    pub fn start(&self) -> DateTime<Utc> {
+        self.start
+    }
+
+    /// Returns the duration of the scheduled observation in hours
+    pub fn duration(&self) -> u32 {
+        self.duration
+    }
+
+    /// Returns the maximum number of object to retrieve
+    pub fn max_objects(&self) -> u32 {
+        self.max_objects
+    }
+
+    /// Returns the minimum altitude of object in degrees
+    pub fn min_alt(&self) -> u32 {
+        self.min_alt
+    }
+
+    /// Returns the minimum solar elongation in degrees
+    pub fn solar_elong(&self) -> u32 {
+        self.solar_elong
+    }
+
+    /// Returns the minimum lunar elongation in degrees
+    pub fn lunar_elong(&self) -> u32 {
+        self.lunar_elong
+    }
+
+    /// Returns the object type
+    pub fn object_type(&self) -> ObjectType {
+        self.object_type
+    }
+
+    /// Returns the faintest magnitude to keep, if any
+    pub fn max_magnitude(&self) -> Option<f32> {
+        self.max_magnitude
+    }
+
+    /// Returns true if a target of magnitude `magnitude` is bright enough
+    ///
+    /// * `magnitude`: target magnitude
+    pub fn is_bright_enough(&self, magnitude: f32) -> bool {
+        self.max_magnitude.is_none_or(|limit| magnitude <= limit)
+    }
+
+    /// Returns the parameters as MPC form fields
+    pub fn to_form_params(&self) -> Vec<(&'static str, String)> {
+        vec![
+            ("year", self.start.year().to_string()),
+            ("month", self.start.month().to_string()),
+            ("day", self.start.day().to_string()),
+            ("hour", self.start.hour().to_string()),
+            ("minute", self.start.minute().to_string()),
+            ("duration", self.duration.to_string()),
+            ("max_objects", self.max_objects.to_string()),
+            ("min_alt", self.min_alt.to_string()),
+            ("solar_elong", self.solar_elong.to_string()),
+            ("lunar_elong", self.lunar_elong.to_string()),
+            ("object_type", self.object_type.code().to_string()),
+        ]
+    }
+}
+
+/// Request parameters builder, validating all values in `build`
+///
+/// Defaults are now, one hour, ten minor planets at least 10° high
+#[derive(Debug, Clone)]
+pub struct WhatsUpParamsBuilder {
+    year: i32,
+    month: u32,
+    day: u32,
+    hour: u32,
+    minute: u32,
+    duration: u32,
+    max_objects: u32,
+    min_alt: u32,
+    solar_elong: u32,
+    lunar_elong: u32,
+    object_type: ObjectType,
+    max_magnitude: Option<f32>,
+}
+
+impl Default for WhatsUpParamsBuilder {
+    fn default() -> Self {
+        WhatsUpParamsBuilder {
+            year: 0,
+            month: 0,
+            day: 0,
+            hour: 0,
+            minute: 0,
+            duration: 1,
+            max_objects: 10,
+            min_alt: 10,
+            solar_elong: 0,
+            lunar_elong: 0,
+            object_type: ObjectType::MinorPlanet,
+            max_magnitude: None,
+        }
+        .start(Utc::now())
+    }
+}
+
+impl WhatsUpParamsBuilder {
+    /// Sets date and time of scheduled observation, seconds are dropped
+    ///
+    /// * `start`: start of scheduled observation
+    pub fn start(self, start: DateTime<Utc>) -> Self {
+        self.date(start.year(), start.month(), start.day())
+            .time(start.hour(), start.minute())
+    }
+
+    /// Sets date of scheduled observation
+    ///
+    /// * `year`: year
+    /// * `month`: month, from 1
+    /// * `day`: day of month, from 1
+    pub fn date(mut self, year: i32, month: u32, day: u32) -> Self {
+        (self.year, self.month, self.day) = (year, month, day);
+        self
+    }
+
+    /// Sets UTC time of scheduled observation
+    ///
+    /// * `hour`: hour
+    /// * `minute`: minute
+    pub fn time(mut self, hour: u32, minute: u32) -> Self {
+        (self.hour, self.minute) = (hour, minute);
+        self
+    }
+
+    /// Sets duration of scheduled observation
+    ///
+    /// * `duration`: duration in hours, from 1 to 24
+    pub fn duration(mut self, duration: u32) -> Self {
+        self.duration = duration;
+        self
+    }
+
+    /// Sets maximum number of object to retrieve
+    ///
+    /// * `max_objects`: maximum number of objects, at least 1
+    pub fn max_objects(mut self, max_objects: u32) -> Self {
+        self.max_objects = max_objects;
+        self
+    }
+
+    /// Sets minimum altitude of object
+    ///
+    /// * `min_alt`: minimum altitude in degrees, up to 90
+    pub fn min_alt(mut self, min_alt: u32) -> Self {
+        self.min_alt = min_alt;
+        self
+    }
+
+    /// Sets minimum solar elongation
+    ///
+    /// * `solar_elong`: minimum solar elongation in degrees, up to 180
+    pub fn solar_elong(mut self, solar_elong: u32) -> Self {
+        self.solar_elong = solar_elong;
+        self
+    }
+
+    /// Sets minimum lunar elongation
+    ///
+    /// * `lunar_elong`: minimum lunar elongation in degrees, up to 180
+    pub fn lunar_elong(mut self, lunar_elong: u32) -> Self {
+        self.lunar_elong = lunar_elong;
+        self
+    }
+
+    /// Sets object type
+    ///
+    /// * `object_type`: object type
+    pub fn object_type(mut self, object_type: ObjectType) -> Self {
+        self.object_type = object_type;
+        self
+    }
+
+    /// Sets faintest magnitude to keep
+    ///
+    /// * `max_magnitude`: limiting magnitude, i.e. from `Equipment::limiting_magnitude`
+    pub fn max_magnitude(mut self, max_magnitude: f32) -> Self {
+        self.max_magnitude = Some(max_magnitude);
+        self
+    }
+
+    /// Validates the values, returning the request parameters
+    pub fn build(self) -> Result<WhatsUpParams> {
+        let date = NaiveDate::from_ymd_opt(self.year, self.month, self.day).ok_or_else(|| {
+            anyhow!(
+                "{:04}-{:02}-{:02} is not a valid date",
+                self.year,
+                self.month,
+                self.day
+            )
+        })?;
+        let start = date
+            .and_hms_opt(self.hour, self.minute, 0)
+            .ok_or_else(|| anyhow!("{:02}:{:02} is not a valid time", self.hour, self.minute))?
+            .and_utc();
+        if !(1..=24).contains(&self.duration) {
+            return Err(anyhow!("Duration must be between 1 and 24 hours"));
+        }
+        if self.max_objects == 0 {
+            return Err(anyhow!("Maximum number of objects must be at least 1"));
+        }
+        if self.min_alt > 90 {
+            return Err(anyhow!("Minimum altitude must be at most 90°"));
+        }
+        if self.solar_elong > 180 || self.lunar_elong > 180 {
+            return Err(anyhow!("Minimum elongations must be at most 180°"));
+        }
+        Ok(WhatsUpParams {
+            start,
+            duration: self.duration,
+            max_objects: self.max_objects,
+            min_alt: self.min_alt,
+            solar_elong: self.solar_elong,
+            lunar_elong: self.lunar_elong,
+            object_type: self.object_type,
+            max_magnitude: self.max_magnitude,
+        })
     }
 }
 
 impl Default for PossibleTarget {
     fn default() -> Self {
         PossibleTarget {
-            designation: "None".to_string(),
-            ra: "None".to_string(),
-            dec: "None".to_string(),
+            designation: Designation::default(),
             magnitude: 0.0,
-            altitude: 0.0,
+            solar_elongation: 0.0,
+            lunar_elongation: 0.0,
+            begin: TargetPosition::default(),
+            max: TargetPosition::default(),
+            end: TargetPosition::default(),
+            azimuth: 0.0,
+            blocked: None,
         }
     }
 }
 
+/// MPC what's up form, fetched for the session and posted with the parameters
+const WHATS_UP_URL: &str = "https://www.minorplanetcenter.net/whatsup/index";
+
+/// Extracts the authenticity token from the what's up form
+///
+/// * `data`: HTML page of the form
+fn extract_authenticity_token(data: &str) -> Result<String> {
+    let document = scraper::Html::parse_document(data);
+    let token_selector = scraper::Selector::parse("input[name=authenticity_token]")
+        .map_err(|e| anyhow!("Failed to parse selector: {}", e))?;
+    document
+        .select(&token_selector)
+        .next()
+        .and_then(|input| input.value().attr("value"))
+        .map(|token| token.to_string())
+        .ok_or_else(|| anyhow!("Authenticity token not found in MPC what's up form"))
+}
+
 /// Gets raw observing target list from MPC
 ///
+/// The form is fetched first, so that the post uses its session cookies and authenticity token
+///
 /// * `params`: WhatsupParams struct with all requested parameters
-fn get_observing_target_list(params: &WhatsUpParams) -> String {
+fn get_observing_target_list(params: &WhatsUpParams) -> Result<String> {
     let settings = Settings::new().unwrap();
-    let mut full_params: Vec<(&str, &str)> = Vec::new();
-    let encoded_param = "%E2%9C%93";
-    //full_params.push(("utf8", "%E2%9C%93"));
-    let decoded = percent_decode_str(encoded_param)
-        .decode_utf8_lossy()
-        .into_owned();
-    full_params.push(("utf8", decoded.as_str()));
-    let auth_token = "W5eBzzw9Clj4tJVzkz0z%2F2EK18jvSS%2BffHxZpAshylg%3D";
-    let decoded_auth_token = percent_decode_str(auth_token)
-        .decode_utf8_lossy()
-        .into_owned();
-    full_params.push(("authenticity_token", decoded_auth_token.as_str()));
-    let latitude = settings.get_latitude().to_string();
-    full_params.push(("latitude", latitude.as_str()));
-    let longitude = settings.get_longitude().to_string();
-    full_params.push(("longitude", longitude.as_str()));
-    full_params.push(("year", params.year.as_str()));
-    full_params.push(("month", params.month.as_str()));
-    full_params.push(("day", params.day.as_str()));
-    full_params.push(("hour", params.hour.as_str()));
-    full_params.push(("minute", params.minute.as_str()));
-    full_params.push(("duration", params.duration.as_str()));
-    full_params.push(("max_objects", params.max_objects.as_str()));
-    full_params.push(("min_alt", params.min_alt.as_str()));
-    full_params.push(("solar_elong", params.solar_elong.as_str()));
-    full_params.push(("lunar_elong", params.lunar_elong.as_str()));
-    full_params.push(("object_type", params.object_type.as_str()));
-    full_params.push(("submit", "Submit"));
-    let url: reqwest::Url = reqwest::Url::parse_with_params(
-        "https://www.minorplanetcenter.net/whatsup/index",
-        full_params,
-    )
-    .expect("Failed to create url");
-    let client = reqwest::blocking::Client::new();
-    client
-        .post(url)
-        .send()
-        .expect("Failed on api call")
-        .text()
-        .expect("Failed to convert to text")
-}
-
-//TODO: Add altitude filtering on different directions
-//TODO: Write better documentation
+    let client = reqwest::blocking::Client::builder()
+        .cookie_store(true)
+        .build()?;
+    let form = client
+        .get(WHATS_UP_URL)
+        .send()?
+        .error_for_status()?
+        .text()?;
+    let auth_token = extract_authenticity_token(&form)?;
+    let mut full_params: Vec<(&str, String)> = vec![
+        ("utf8", "✓".to_string()),
+        ("authenticity_token", auth_token),
+        ("latitude", settings.get_latitude().to_string()),
+        ("longitude", settings.get_longitude().to_string()),
+    ];
+    full_params.extend(params.to_form_params());
+    full_params.push(("submit", "Submit".to_string()));
+    let response = client.post(WHATS_UP_URL).form(&full_params).send()?;
+    if response.status() == reqwest::StatusCode::UNPROCESSABLE_ENTITY {
+        return Err(anyhow!(
+            "MPC rejected the authenticity token of the session"
+        ));
+    }
+    Ok(response.error_for_status()?.text()?)
+}
 
 /// Returns data from what's up list of MPC
 ///
+/// Objects below the horizon limit of their direction are kept, flagged as blocked, objects
+/// fainter than the magnitude limit of the parameters are dropped.
+///
 /// * `params`: WhatsupParams struct with all requested parameters
-pub fn parse_whats_up_response(params: &WhatsUpParams) -> Vec<PossibleTarget> {
-    let mut objects: Vec<PossibleTarget> = Vec::new();
-    let data = get_observing_target_list(params);
-    let document = scraper::Html::parse_document(data.as_str());
+pub fn parse_whats_up_response(params: &WhatsUpParams) -> Result<Vec<PossibleTarget>> {
+    let data = get_observing_target_list(params)?;
+    let mut objects = parse_whats_up_document(&data)?;
+    objects.retain(|object| params.is_bright_enough(object.magnitude));
+    apply_horizon_limits(&mut objects, params.start(), &Settings::new().unwrap());
+    Ok(objects)
+}
+
+/// Flags the targets below the horizon profile of the observatory at `time`
+///
+/// * `targets`: targets to be checked
+/// * `time`: UTC time of the observation
+/// * `settings`: settings with observatory location and horizon profile
+pub fn apply_horizon_limits(
+    targets: &mut [PossibleTarget],
+    time: DateTime<Utc>,
+    settings: &Settings,
+) {
+    let observer = Observer::from_settings(settings);
+    let horizon = settings.get_horizon();
+    targets.iter_mut().for_each(|target| {
+        let position =
+            coordinates::apparent_horizontal(target.begin.ra, target.begin.dec, time, &observer);
+        let limit = horizon.altitude_at(position.azimuth);
+        target.azimuth = position.azimuth as f32;
+        target.blocked = (target.begin.altitude <= limit).then(|| {
+            format!(
+                "{:.1}° below horizon of {:.1}° at azimuth {:.0}°",
+                target.begin.altitude, limit, position.azimuth
+            )
+        });
+    });
+}
+
+/// Parses the HTML page of what's up list of MPC
+///
+/// Returns an error if the page has no results table, i.e. MPC answered with the bare form
+/// because the authenticity token was stale.
+///
+/// * `data`: HTML page returned by MPC
+fn parse_whats_up_document(data: &str) -> Result<Vec<PossibleTarget>> {
+    let document = scraper::Html::parse_document(data);
+    let header_selector = scraper::Selector::parse("th").unwrap();
+    if !document
+        .select(&header_selector)
+        .any(|header| header.text().collect::<String>().trim() == "Designation")
+    {
+        return Err(anyhow!(
+            "MPC returned the what's up form without results, the session token may be stale"
+        ));
+    }
     let table_item_selector = scraper::Selector::parse("td").unwrap();
     let rows_selector =
         scraper::Selector::parse("#main table:nth-child(1) tr:not(:first-child)").unwrap();
-    let rows: Vec<scraper::ElementRef<'_>> = document.select(&rows_selector).collect();
-    rows.into_iter().for_each(|row| {
-        let cells: Vec<scraper::ElementRef<'_>> = row.select(&table_item_selector).collect();
-        objects.push(create_possible_target(cells).unwrap())
-    });
-    objects
+    document
+        .select(&rows_selector)
+        .map(|row| create_possible_target(row.select(&table_item_selector).collect()))
+        .collect()
 }
 
-fn create_possible_target(item: Vec<scraper::ElementRef<'_>>) -> Result<PossibleTarget> {
-    let mut possible_target = PossibleTarget::default();
+/// Returns the trimmed text of a table cell
+///
+/// * `item`: cells of the row
+/// * `index`: index of the cell
+fn cell_text(item: &[scraper::ElementRef<'_>], index: usize) -> String {
+    item[index].text().collect::<String>().trim().to_string()
+}
+
+/// Parses a table cell as number
+///
+/// * `item`: cells of the row
+/// * `index`: index of the cell
+/// * `name`: name of the column, for errors
+fn parse_number(item: &[scraper::ElementRef<'_>], index: usize, name: &str) -> Result<f64> {
+    cell_text(item, index)
+        .parse::<f64>()
+        .map_err(|e| anyhow!("Failed to parse {}: {}", name, e))
+}
+
+/// Parses four table cells, time, RA, Dec and altitude, as target position
+///
+/// * `item`: cells of the row
+/// * `index`: index of the time cell
+fn parse_position(item: &[scraper::ElementRef<'_>], index: usize) -> Result<TargetPosition> {
+    Ok(TargetPosition {
+        time: DateTime::parse_from_rfc3339(&cell_text(item, index))
+            .map_err(|e| anyhow!("Failed to parse time: {}", e))?
+            .with_timezone(&Utc),
+        ra: coordinates::parse_ra(&cell_text(item, index + 1))?,
+        dec: coordinates::parse_dec(&cell_text(item, index + 2))?,
+        altitude: parse_number(item, index + 3, "altitude")?,
+    })
+}
 
-    // Verifica che ci siano abbastanza elementi
-    if item.len() < 8 {
+fn create_possible_target(item: Vec<scraper::ElementRef<'_>>) -> Result<PossibleTarget> {
+    // Designation, magnitude, elongations, then begin, max and end positions
+    if item.len() < 16 {
         return Err(anyhow!("Not enough elements in input vector"));
     }
 
@@ -185,22 +548,16 @@ fn create_possible_target(item: Vec<scraper::ElementRef<'_>>) -> Result<Possible
         .next()
         .ok_or_else(|| anyhow!("Designation element not found"))?;
 
-    possible_target.designation = designation.inner_html();
-
-    possible_target.magnitude = item[2]
-        .inner_html()
-        .parse::<f32>()
-        .map_err(|e| anyhow!("Failed to parse magnitude: {}", e))?;
-
-    possible_target.altitude = item[7]
-        .inner_html()
-        .parse::<f32>()
-        .map_err(|e| anyhow!("Failed to parse altitude: {}", e))?;
-
-    possible_target.ra = item[5].inner_html();
-    possible_target.dec = item[6].inner_html();
-
-    Ok(possible_target)
+    Ok(PossibleTarget {
+        designation: Designation::parse(&designation.text().collect::<String>())?,
+        magnitude: parse_number(&item, 1, "magnitude")? as f32,
+        solar_elongation: parse_number(&item, 2, "solar elongation")? as f32,
+        lunar_elongation: parse_number(&item, 3, "lunar elongation")? as f32,
+        begin: parse_position(&item, 4)?,
+        max: parse_position(&item, 8)?,
+        end: parse_position(&item, 12)?,
+        ..PossibleTarget::default()
+    })
 }
 
 #[cfg(test)]
@@ -209,12 +566,116 @@ mod test {
 
     #[test]
     fn test_get_observing_target_list() {
-        let result = get_observing_target_list(&WhatsUpParams::default());
+        let result = get_observing_target_list(&WhatsUpParams::default()).unwrap();
         assert!(result.contains("Designation"));
     }
 
+    #[test]
+    fn test_parse_whats_up_document() {
+        let data = std::fs::read_to_string("response_examples/whatsup.html").unwrap();
+        let targets = parse_whats_up_document(&data).unwrap();
+        assert_eq!(targets.len(), 10);
+        let eunomia = &targets[0];
+        assert_eq!(eunomia.designation.number, Some(15));
+        assert_eq!(eunomia.designation.to_string(), "(15) Eunomia");
+        assert_eq!(eunomia.magnitude, 9.0);
+        assert_eq!(eunomia.solar_elongation, 141.0);
+        assert_eq!(eunomia.lunar_elongation, 51.0);
+        assert_eq!(eunomia.begin.time.to_rfc3339(), "2025-01-15T00:00:00+00:00");
+        assert!((eunomia.begin.ra - 74.525833).abs() < 1e-5);
+        assert!((eunomia.begin.dec - 29.505).abs() < 1e-9);
+        assert_eq!(eunomia.max.altitude, 48.2);
+        assert_eq!(eunomia.end.time.to_rfc3339(), "2025-01-15T01:00:00+00:00");
+        assert!((eunomia.end.dec - 29.499444).abs() < 1e-5);
+        assert_eq!(eunomia.end.altitude, 37.4);
+    }
+
+    #[test]
+    fn test_extract_authenticity_token() {
+        let data = std::fs::read_to_string("response_examples/whatsup.html").unwrap();
+        assert_eq!(
+            extract_authenticity_token(&data).unwrap(),
+            "6jL1Ruhw/ENf7P8I7VSi5YgwcNKf8+8ps2vvYtjf/Us="
+        );
+        assert!(extract_authenticity_token("<html></html>").is_err());
+    }
+
+    #[test]
+    fn test_stale_token_response() {
+        let data = std::fs::read_to_string("response_examples/whatsup.html").unwrap();
+        // The form page without the results table is what MPC returns for a stale token
+        let form = &data[..data.find("<div style=\"float: none\">").unwrap()];
+        assert!(parse_whats_up_document(form).is_err());
+    }
+
+    fn test_settings(south_altitude: i32, west_altitude: i32) -> Settings {
+        let mut settings = Settings::new().unwrap();
+        settings.observatory.latitude = 44.09727;
+        settings.observatory.longitude = 9.7737;
+        settings.observatory.north_altitude = 0;
+        settings.observatory.south_altitude = south_altitude;
+        settings.observatory.east_altitude = 0;
+        settings.observatory.west_altitude = west_altitude;
+        settings
+    }
+
+    #[test]
+    fn test_apply_horizon_limits() {
+        let data = std::fs::read_to_string("response_examples/whatsup.html").unwrap();
+        let params = WhatsUpParams::builder()
+            .date(2025, 1, 15)
+            .time(0, 0)
+            .build()
+            .unwrap();
+        let time = params.start();
+        let mut targets = parse_whats_up_document(&data).unwrap();
+        apply_horizon_limits(&mut targets, time, &test_settings(0, 0));
+        assert!(targets.iter().all(|target| target.blocked.is_none()));
+        // (15) Eunomia is setting in the west at 48° of altitude
+        assert!((225.0..315.0).contains(&targets[0].azimuth));
+        apply_horizon_limits(&mut targets, time, &test_settings(0, 50));
+        assert!(targets[0]
+            .blocked
+            .as_ref()
+            .unwrap()
+            .contains("below horizon of 50.0°"));
+        apply_horizon_limits(&mut targets, time, &test_settings(50, 0));
+        assert!(targets[0].blocked.is_none());
+    }
+
+    #[test]
+    fn test_whats_up_params_builder() {
+        let params = WhatsUpParams::builder()
+            .date(2024, 2, 29)
+            .time(21, 30)
+            .duration(3)
+            .object_type(ObjectType::Neo)
+            .build()
+            .unwrap();
+        let form_params = params.to_form_params();
+        assert!(form_params.contains(&("day", "29".to_string())));
+        assert!(form_params.contains(&("minute", "30".to_string())));
+        assert!(form_params.contains(&("duration", "3".to_string())));
+        assert!(form_params.contains(&("object_type", "neo".to_string())));
+        assert!(WhatsUpParams::builder().date(2025, 2, 30).build().is_err());
+        assert!(WhatsUpParams::builder().time(24, 0).build().is_err());
+        assert!(WhatsUpParams::builder().duration(0).build().is_err());
+        assert!(WhatsUpParams::builder().min_alt(91).build().is_err());
+        assert!(params.is_bright_enough(21.0));
+        let limited = WhatsUpParams::builder()
+            .max_magnitude(19.5)
+            .build()
+            .unwrap();
+        assert_eq!(limited.max_magnitude(), Some(19.5));
+        assert!(limited.is_bright_enough(19.5));
+        assert!(!limited.is_bright_enough(19.6));
+        assert_eq!("cmt".parse::<ObjectType>().unwrap(), ObjectType::Comet);
+    }
+
     #[test]
     fn test_parse_whats_up_response() {
-        assert!(!parse_whats_up_response(&WhatsUpParams::default()).is_empty());
+        assert!(!parse_whats_up_response(&WhatsUpParams::default())
+            .unwrap()
+            .is_empty());
     }
 }
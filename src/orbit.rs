@@ -0,0 +1,511 @@
+//! # Orbit
+//!
+//! Library for minor planet orbits in the MPCORB.DAT format of the Minor Planet Center
+//!
+//! MPCORB.DAT can be downloaded from <https://minorplanetcenter.net/iau/MPCORB/MPCORB.DAT.gz>
+//! and unpacked next to `config.toml`, or reduced to a subset of lines: the header is
+//! skipped and every line with valid elements is loaded. Orbits are propagated as
+//! unperturbed two-body Keplerian ellipses, which is accurate to a few arcminutes for
+//! some months around the epoch of the elements.
+//!
+//! Positions are heliocentric ecliptic J2000 vectors in AU.
+
+#![warn(missing_docs)]
+
+use crate::{coordinates, designation};
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Utc};
+use std::fs;
+use std::path::{Path, PathBuf};
+
+/// Gaussian gravitational constant in degrees per day, i.e. Earth mean motion
+const GAUSS_MEAN_MOTION: f64 = 0.9856076686;
+
+/// Obliquity of the ecliptic at J2000 in degrees
+const J2000_OBLIQUITY: f64 = 23.4392911;
+
+/// Heliocentric or geocentric vector in AU
+pub type Vector = [f64; 3];
+
+/// Minor planet orbit structure, with osculating elements at epoch
+///
+/// * `designation`: readable designation, i.e. "(1) Ceres"
+/// * `packed_designation`: packed designation, i.e. "00001"
+/// * `absolute_magnitude`: absolute magnitude H
+/// * `slope`: slope parameter G
+/// * `epoch`: epoch of the elements as Julian Day
+/// * `mean_anomaly`: mean anomaly at epoch in degrees
+/// * `argument_of_perihelion`: argument of perihelion in degrees, J2000
+/// * `longitude_of_node`: longitude of the ascending node in degrees, J2000
+/// * `inclination`: inclination in degrees, J2000
+/// * `eccentricity`: eccentricity
+/// * `mean_motion`: mean daily motion in degrees per day
+/// * `semi_major_axis`: semi-major axis in AU
+#[derive(Debug, Clone, PartialEq)]
+pub struct Orbit {
+    /// Readable designation, i.e. "(1) Ceres"
+    pub designation: String,
+    /// Packed designation, i.e. "00001"
+    pub packed_designation: String,
+    /// Absolute magnitude H
+    pub absolute_magnitude: f64,
+    /// Slope parameter G
+    pub slope: f64,
+    /// Epoch of the elements as Julian Day
+    pub epoch: f64,
+    /// Mean anomaly at epoch in degrees
+    pub mean_anomaly: f64,
+    /// Argument of perihelion in degrees, J2000
+    pub argument_of_perihelion: f64,
+    /// Longitude of the ascending node in degrees, J2000
+    pub longitude_of_node: f64,
+    /// Inclination in degrees, J2000
+    pub inclination: f64,
+    /// Eccentricity
+    pub eccentricity: f64,
+    /// Mean daily motion in degrees per day
+    pub mean_motion: f64,
+    /// Semi-major axis in AU
+    pub semi_major_axis: f64,
+}
+
+/// Returns the field between two columns of a fixed width line, 1-based and inclusive
+///
+/// * `line`: the line
+/// * `first`: first column
+/// * `last`: last column
+fn column(line: &str, first: usize, last: usize) -> &str {
+    line.get(first - 1..last.min(line.len()))
+        .unwrap_or("")
+        .trim()
+}
+
+/// Parses the field between two columns as number
+///
+/// * `line`: the line
+/// * `first`: first column
+/// * `last`: last column
+/// * `name`: name of the field, for errors
+fn parse_column(line: &str, first: usize, last: usize, name: &str) -> Result<f64> {
+    let value = column(line, first, last);
+    value
+        .parse::<f64>()
+        .map_err(|_| anyhow!("Invalid {} \"{}\"", name, value))
+}
+
+/// Decodes one character of a packed date, 1-9 then A for 10 up to V for 31
+///
+/// * `value`: packed character
+fn unpack_digit(value: char) -> Result<u32> {
+    match value {
+        '1'..='9' => Ok(value as u32 - '0' as u32),
+        'A'..='V' => Ok(value as u32 - 'A' as u32 + 10),
+        _ => Err(anyhow!("Invalid packed digit {}", value)),
+    }
+}
+
+/// Unpacks an MPC packed epoch, i.e. "K2555" for 2025-05-05, returning the Julian Day at 0h TT
+///
+/// * `packed`: packed epoch
+pub fn unpack_epoch(packed: &str) -> Result<f64> {
+    let characters: Vec<char> = packed.trim().chars().collect();
+    let [century, decade, unit, month, day] = characters[..] else {
+        return Err(anyhow!("Invalid packed epoch \"{}\"", packed));
+    };
+    let century = match century {
+        'I' => 1800,
+        'J' => 1900,
+        'K' => 2000,
+        _ => return Err(anyhow!("Invalid packed epoch \"{}\"", packed)),
+    };
+    let year = format!("{}{}", decade, unit)
+        .parse::<i32>()
+        .map_err(|_| anyhow!("Invalid packed epoch \"{}\"", packed))?;
+    let date =
+        chrono::NaiveDate::from_ymd_opt(century + year, unpack_digit(month)?, unpack_digit(day)?)
+            .ok_or_else(|| anyhow!("Invalid packed epoch \"{}\"", packed))?;
+    Ok(coordinates::julian_day(
+        date.and_hms_opt(0, 0, 0).unwrap().and_utc(),
+    ))
+}
+
+/// Packs a Julian Day at 0h as MPC epoch, i.e. "K2555" for 2025-05-05
+///
+/// * `jd`: Julian Day
+fn pack_epoch(jd: f64) -> String {
+    let date = coordinates::datetime_from_julian_day(jd).date_naive();
+    let digit = |value: u32| {
+        char::from_digit(value, 32)
+            .unwrap_or('0')
+            .to_ascii_uppercase()
+    };
+    format!(
+        "{}{:02}{}{}",
+        digit(date.year() as u32 / 100),
+        date.year() % 100,
+        digit(date.month()),
+        digit(date.day())
+    )
+}
+
+/// Solves Kepler's equation, returning the eccentric anomaly in radians
+///
+/// * `eccentricity`: eccentricity, below 1
+/// * `mean_anomaly`: mean anomaly in degrees
+fn eccentric_anomaly(eccentricity: f64, mean_anomaly: f64) -> f64 {
+    let m = mean_anomaly.rem_euclid(360.0).to_radians();
+    let e = eccentricity;
+    let mut anomaly = if e > 0.8 { std::f64::consts::PI } else { m };
+    for _ in 0..50 {
+        let delta = (anomaly - e * anomaly.sin() - m) / (1.0 - e * anomaly.cos());
+        anomaly -= delta;
+        if delta.abs() < 1e-12 {
+            break;
+        }
+    }
+    anomaly
+}
+
+/// Rotates a vector from the orbital plane, with x towards the perihelion, to ecliptic J2000
+///
+/// * `x`: component towards the perihelion
+/// * `y`: component 90° ahead along the motion
+/// * `inclination`: inclination in degrees
+/// * `longitude_of_node`: longitude of the ascending node in degrees
+/// * `argument_of_perihelion`: argument of perihelion in degrees
+fn orbital_plane_to_ecliptic(
+    x: f64,
+    y: f64,
+    inclination: f64,
+    longitude_of_node: f64,
+    argument_of_perihelion: f64,
+) -> Vector {
+    let (i, node, peri) = (
+        inclination.to_radians(),
+        longitude_of_node.to_radians(),
+        argument_of_perihelion.to_radians(),
+    );
+    let (xp, yp) = (
+        x * peri.cos() - y * peri.sin(),
+        x * peri.sin() + y * peri.cos(),
+    );
+    [
+        xp * node.cos() - yp * i.cos() * node.sin(),
+        xp * node.sin() + yp * i.cos() * node.cos(),
+        yp * i.sin(),
+    ]
+}
+
+/// Returns the position on a Keplerian ellipse, ecliptic J2000 in AU
+///
+/// * `semi_major_axis`: semi-major axis in AU
+/// * `eccentricity`: eccentricity, below 1
+/// * `inclination`: inclination in degrees
+/// * `longitude_of_node`: longitude of the ascending node in degrees
+/// * `argument_of_perihelion`: argument of perihelion in degrees
+/// * `mean_anomaly`: mean anomaly in degrees
+pub(crate) fn kepler_position(
+    semi_major_axis: f64,
+    eccentricity: f64,
+    inclination: f64,
+    longitude_of_node: f64,
+    argument_of_perihelion: f64,
+    mean_anomaly: f64,
+) -> Vector {
+    let e = eccentricity;
+    let anomaly = eccentric_anomaly(e, mean_anomaly);
+    orbital_plane_to_ecliptic(
+        semi_major_axis * (anomaly.cos() - e),
+        semi_major_axis * (1.0 - e * e).sqrt() * anomaly.sin(),
+        inclination,
+        longitude_of_node,
+        argument_of_perihelion,
+    )
+}
+
+/// Returns the heliocentric position of the Earth-Moon barycenter, ecliptic J2000 in AU
+///
+/// It uses the approximate Keplerian elements by Standish (JPL), valid from 1800 to 2050
+///
+/// * `jd`: Julian Day
+pub fn earth_position(jd: f64) -> Vector {
+    let t = (jd - 2451545.0) / 36525.0;
+    let semi_major_axis = 1.00000261 + 0.00000562 * t;
+    let eccentricity = 0.01671123 - 0.00004392 * t;
+    let inclination = -0.00001531 - 0.01294668 * t;
+    let mean_longitude = 100.46457166 + 35999.37244981 * t;
+    let longitude_of_perihelion = 102.93768193 + 0.32327364 * t;
+    kepler_position(
+        semi_major_axis,
+        eccentricity,
+        inclination,
+        0.0,
+        longitude_of_perihelion,
+        mean_longitude - longitude_of_perihelion,
+    )
+}
+
+/// Rotates an ecliptic J2000 vector to equatorial J2000
+///
+/// * `vector`: ecliptic vector
+pub fn ecliptic_to_equatorial(vector: Vector) -> Vector {
+    let (sin, cos) = J2000_OBLIQUITY.to_radians().sin_cos();
+    [
+        vector[0],
+        vector[1] * cos - vector[2] * sin,
+        vector[1] * sin + vector[2] * cos,
+    ]
+}
+
+/// Returns right ascension and declination in degrees and distance of an equatorial vector
+///
+/// * `vector`: equatorial vector
+pub fn to_spherical(vector: Vector) -> (f64, f64, f64) {
+    let distance = norm(vector);
+    (
+        vector[1].atan2(vector[0]).to_degrees().rem_euclid(360.0),
+        (vector[2] / distance).asin().to_degrees(),
+        distance,
+    )
+}
+
+/// Returns the length of a vector
+///
+/// * `vector`: the vector
+pub(crate) fn norm(vector: Vector) -> f64 {
+    vector.iter().map(|value| value * value).sum::<f64>().sqrt()
+}
+
+/// Returns the angle between two vectors in degrees
+///
+/// * `first`: first vector
+/// * `second`: second vector
+pub(crate) fn angle(first: Vector, second: Vector) -> f64 {
+    let dot: f64 = first.iter().zip(second).map(|(a, b)| a * b).sum();
+    (dot / (norm(first) * norm(second)))
+        .clamp(-1.0, 1.0)
+        .acos()
+        .to_degrees()
+}
+
+impl Orbit {
+    /// Parses one line of MPCORB.DAT
+    ///
+    /// * `line`: the line to be parsed
+    pub fn from_mpcorb_line(line: &str) -> Result<Orbit> {
+        let packed_designation = column(line, 1, 7).to_string();
+        if packed_designation.is_empty() {
+            return Err(anyhow!("Missing designation"));
+        }
+        let eccentricity = parse_column(line, 71, 79, "eccentricity")?;
+        if !(0.0..1.0).contains(&eccentricity) {
+            return Err(anyhow!("{} is not on an ellipse", packed_designation));
+        }
+        let designation = match column(line, 167, 194) {
+            "" => designation::unpack(&packed_designation)
+                .unwrap_or_else(|_| packed_designation.clone()),
+            readable => readable.to_string(),
+        };
+        Ok(Orbit {
+            designation,
+            packed_designation,
+            absolute_magnitude: parse_column(line, 9, 13, "H")?,
+            slope: parse_column(line, 15, 19, "G").unwrap_or(0.15),
+            epoch: unpack_epoch(column(line, 21, 25))?,
+            mean_anomaly: parse_column(line, 27, 35, "mean anomaly")?,
+            argument_of_perihelion: parse_column(line, 38, 46, "argument of perihelion")?,
+            longitude_of_node: parse_column(line, 49, 57, "longitude of node")?,
+            inclination: parse_column(line, 60, 68, "inclination")?,
+            eccentricity,
+            mean_motion: parse_column(line, 81, 91, "mean motion")?,
+            semi_major_axis: parse_column(line, 93, 103, "semi-major axis")?,
+        })
+    }
+
+    /// Formats the orbit as a line of MPCORB.DAT, with the fields of this struct only
+    pub fn to_mpcorb_line(&self) -> String {
+        format!(
+            "{:<7} {:5.2} {:5.2} {} {:9.5}  {:9.5}  {:9.5}  {:9.5}  {:9.7} {:11.8} {:11.7}{:63}{}",
+            self.packed_designation,
+            self.absolute_magnitude,
+            self.slope,
+            pack_epoch(self.epoch),
+            self.mean_anomaly,
+            self.argument_of_perihelion,
+            self.longitude_of_node,
+            self.inclination,
+            self.eccentricity,
+            self.mean_motion,
+            self.semi_major_axis,
+            "",
+            self.designation
+        )
+    }
+
+    /// Returns the epoch of the elements as date-time
+    pub fn epoch_datetime(&self) -> DateTime<Utc> {
+        coordinates::datetime_from_julian_day(self.epoch)
+    }
+
+    /// Returns the perihelion distance in AU
+    pub fn perihelion_distance(&self) -> f64 {
+        self.semi_major_axis * (1.0 - self.eccentricity)
+    }
+
+    /// Returns true for near Earth objects, with perihelion closer than 1.3 AU
+    pub fn is_neo(&self) -> bool {
+        self.perihelion_distance() < 1.3
+    }
+
+    /// Returns the heliocentric position, ecliptic J2000 in AU
+    ///
+    /// * `jd`: Julian Day
+    pub fn heliocentric_position(&self, jd: f64) -> Vector {
+        let mean_motion = if self.mean_motion > 0.0 {
+            self.mean_motion
+        } else {
+            GAUSS_MEAN_MOTION / self.semi_major_axis.powf(1.5)
+        };
+        kepler_position(
+            self.semi_major_axis,
+            self.eccentricity,
+            self.inclination,
+            self.longitude_of_node,
+            self.argument_of_perihelion,
+            self.mean_anomaly + mean_motion * (jd - self.epoch),
+        )
+    }
+
+    /// Returns the heliocentric velocity, ecliptic J2000 in AU per day
+    ///
+    /// The mean motion follows the semi-major axis, as required by the equations of motion.
+    ///
+    /// * `jd`: Julian Day
+    pub fn heliocentric_velocity(&self, jd: f64) -> Vector {
+        let mean_motion = if self.mean_motion > 0.0 {
+            self.mean_motion
+        } else {
+            GAUSS_MEAN_MOTION / self.semi_major_axis.powf(1.5)
+        };
+        let e = self.eccentricity;
+        let anomaly = eccentric_anomaly(e, self.mean_anomaly + mean_motion * (jd - self.epoch));
+        // Derivative of the eccentric anomaly, in radians per day
+        let rate = (GAUSS_MEAN_MOTION / self.semi_major_axis.powf(1.5)).to_radians()
+            / (1.0 - e * anomaly.cos());
+        orbital_plane_to_ecliptic(
+            -self.semi_major_axis * anomaly.sin() * rate,
+            self.semi_major_axis * (1.0 - e * e).sqrt() * anomaly.cos() * rate,
+            self.inclination,
+            self.longitude_of_node,
+            self.argument_of_perihelion,
+        )
+    }
+
+    /// Returns the visual magnitude with the H-G system
+    ///
+    /// * `sun_distance`: distance from the Sun in AU
+    /// * `earth_distance`: distance from the Earth in AU
+    /// * `phase_angle`: Sun-object-Earth angle in degrees
+    pub fn magnitude(&self, sun_distance: f64, earth_distance: f64, phase_angle: f64) -> f64 {
+        let tan = (phase_angle.to_radians() / 2.0).tan();
+        let phi1 = (-3.33 * tan.powf(0.63)).exp();
+        let phi2 = (-1.87 * tan.powf(1.22)).exp();
+        self.absolute_magnitude + 5.0 * (sun_distance * earth_distance).log10()
+            - 2.5 * ((1.0 - self.slope) * phi1 + self.slope * phi2).log10()
+    }
+}
+
+/// Parses MPCORB.DAT text, skipping the header and the lines without valid elements
+///
+/// * `text`: the text to be parsed
+pub fn parse_mpcorb(text: &str) -> Vec<Orbit> {
+    text.lines()
+        .filter_map(|line| Orbit::from_mpcorb_line(line).ok())
+        .collect()
+}
+
+/// Returns the default path of MPCORB.DAT, next to `config.toml`
+pub fn mpcorb_path() -> PathBuf {
+    dirs::config_local_dir()
+        .unwrap()
+        .join("asteroid_tui")
+        .join("MPCORB.DAT")
+}
+
+/// Loads orbits from an MPCORB.DAT file
+///
+/// * `path`: path of the file
+pub fn load_mpcorb(path: &Path) -> Result<Vec<Orbit>> {
+    let text = fs::read_to_string(path)
+        .map_err(|e| anyhow!("Failed to read {}: {}", path.display(), e))?;
+    let orbits = parse_mpcorb(&text);
+    if orbits.is_empty() {
+        return Err(anyhow!("No orbits found in {}", path.display()));
+    }
+    Ok(orbits)
+}
+
+#[cfg(test)]
+pub(crate) mod test {
+    use super::*;
+    use crate::sun_moon_times::sun_position;
+
+    /// Returns the orbit of (433) Eros from response_examples/mpcorb.dat
+    pub(crate) fn eros() -> Orbit {
+        let text = fs::read_to_string("response_examples/mpcorb.dat").unwrap();
+        parse_mpcorb(&text)
+            .into_iter()
+            .find(|orbit| orbit.packed_designation == "00433")
+            .unwrap()
+    }
+
+    #[test]
+    fn test_unpack_epoch() {
+        assert_eq!(unpack_epoch("K24AH").unwrap(), 2460600.5);
+        assert_eq!(unpack_epoch("J9611").unwrap(), 2450083.5);
+        assert!(unpack_epoch("K252U").is_err());
+        assert!(unpack_epoch("X2555").is_err());
+    }
+
+    #[test]
+    fn test_parse_mpcorb() {
+        let text = fs::read_to_string("response_examples/mpcorb.dat").unwrap();
+        let orbits = parse_mpcorb(&text);
+        assert_eq!(orbits.len(), 3);
+        let ceres = &orbits[0];
+        assert_eq!(ceres.designation, "(1) Ceres");
+        assert_eq!(ceres.packed_designation, "00001");
+        assert_eq!(ceres.absolute_magnitude, 3.34);
+        assert_eq!(ceres.epoch, 2460800.5);
+        assert_eq!(ceres.semi_major_axis, 2.7660512);
+        assert!(!ceres.is_neo());
+        assert!(orbits[2].is_neo());
+        let line = ceres.to_mpcorb_line();
+        assert_eq!(&line[..103], &text.lines().nth(7).unwrap()[..103]);
+        assert_eq!(Orbit::from_mpcorb_line(&line).unwrap(), *ceres);
+        assert_eq!(
+            ceres.epoch_datetime().to_rfc3339(),
+            "2025-05-05T00:00:00+00:00"
+        );
+    }
+
+    #[test]
+    fn test_earth_position() {
+        // Sun longitude by NOAA formulas, precessed back to J2000
+        let jd = 2460754.875;
+        let t = (jd - 2451545.0) / 36525.0;
+        let earth = earth_position(jd);
+        let longitude = (sun_position(jd).longitude - 1.397 * t).rem_euclid(360.0);
+        let sun_longitude = (-earth[1]).atan2(-earth[0]).to_degrees().rem_euclid(360.0);
+        assert!((sun_longitude - longitude).abs() < 0.02);
+        assert!((norm(earth) - 0.996).abs() < 0.001);
+    }
+
+    #[test]
+    fn test_magnitude() {
+        let orbit =
+            parse_mpcorb(&fs::read_to_string("response_examples/mpcorb.dat").unwrap()).remove(0);
+        assert!((orbit.magnitude(2.0, 1.0, 0.0) - (3.34 + 5.0 * 2.0_f64.log10())).abs() < 1e-9);
+        assert!(orbit.magnitude(2.0, 1.0, 20.0) > orbit.magnitude(2.0, 1.0, 0.0));
+    }
+}
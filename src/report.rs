@@ -0,0 +1,762 @@
+//! # Report
+//!
+//! Library for astrometric reports to the Minor Planet Center
+//!
+//! Measured positions are written as the MPC1992 80-column format, with its COD, OBS, MEA,
+//! TEL, ACK, AC2 and NET header lines, or as ADES PSV and XML. The header is filled from the
+//! observatory and the active equipment of `Settings`, every output is validated first.
+//!
+//! ```rust
+//! use asteroid_tui::designation::Designation;
+//! use asteroid_tui::report::Observation;
+//! use chrono::{TimeZone, Utc};
+//! let observation = Observation {
+//!     designation: Designation::parse("2024 AB1").unwrap(),
+//!     time: Utc.with_ymd_and_hms(2025, 1, 15, 2, 53, 45).unwrap(),
+//!     ra: 74.526,
+//!     dec: 29.505,
+//!     magnitude: Some(19.5),
+//!     band: 'G',
+//!     ..Observation::default()
+//! };
+//! let line = observation.to_mpc1992_line("123", "Gaia3").unwrap();
+//! assert_eq!(&line[15..32], "2025 01 15.12066 ");
+//! assert_eq!(line.len(), 80);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::designation::Designation;
+use crate::settings::Settings;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Datelike, Timelike, Utc};
+use serde::{Deserialize, Serialize};
+
+/// Photometric bands accepted by the MPC
+pub const BANDS: [char; 19] = [
+    'V', 'R', 'B', 'I', 'U', 'G', 'C', 'W', 'J', 'H', 'K', 'g', 'r', 'i', 'z', 'y', 'o', 'c', 'w',
+];
+
+/// Astrometric catalogues: ADES code, MPC1992 column 72 code and NET name
+pub const CATALOGUES: [(&str, char, &str); 8] = [
+    ("Gaia1", 'U', "Gaia-DR1"),
+    ("Gaia2", 'V', "Gaia-DR2"),
+    ("Gaia3", 'W', "Gaia-DR3"),
+    ("Gaia3E", 'X', "Gaia-EDR3"),
+    ("UCAC4", 'q', "UCAC-4"),
+    ("PPMXL", 't', "PPMXL"),
+    ("URAT1", 'S', "URAT-1"),
+    ("ATLAS2", 'Z', "ATLAS-2"),
+];
+
+/// ADES format version
+const ADES_VERSION: &str = "2017";
+
+/// Telescope structure for the TEL line and the ADES telescope block
+///
+/// * `aperture`: aperture in meters
+/// * `f_ratio`: focal ratio
+/// * `design`: optical design, i.e. "reflector"
+/// * `detector`: detector, i.e. "CCD"
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Telescope {
+    /// Aperture in meters
+    pub aperture: f64,
+    /// Focal ratio
+    pub f_ratio: f64,
+    /// Optical design, i.e. "reflector"
+    pub design: String,
+    /// Detector, i.e. "CCD"
+    pub detector: String,
+}
+
+/// Report header structure
+///
+/// * `mpc_code`: observatory code (COD)
+/// * `observatory_name`: observatory name
+/// * `observers`: observer names (OBS)
+/// * `measurers`: measurer names (MEA)
+/// * `telescope`: telescope (TEL)
+/// * `acknowledgement`: subject of the MPC acknowledgement (ACK)
+/// * `email`: address for the acknowledgement (AC2)
+/// * `catalogue`: ADES code of the astrometric catalogue (NET)
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct ReportHeader {
+    /// Observatory code (COD)
+    pub mpc_code: String,
+    /// Observatory name
+    pub observatory_name: String,
+    /// Observer names (OBS)
+    pub observers: Vec<String>,
+    /// Measurer names (MEA)
+    pub measurers: Vec<String>,
+    /// Telescope (TEL)
+    pub telescope: Option<Telescope>,
+    /// Subject of the MPC acknowledgement (ACK)
+    pub acknowledgement: String,
+    /// Address for the acknowledgement (AC2)
+    pub email: String,
+    /// ADES code of the astrometric catalogue (NET)
+    pub catalogue: String,
+}
+
+impl ReportHeader {
+    /// Creates the header from the observatory and the active equipment
+    ///
+    /// Observers and measurers are the comma separated observer names of the settings,
+    /// the catalogue is Gaia DR3. Optical design and detector are those of the equipment,
+    /// `validate` reports them if missing.
+    ///
+    /// * `settings`: settings
+    /// * `email`: address for the acknowledgement
+    pub fn from_settings(settings: &Settings, email: &str) -> Self {
+        let observers: Vec<String> = settings
+            .get_observer_name()
+            .split(',')
+            .map(|name| name.trim().to_string())
+            .filter(|name| !name.is_empty())
+            .collect();
+        ReportHeader {
+            mpc_code: settings.get_mpc_code().trim().to_string(),
+            observatory_name: settings.get_observatory_name().to_string(),
+            measurers: observers.clone(),
+            observers,
+            telescope: settings.get_equipment().map(|setup| Telescope {
+                aperture: setup.aperture as f64 / 1000.0,
+                f_ratio: (setup.focal_length / setup.aperture) as f64,
+                design: setup.design.trim().to_string(),
+                detector: setup.detector.trim().to_string(),
+            }),
+            acknowledgement: format!("{} astrometry", settings.get_observatory_name()),
+            email: email.trim().to_string(),
+            catalogue: "Gaia3".to_string(),
+        }
+    }
+
+    /// Returns the format problems of the header
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        if !is_mpc_code(&self.mpc_code) {
+            issues.push(format!("Invalid MPC code \"{}\"", self.mpc_code));
+        }
+        if self.observers.is_empty() {
+            issues.push("At least one observer is needed".to_string());
+        }
+        if self.measurers.is_empty() {
+            issues.push("At least one measurer is needed".to_string());
+        }
+        match &self.telescope {
+            Some(telescope) if telescope.aperture <= 0.0 || telescope.f_ratio <= 0.0 => {
+                issues.push("Telescope aperture and focal ratio must be positive".to_string())
+            }
+            Some(telescope) if telescope.design.is_empty() || telescope.detector.is_empty() => {
+                issues.push("Telescope optical design and detector are needed".to_string())
+            }
+            Some(_) => (),
+            None => issues.push("An equipment setup is needed for the telescope".to_string()),
+        }
+        if self.acknowledgement.trim().is_empty() {
+            issues.push("Acknowledgement subject must not be empty".to_string());
+        }
+        if !self.email.contains('@') {
+            issues.push(format!(
+                "Invalid acknowledgement address \"{}\"",
+                self.email
+            ));
+        }
+        if catalogue(&self.catalogue).is_none() {
+            issues.push(format!("Unknown catalogue \"{}\"", self.catalogue));
+        }
+        issues
+    }
+
+    /// Returns the MPC1992 header lines
+    fn to_mpc1992_lines(&self) -> Vec<String> {
+        let mut lines = vec![
+            format!("COD {}", self.mpc_code),
+            format!("OBS {}", self.observers.join(", ")),
+            format!("MEA {}", self.measurers.join(", ")),
+        ];
+        if let Some(telescope) = &self.telescope {
+            lines.push(format!(
+                "TEL {:.2}-m f/{:.1} {} + {}",
+                telescope.aperture, telescope.f_ratio, telescope.design, telescope.detector
+            ));
+        }
+        lines.push(format!("ACK {}", self.acknowledgement));
+        lines.push(format!("AC2 {}", self.email));
+        if let Some((_, _, name)) = catalogue(&self.catalogue) {
+            lines.push(format!("NET {}", name));
+        }
+        lines
+    }
+}
+
+/// Measured position structure
+///
+/// * `designation`: object designation, empty for unidentified objects
+/// * `trksub`: temporary designation given by the observer to unidentified objects
+/// * `discovery`: true to flag a discovery observation
+/// * `note`: MPC observation note
+/// * `time`: UTC mid-exposure time
+/// * `ra`: right ascension in degrees, J2000
+/// * `dec`: declination in degrees, J2000
+/// * `magnitude`: measured magnitude
+/// * `band`: photometric band of the magnitude
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Observation {
+    /// Object designation, empty for unidentified objects
+    pub designation: Designation,
+    /// Temporary designation given by the observer to unidentified objects
+    pub trksub: Option<String>,
+    /// True to flag a discovery observation
+    pub discovery: bool,
+    /// MPC observation note
+    pub note: Option<char>,
+    /// UTC mid-exposure time
+    pub time: DateTime<Utc>,
+    /// Right ascension in degrees, J2000
+    pub ra: f64,
+    /// Declination in degrees, J2000
+    pub dec: f64,
+    /// Measured magnitude
+    pub magnitude: Option<f32>,
+    /// Photometric band of the magnitude
+    pub band: char,
+}
+
+impl Default for Observation {
+    fn default() -> Self {
+        Observation {
+            designation: Designation::default(),
+            trksub: None,
+            discovery: false,
+            note: None,
+            time: DateTime::<Utc>::UNIX_EPOCH,
+            ra: 0.0,
+            dec: 0.0,
+            magnitude: None,
+            band: 'G',
+        }
+    }
+}
+
+impl Observation {
+    /// Returns the format problems of the observation
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = Vec::new();
+        let identified = self.designation.to_packed().is_ok();
+        match &self.trksub {
+            Some(trksub)
+                if trksub.is_empty()
+                    || trksub.len() > 7
+                    || !trksub.chars().all(|c| c.is_ascii_alphanumeric()) =>
+            {
+                issues.push(format!("Invalid temporary designation \"{}\"", trksub))
+            }
+            None if !identified => issues.push(format!(
+                "Designation {} cannot be reported",
+                self.designation
+            )),
+            _ => (),
+        }
+        if self.time > Utc::now() || self.time.year() < 1990 {
+            issues.push(format!("Invalid time {}", self.time.to_rfc3339()));
+        }
+        if !(0.0..360.0).contains(&self.ra) {
+            issues.push(format!("Right ascension {} out of range", self.ra));
+        }
+        if !(-90.0..=90.0).contains(&self.dec) {
+            issues.push(format!("Declination {} out of range", self.dec));
+        }
+        if let Some(magnitude) = self.magnitude {
+            if !(-5.0..=30.0).contains(&magnitude) {
+                issues.push(format!("Magnitude {} out of range", magnitude));
+            }
+            if !BANDS.contains(&self.band) {
+                issues.push(format!("Unknown band '{}'", self.band));
+            }
+        }
+        if let Some(note) = self.note {
+            if !note.is_ascii_graphic() {
+                issues.push(format!("Invalid note '{}'", note));
+            }
+        }
+        issues
+    }
+
+    /// Returns the designation columns 1-12, the temporary designation for unidentified
+    /// objects
+    fn packed_columns(&self) -> Result<String> {
+        match &self.trksub {
+            Some(trksub) if self.designation.to_packed().is_err() => {
+                Ok(format!("     {:<7}", trksub))
+            }
+            _ => self.designation.to_packed_columns(),
+        }
+    }
+
+    /// Formats the observation as an MPC1992 80-column record
+    ///
+    /// RA is written to 0.01s and Dec to 0.1", ADES keeps the full precision.
+    ///
+    /// * `mpc_code`: observatory code
+    /// * `catalogue_code`: ADES code of the astrometric catalogue
+    pub fn to_mpc1992_line(&self, mpc_code: &str, catalogue_code: &str) -> Result<String> {
+        let (_, catalogue_column, _) = catalogue(catalogue_code)
+            .ok_or_else(|| anyhow!("Unknown catalogue \"{}\"", catalogue_code))?;
+        let magnitude = match self.magnitude {
+            Some(magnitude) => format!("{:4.1} {}", magnitude, self.band),
+            None => " ".repeat(6),
+        };
+        let line = format!(
+            "{}{}{}C{}{}{}{:9}{}{}{:5}{:>3}",
+            self.packed_columns()?,
+            if self.discovery { '*' } else { ' ' },
+            self.note.unwrap_or(' '),
+            format_date(self.time),
+            format_ra(self.ra),
+            format_dec(self.dec),
+            "",
+            magnitude,
+            catalogue_column,
+            "",
+            mpc_code
+        );
+        if line.len() != 80 || !line.is_ascii() {
+            return Err(anyhow!("Invalid MPC1992 record \"{}\"", line));
+        }
+        Ok(line)
+    }
+
+    /// Returns the ADES permanent and provisional designations
+    fn ades_designations(&self) -> (String, String) {
+        let designation = &self.designation;
+        let perm_id = match (designation.number, designation.comet_type) {
+            (Some(number), Some(comet_type)) => format!("{}{}", number, comet_type),
+            (Some(number), None) => number.to_string(),
+            _ => String::new(),
+        };
+        let prov_id = match (&designation.provisional, designation.comet_type) {
+            (Some(provisional), Some(comet_type)) if perm_id.is_empty() => {
+                format!("{}/{}", comet_type, provisional)
+            }
+            (Some(provisional), None) => provisional.clone(),
+            _ => String::new(),
+        };
+        (perm_id, prov_id)
+    }
+
+    /// Returns the ADES fields in `ADES_FIELDS` order
+    ///
+    /// * `mpc_code`: observatory code
+    /// * `catalogue`: ADES code of the astrometric catalogue
+    fn ades_values(&self, mpc_code: &str, catalogue: &str) -> Vec<String> {
+        let (perm_id, prov_id) = self.ades_designations();
+        vec![
+            perm_id,
+            prov_id,
+            self.trksub.clone().unwrap_or_default(),
+            "CCD".to_string(),
+            mpc_code.to_string(),
+            self.time.format("%Y-%m-%dT%H:%M:%S%.3fZ").to_string(),
+            format!("{:.6}", self.ra),
+            format!("{:.6}", self.dec),
+            catalogue.to_string(),
+            self.magnitude
+                .map(|magnitude| format!("{:.2}", magnitude))
+                .unwrap_or_default(),
+            if self.magnitude.is_some() {
+                self.band.to_string()
+            } else {
+                String::new()
+            },
+            if self.discovery { "*" } else { "" }.to_string(),
+            self.note.map(String::from).unwrap_or_default(),
+        ]
+    }
+}
+
+/// ADES optical fields written in PSV columns and XML elements
+const ADES_FIELDS: [&str; 13] = [
+    "permID", "provID", "trkSub", "mode", "stn", "obsTime", "ra", "dec", "astCat", "mag", "band",
+    "disc", "notes",
+];
+
+/// Report structure, a header with its observations
+///
+/// * `header`: report header
+/// * `observations`: measured positions
+#[derive(Debug, Clone, PartialEq, Deserialize, Serialize)]
+pub struct Report {
+    /// Report header
+    pub header: ReportHeader,
+    /// Measured positions
+    pub observations: Vec<Observation>,
+}
+
+impl Report {
+    /// Creates a report
+    ///
+    /// * `header`: report header
+    /// * `observations`: measured positions
+    pub fn new(header: ReportHeader, observations: Vec<Observation>) -> Self {
+        Report {
+            header,
+            observations,
+        }
+    }
+
+    /// Returns the format problems of header and observations
+    pub fn validate(&self) -> Vec<String> {
+        let mut issues = self.header.validate();
+        if self.observations.is_empty() {
+            issues.push("The report has no observations".to_string());
+        }
+        for (number, observation) in self.observations.iter().enumerate() {
+            issues.extend(
+                observation
+                    .validate()
+                    .into_iter()
+                    .map(|issue| format!("Observation {}: {}", number + 1, issue)),
+            );
+        }
+        issues
+    }
+
+    /// Returns an error with all format problems, if any
+    fn check(&self) -> Result<()> {
+        let issues = self.validate();
+        if issues.is_empty() {
+            Ok(())
+        } else {
+            Err(anyhow!("Invalid report: {}", issues.join("; ")))
+        }
+    }
+
+    /// Writes the report in the MPC1992 80-column format, header lines first
+    pub fn to_mpc1992(&self) -> Result<String> {
+        self.check()?;
+        let mut lines = self.header.to_mpc1992_lines();
+        if let Some(line) = lines
+            .iter()
+            .find(|line| line.len() > 80 || !line.is_ascii())
+        {
+            return Err(anyhow!(
+                "Header line \"{}\" is not ASCII or longer than 80 columns",
+                line
+            ));
+        }
+        for observation in &self.observations {
+            lines.push(observation.to_mpc1992_line(&self.header.mpc_code, &self.header.catalogue)?);
+        }
+        lines.push("----- end -----".to_string());
+        Ok(lines.join("\n") + "\n")
+    }
+
+    /// Writes the report in the ADES pipe separated values format
+    pub fn to_ades_psv(&self) -> Result<String> {
+        self.check()?;
+        let header = &self.header;
+        let mut lines = vec![
+            format!("# version={}", ADES_VERSION),
+            "# observatory".to_string(),
+            format!("! mpcCode {}", header.mpc_code),
+            format!("! name {}", header.observatory_name),
+            "# submitter".to_string(),
+            format!("! name {}", header.observers[0]),
+            "# observers".to_string(),
+        ];
+        lines.extend(
+            header
+                .observers
+                .iter()
+                .map(|name| format!("! name {}", name)),
+        );
+        lines.push("# measurers".to_string());
+        lines.extend(
+            header
+                .measurers
+                .iter()
+                .map(|name| format!("! name {}", name)),
+        );
+        if let Some(telescope) = &header.telescope {
+            lines.extend([
+                "# telescope".to_string(),
+                format!("! aperture {:.2}", telescope.aperture),
+                format!("! design {}", telescope.design),
+                format!("! detector {}", telescope.detector),
+                format!("! fRatio {:.1}", telescope.f_ratio),
+            ]);
+        }
+        lines.push(ADES_FIELDS.join("|"));
+        for observation in &self.observations {
+            let values = observation.ades_values(&header.mpc_code, &header.catalogue);
+            if let Some(value) = values.iter().find(|value| value.contains('|')) {
+                return Err(anyhow!("Value \"{}\" contains the separator", value));
+            }
+            lines.push(values.join("|"));
+        }
+        Ok(lines.join("\n") + "\n")
+    }
+
+    /// Writes the report in the ADES XML format
+    pub fn to_ades_xml(&self) -> Result<String> {
+        self.check()?;
+        let header = &self.header;
+        let names = |names: &[String]| -> String {
+            names
+                .iter()
+                .map(|name| format!("<name>{}</name>", escape_xml(name)))
+                .collect()
+        };
+        let mut xml = format!(
+            "<?xml version=\"1.0\" encoding=\"UTF-8\"?>\n<ades version=\"{}\">\n  <obsBlock>\n    <obsContext>\n",
+            ADES_VERSION
+        );
+        xml += &format!(
+            "      <observatory><mpcCode>{}</mpcCode><name>{}</name></observatory>\n",
+            escape_xml(&header.mpc_code),
+            escape_xml(&header.observatory_name)
+        );
+        xml += &format!(
+            "      <submitter>{}</submitter>\n",
+            names(&header.observers[..1])
+        );
+        xml += &format!(
+            "      <observers>{}</observers>\n",
+            names(&header.observers)
+        );
+        xml += &format!(
+            "      <measurers>{}</measurers>\n",
+            names(&header.measurers)
+        );
+        if let Some(telescope) = &header.telescope {
+            xml += &format!(
+                "      <telescope><aperture>{:.2}</aperture><design>{}</design><detector>{}</detector><fRatio>{:.1}</fRatio></telescope>\n",
+                telescope.aperture,
+                escape_xml(&telescope.design),
+                escape_xml(&telescope.detector),
+                telescope.f_ratio
+            );
+        }
+        xml += "    </obsContext>\n    <obsData>\n";
+        for observation in &self.observations {
+            xml += "      <optical>";
+            for (field, value) in ADES_FIELDS
+                .iter()
+                .zip(observation.ades_values(&header.mpc_code, &header.catalogue))
+                .filter(|(_, value)| !value.is_empty())
+            {
+                xml += &format!("<{}>{}</{}>", field, escape_xml(&value), field);
+            }
+            xml += "</optical>\n";
+        }
+        xml += "    </obsData>\n  </obsBlock>\n</ades>\n";
+        Ok(xml)
+    }
+}
+
+/// Returns true for a valid observatory code, i.e. "123" or "K02"
+///
+/// * `code`: observatory code
+pub fn is_mpc_code(code: &str) -> bool {
+    let bytes = code.as_bytes();
+    bytes.len() == 3
+        && (bytes[0].is_ascii_uppercase() || bytes[0].is_ascii_digit())
+        && bytes[1..].iter().all(u8::is_ascii_digit)
+}
+
+/// Returns the catalogue with the ADES code `code`
+///
+/// * `code`: ADES catalogue code, i.e. "Gaia3"
+pub fn catalogue(code: &str) -> Option<(&'static str, char, &'static str)> {
+    CATALOGUES
+        .iter()
+        .find(|(ades, _, _)| *ades == code)
+        .copied()
+}
+
+/// Formats a UTC time as MPC1992 date with decimal day, i.e. "2025 01 15.12066 "
+///
+/// * `time`: UTC time
+fn format_date(time: DateTime<Utc>) -> String {
+    let seconds = time.num_seconds_from_midnight() as f64 + time.nanosecond() as f64 * 1e-9;
+    // Times close to midnight stay in their day
+    let fraction = ((seconds / 86400.0 * 1e5).round() as u32).min(99999);
+    format!(
+        "{:04} {:02} {:02}.{:05} ",
+        time.year(),
+        time.month(),
+        time.day(),
+        fraction
+    )
+}
+
+/// Formats right ascension in degrees for MPC1992, i.e. "04 58 06.24 "
+///
+/// * `ra`: right ascension in degrees
+fn format_ra(ra: f64) -> String {
+    let hundredths = (ra.rem_euclid(360.0) / 15.0 * 360000.0).round() as i64 % 8640000;
+    format!(
+        "{:02} {:02} {:05.2} ",
+        hundredths / 360000,
+        hundredths / 6000 % 60,
+        (hundredths % 6000) as f64 / 100.0
+    )
+}
+
+/// Formats declination in degrees for MPC1992, i.e. "+29 30 18.0 "
+///
+/// * `dec`: declination in degrees
+fn format_dec(dec: f64) -> String {
+    let tenths = (dec.abs() * 36000.0).round() as i64;
+    format!(
+        "{}{:02} {:02} {:04.1} ",
+        if dec < 0.0 { '-' } else { '+' },
+        tenths / 36000,
+        tenths / 600 % 60,
+        (tenths % 600) as f64 / 10.0
+    )
+}
+
+/// Escapes the XML special characters
+///
+/// * `value`: text to escape
+fn escape_xml(value: &str) -> String {
+    value
+        .replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+        .replace('\'', "&apos;")
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+    use chrono::TimeZone;
+
+    fn header() -> ReportHeader {
+        ReportHeader {
+            mpc_code: "123".to_string(),
+            observatory_name: "Club & Friends".to_string(),
+            observers: vec!["J. Doe".to_string(), "R. Roe".to_string()],
+            measurers: vec!["J. Doe".to_string()],
+            telescope: Some(Telescope {
+                aperture: 0.2,
+                f_ratio: 4.0,
+                design: "reflector".to_string(),
+                detector: "CCD".to_string(),
+            }),
+            acknowledgement: "Club astrometry".to_string(),
+            email: "doe@example.org".to_string(),
+            catalogue: "Gaia3".to_string(),
+        }
+    }
+
+    fn observations() -> Vec<Observation> {
+        vec![
+            Observation {
+                designation: Designation::parse("2024 AB1").unwrap(),
+                discovery: true,
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 2, 53, 45).unwrap(),
+                ra: 74.526,
+                dec: 29.505,
+                magnitude: Some(19.5),
+                ..Observation::default()
+            },
+            Observation {
+                designation: Designation::parse("(433) Eros").unwrap(),
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 23, 59, 59).unwrap(),
+                ra: 359.9999,
+                dec: -5.25,
+                magnitude: Some(9.8),
+                band: 'V',
+                ..Observation::default()
+            },
+            Observation {
+                trksub: Some("CLB0001".to_string()),
+                time: Utc.with_ymd_and_hms(2025, 1, 15, 3, 0, 0).unwrap(),
+                ra: 120.0,
+                dec: 0.0,
+                ..Observation::default()
+            },
+        ]
+    }
+
+    #[test]
+    fn test_mpc1992() {
+        let report = Report::new(header(), observations());
+        let text = report.to_mpc1992().unwrap();
+        let lines: Vec<&str> = text.lines().collect();
+        assert_eq!(lines[0], "COD 123");
+        assert_eq!(lines[1], "OBS J. Doe, R. Roe");
+        assert_eq!(lines[3], "TEL 0.20-m f/4.0 reflector + CCD");
+        assert_eq!(lines[6], "NET Gaia-DR3");
+        assert_eq!(
+            lines[7],
+            "     K24A01B* C2025 01 15.12066 04 58 06.24 +29 30 18.0          19.5 GW     123"
+        );
+        assert_eq!(
+            lines[8],
+            "00433         C2025 01 15.99999 23 59 59.98 -05 15 00.0           9.8 VW     123"
+        );
+        assert_eq!(
+            lines[9],
+            "     CLB0001  C2025 01 15.12500 08 00 00.00 +00 00 00.0                W     123"
+        );
+        assert!(lines[7..10].iter().all(|line| line.len() == 80));
+        assert_eq!(lines.last(), Some(&"----- end -----"));
+    }
+
+    #[test]
+    fn test_ades() {
+        let report = Report::new(header(), observations());
+        let psv = report.to_ades_psv().unwrap();
+        assert!(psv.starts_with("# version=2017\n# observatory\n! mpcCode 123\n"));
+        assert!(psv.contains("! fRatio 4.0\n"));
+        assert!(psv.contains(
+            "\n|2024 AB1||CCD|123|2025-01-15T02:53:45.000Z|74.526000|29.505000|Gaia3|19.50|G|*|\n"
+        ));
+        assert!(psv.contains(
+            "\n433|||CCD|123|2025-01-15T23:59:59.000Z|359.999900|-5.250000|Gaia3|9.80|V||\n"
+        ));
+        let xml = report.to_ades_xml().unwrap();
+        assert!(xml.contains("<name>Club &amp; Friends</name>"));
+        assert!(xml.contains("<observers><name>J. Doe</name><name>R. Roe</name></observers>"));
+        assert!(xml.contains(
+            "<optical><permID>433</permID><mode>CCD</mode><stn>123</stn><obsTime>2025-01-15T23:59:59.000Z</obsTime>"
+        ));
+        assert!(xml.contains("<trkSub>CLB0001</trkSub>"));
+        assert_eq!(xml.matches("<optical>").count(), 3);
+    }
+
+    #[test]
+    fn test_validate() {
+        assert!(Report::new(header(), observations()).validate().is_empty());
+        let mut invalid = Report::new(header(), observations());
+        invalid.header.mpc_code = "12".to_string();
+        invalid.header.telescope = None;
+        invalid.header.email = String::new();
+        invalid.observations[0].dec = 91.0;
+        invalid.observations[1].band = 'Q';
+        invalid.observations[2].trksub = Some("TOO-LONG".to_string());
+        let issues = invalid.validate();
+        assert_eq!(issues.len(), 6);
+        assert_eq!(issues[3], "Observation 1: Declination 91 out of range");
+        assert!(invalid.to_mpc1992().is_err());
+        assert!(invalid.to_ades_xml().is_err());
+        assert!(is_mpc_code("K02"));
+        assert!(!is_mpc_code("k02"));
+        assert!(Report::new(header(), Vec::new()).to_ades_psv().is_err());
+        let mut refractor = header();
+        if let Some(telescope) = refractor.telescope.as_mut() {
+            telescope.design = String::new();
+        }
+        assert_eq!(
+            refractor.validate(),
+            vec!["Telescope optical design and detector are needed".to_string()]
+        );
+    }
+}
@@ -1,10 +1,27 @@
+use crate::astrometry_import::{self, Issue};
+use crate::ephemeris::{self, EphemerisEntry, Propagation};
+use crate::horizon::HorizonProfile;
+use crate::neocp::{self, NeocpObject};
+use crate::neocp_ephemeris::{self, NeocpEphemerisEntry};
 use crate::observing_target_list::PossibleTarget;
+use crate::orbit::{self, Orbit};
 use crate::{
-    observing_target_list::parse_whats_up_response, observing_target_list::WhatsUpParams,
-    sun_moon_times, sun_moon_times::SunMoonTimesResponse, tui, weather, weather::Forecast,
+    catalogue::Catalogue,
+    coordinates,
+    coordinates::Observer,
+    horizon, night_quality,
+    observing_target_list::parse_whats_up_response,
+    observing_target_list::ObjectType,
+    observing_target_list::WhatsUpParams,
+    settings::Settings,
+    sun_moon_times,
+    sun_moon_times::SunMoonTimesResponse,
+    tui, weather,
+    weather::{CivilLightForecast, Forecast},
+    weather_providers::{self, HourlyForecast},
+    whats_up,
 };
-use chrono::format::StrftimeItems;
-use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use chrono::{DateTime, Duration, NaiveDate, NaiveDateTime, NaiveTime, Timelike, Utc};
 
 use promkit::{
     crossterm::{
@@ -14,10 +31,12 @@ use promkit::{
     preset::listbox::Listbox,
     preset::readline::Readline,
 };
-use regex;
 
 use comfy_table::Table;
 
+/// Converts a row item into the string shown in a table cell
+type Converter<T> = Box<dyn Fn(&T) -> String>;
+
 fn parse_input(input: &str) -> Result<DateTime<Utc>, chrono::ParseError> {
     let naive_dt = NaiveDateTime::parse_from_str(input, "%Y%m%d%H%M")?;
     Ok(DateTime::from_naive_utc_and_offset(naive_dt, Utc))
@@ -27,25 +46,63 @@ fn add_hours(dt: DateTime<Utc>, hours: u32) -> DateTime<Utc> {
     dt + Duration::hours(hours.into())
 }
 
-fn format_output(dt: DateTime<Utc>) -> String {
-    let items = StrftimeItems::new("%a %H");
-    dt.format_with_items(items).to_string()
+/// Formats a UTC time in the zone selected in settings
+///
+/// * `dt`: time to format
+/// * `format`: strftime-like format
+/// * `settings`: settings with time display and observatory timezone
+fn format_time(dt: DateTime<Utc>, format: &str, settings: &Settings) -> String {
+    if *settings.get_local_time() {
+        dt.with_timezone(&settings.get_timezone())
+            .format(format)
+            .to_string()
+    } else {
+        dt.format(format).to_string()
+    }
+}
+
+/// Returns the name of the zone selected in settings
+///
+/// * `settings`: settings with time display and observatory timezone
+fn zone_name(settings: &Settings) -> String {
+    if *settings.get_local_time() {
+        settings.get_timezone().name().to_string()
+    } else {
+        "UTC".to_string()
+    }
+}
+
+fn format_output(dt: DateTime<Utc>, settings: &Settings) -> String {
+    format_time(dt, "%a %H", settings)
 }
 
 fn create_weather_table() {
     let _ = disable_raw_mode();
+    let settings = Settings::new().unwrap();
+    let time_header = format!("Time ({})", zone_name(&settings));
     let mut table = Table::new();
-    let data = weather::prepare_data().unwrap();
+    let data = match weather::prepare_data() {
+        Ok(data) => data,
+        Err(e) => {
+            println!("7timer unavailable: {}", e);
+            create_hourly_weather_table(settings);
+            return;
+        }
+    };
+    match night_quality::rate_forecast(&data, &night_quality::Weights::default()) {
+        Ok(nights) => println!("{}", night_quality::summary(&nights)),
+        Err(e) => println!("Night quality unavailable: {}", e),
+    }
     let timezero = format!("{}00", data.init);
     let forecast = data.dataseries;
 
-    let converters: Vec<Box<dyn Fn(&Forecast) -> String>> = vec![
+    let converters: Vec<Converter<Forecast>> = vec![
         Box::new(move |item: &Forecast| {
             let timezero_clone = timezero.clone();
             match parse_input(timezero_clone.as_str()) {
                 Ok(result) => {
                     let new_dt = add_hours(result, item.timepoint as u32);
-                    format_output(new_dt)
+                    format_output(new_dt, &settings)
                 }
                 Err(e) => format!("Errore durante il parsing: {}", e),
             }
@@ -67,7 +124,15 @@ fn create_weather_table() {
     ];
 
     table.set_width(80).set_header(vec![
-        "Time", "Clouds", "Seeing", "Transp", "Instab", "RH2m", "Wind", "T", "Prec",
+        time_header.as_str(),
+        "Clouds",
+        "Seeing",
+        "Transp",
+        "Instab",
+        "RH2m",
+        "Wind",
+        "T",
+        "Prec",
     ]);
     for item in forecast {
         let row: Vec<String> = converters
@@ -79,38 +144,684 @@ fn create_weather_table() {
     println!("{table}");
 }
 
-fn generate_sun_moon_times_table() {
+/// Prints the forecast of the first other provider answering, every 3 hours as 7timer
+///
+/// * `settings`: settings with the observatory
+fn create_hourly_weather_table(settings: Settings) {
+    let (provider, forecast) = match weather_providers::fallback_forecast(
+        *settings.get_latitude() as f64,
+        *settings.get_longitude() as f64,
+        *settings.get_altitude() as f64,
+    ) {
+        Ok(result) => result,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    println!("Forecast by {}", provider);
+    match night_quality::rate_hourly_forecast(&forecast, &night_quality::Weights::default()) {
+        Ok(nights) => println!("{}", night_quality::summary(&nights)),
+        Err(e) => println!("Night quality unavailable: {}", e),
+    }
+    let time_header = format!("Time ({})", zone_name(&settings));
+    let mut table = Table::new();
+    let converters: Vec<Converter<HourlyForecast>> = vec![
+        Box::new(move |item: &HourlyForecast| format_output(item.time, &settings)),
+        Box::new(|item: &HourlyForecast| format!("{:.0}%", item.cloud_cover)),
+        Box::new(|item: &HourlyForecast| format!("{:.0}%", item.humidity)),
+        Box::new(|item: &HourlyForecast| format!("{:.1} m/s", item.wind_speed)),
+        Box::new(|item: &HourlyForecast| format!("{:.0}", item.temperature)),
+        Box::new(|item: &HourlyForecast| format!("{:.0}", item.dew_point)),
+        Box::new(|item: &HourlyForecast| match item.precipitation {
+            Some(amount) => format!("{:.1} mm/h", amount),
+            None => "N/A".to_string(),
+        }),
+    ];
+
+    table.set_width(80).set_header(vec![
+        time_header.as_str(),
+        "Clouds",
+        "RH2m",
+        "Wind",
+        "T",
+        "Dew",
+        "Prec",
+    ]);
+    for item in forecast
+        .iter()
+        .filter(|item| item.time.hour() % 3 == 0)
+        .take(24)
+    {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+fn create_outlook_table() {
     let _ = disable_raw_mode();
-    let data: SunMoonTimesResponse = sun_moon_times::prepare_data().unwrap();
-    println!("All times are {}", data.tzid);
-    println!("Sunrise: {}", data.results.sunrise);
-    println!("Sunset: {}", data.results.sunset);
-    println!("Solar noon: {}", data.results.solar_noon);
-    println!("Day length: {}", data.results.day_length);
+    let mut table = Table::new();
+    let data = match weather::prepare_civillight_data() {
+        Ok(data) => data,
+        Err(e) => {
+            println!("Error: {}", e);
+            return;
+        }
+    };
+    let converters: Vec<Converter<CivilLightForecast>> = vec![
+        Box::new(|item: &CivilLightForecast| match item.date() {
+            Some(date) => date.format("%a %Y-%m-%d").to_string(),
+            None => item.date.to_string(),
+        }),
+        Box::new(|item: &CivilLightForecast| weather::weather_description(&item.weather)),
+        Box::new(|item: &CivilLightForecast| item.temp2m.min.to_string()),
+        Box::new(|item: &CivilLightForecast| item.temp2m.max.to_string()),
+        Box::new(|item: &CivilLightForecast| item.wind10m_max.to_str().to_string()),
+    ];
+
+    table
+        .set_width(80)
+        .set_header(vec!["Date", "Weather", "T min", "T max", "Wind max"]);
+    for item in &data.dataseries {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+/// Asks for the 3-day astro forecast or the 7-day outlook and prints it
+fn weather_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let view: String = Listbox::new(vec!["3-day astro forecast", "7-day outlook"])
+        .title("Select the forecast")
+        .prompt()?
+        .run()?;
+    if view == "7-day outlook" {
+        create_outlook_table();
+    } else {
+        create_weather_table();
+    }
+    Ok(())
+}
+
+/// Asks for a date, returning today if the answer is empty
+///
+/// * `title`: prompt title
+fn ask_date(title: &str) -> Result<NaiveDate, Box<dyn std::error::Error>> {
+    let date: String = Readline::default()
+        .title(title)
+        .validator(
+            |x| x.is_empty() || NaiveDate::parse_from_str(x, "%Y-%m-%d").is_ok(),
+            |x| format!("{} is not a valid date", x),
+        )
+        .prompt()?
+        .run()?;
+    if date.is_empty() {
+        Ok(Utc::now().date_naive())
+    } else {
+        Ok(NaiveDate::parse_from_str(&date, "%Y-%m-%d")?)
+    }
+}
+
+/// Asks for a time of the day
+///
+/// * `title`: prompt title
+fn ask_time(title: &str) -> Result<NaiveTime, Box<dyn std::error::Error>> {
+    let time: String = Readline::default()
+        .title(title)
+        .validator(
+            |x| NaiveTime::parse_from_str(x, "%H:%M").is_ok(),
+            |x| format!("{} is not a valid time", x),
+        )
+        .prompt()?
+        .run()?;
+    Ok(NaiveTime::parse_from_str(&time, "%H:%M")?)
+}
+
+/// Asks for a whole number, until it is in `range`
+///
+/// * `title`: prompt title
+/// * `range`: accepted values
+fn ask_number(
+    title: &str,
+    range: std::ops::RangeInclusive<u32>,
+) -> Result<u32, Box<dyn std::error::Error>> {
+    loop {
+        let number: String = Readline::default()
+            .title(title)
+            .validator(
+                |x| x.parse::<u32>().is_ok(),
+                |x| format!("{} is not a valid number", x),
+            )
+            .prompt()?
+            .run()?;
+        let number = number.parse::<u32>()?;
+        if range.contains(&number) {
+            return Ok(number);
+        }
+        println!(
+            "{} is not between {} and {}",
+            number,
+            range.start(),
+            range.end()
+        );
+    }
+}
+
+/// Formats an event time in the zone selected in settings, "N/A" if it does not happen
+///
+/// * `time`: event time
+/// * `settings`: settings with time display and observatory timezone
+fn format_event(time: Option<DateTime<Utc>>, settings: &Settings) -> String {
+    match time {
+        Some(time) => format_time(time, "%H:%M:%S", settings),
+        None => "N/A".to_string(),
+    }
+}
+
+/// Formats a duration as hours and minutes
+///
+/// * `duration`: duration to format
+fn format_duration(duration: Duration) -> String {
+    format!(
+        "{:02}h {:02}m",
+        duration.num_hours(),
+        duration.num_minutes() % 60
+    )
+}
+
+fn generate_sun_moon_times_table() -> Result<(), Box<dyn std::error::Error>> {
+    let date = ask_date("Date (YYYY-MM-DD, empty for today): ")?;
+    let _ = disable_raw_mode();
+    let data: SunMoonTimesResponse = sun_moon_times::prepare_data(date)?;
+    let results = &data.results;
+    let settings = Settings::new().unwrap();
+    println!("Date: {}", date);
+    println!("All times are {}", zone_name(&settings));
+    println!("Sunrise: {}", format_event(results.sunrise, &settings));
+    println!("Sunset: {}", format_event(results.sunset, &settings));
+    println!(
+        "Solar noon: {}",
+        format_event(Some(results.solar_noon), &settings)
+    );
+    println!("Day length: {}", format_duration(results.day_length));
     println!(
         "Civil twilight begin: {}",
-        data.results.civil_twilight_begin
+        format_event(results.civil_twilight_begin, &settings)
+    );
+    println!(
+        "Civil twilight end: {}",
+        format_event(results.civil_twilight_end, &settings)
     );
-    println!("Civil twilight end: {}", data.results.civil_twilight_end);
     println!(
         "Nautical twilight begin: {}",
-        data.results.nautical_twilight_begin
+        format_event(results.nautical_twilight_begin, &settings)
     );
     println!(
         "Nautical twilight end: {}",
-        data.results.nautical_twilight_end
+        format_event(results.nautical_twilight_end, &settings)
     );
     println!(
         "Astronomical twilight begin: {}",
-        data.results.astronomical_twilight_begin
+        format_event(results.astronomical_twilight_begin, &settings)
     );
     println!(
         "Astronomical twilight end: {}",
-        data.results.astronomical_twilight_end
+        format_event(results.astronomical_twilight_end, &settings)
+    );
+    println!(
+        "Astronomical night length: {}",
+        format_duration(results.astronomical_night_length())
+    );
+    println!("Moonrise: {}", format_event(results.moonrise, &settings));
+    println!("Moonset: {}", format_event(results.moonset, &settings));
+    println!(
+        "Moon transit: {}",
+        format_event(results.moon_transit, &settings)
+    );
+    println!("Moon phase angle: {:.1}°", results.moon_phase_angle);
+    println!(
+        "Moon illumination: {:.0}%",
+        results.moon_illumination * 100.0
+    );
+    println!("Moon age: {:.1} days", results.moon_age);
+    Ok(())
+}
+
+fn generate_darkness_table() -> Result<(), Box<dyn std::error::Error>> {
+    let start = ask_date("First night (YYYY-MM-DD, empty for today): ")?;
+    let nights: String = Readline::default()
+        .title("Number of nights: ")
+        .validator(
+            |x| x.parse::<u32>().is_ok_and(|nights| nights > 0),
+            |x| format!("{} is not a valid number", x),
+        )
+        .prompt()?
+        .run()?;
+    let _ = disable_raw_mode();
+    let darkness = sun_moon_times::prepare_darkness(start, nights.parse::<u32>()?)?;
+    let settings = Settings::new().unwrap();
+    let zone = zone_name(&settings);
+    let mut table = Table::new();
+    table.set_width(80).set_header(vec![
+        "Night".to_string(),
+        format!("Darkness begins ({})", zone),
+        format!("Darkness ends ({})", zone),
+        "Length".to_string(),
+        "Moon".to_string(),
+    ]);
+    let format_begin_end = |time: Option<DateTime<Utc>>| match time {
+        Some(time) => format_time(time, "%H:%M", &settings),
+        None => "N/A".to_string(),
+    };
+    for night in darkness {
+        table.add_row(vec![
+            night.date.format("%a %Y-%m-%d").to_string(),
+            if night.polar_night {
+                "All day".to_string()
+            } else {
+                format_begin_end(night.begin)
+            },
+            if night.polar_night {
+                "All day".to_string()
+            } else {
+                format_begin_end(night.end)
+            },
+            format_duration(night.duration()),
+            format!("{:.0}%", night.moon_illumination * 100.0),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
+}
+
+/// Searches the local orbit catalogue or imports orbits into it
+fn orbit_catalogue() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    let mut catalogue = Catalogue::load()?;
+    println!("\n\n\nOrbit Catalogue ({} orbits)\n", catalogue.len());
+    let action: String = Listbox::new(vec!["Search", "Import MPCORB.DAT or NEA.txt"])
+        .title("Select an action")
+        .prompt()?
+        .run()?;
+    if action == "Search" {
+        let query: String = Readline::default()
+            .title("Number, name or designation: ")
+            .prompt()?
+            .run()?;
+        let _ = disable_raw_mode();
+        create_orbits_table(&catalogue.search(&query));
+    } else {
+        let default_path = orbit::mpcorb_path();
+        let path: String = Readline::default()
+            .title(format!("File (empty for {}): ", default_path.display()))
+            .prompt()?
+            .run()?;
+        let _ = disable_raw_mode();
+        let path = if path.trim().is_empty() {
+            default_path
+        } else {
+            std::path::PathBuf::from(path.trim())
+        };
+        match catalogue.import(&path) {
+            Ok(count) => {
+                catalogue.save()?;
+                println!(
+                    "Imported {} orbits, {} in catalogue",
+                    count,
+                    catalogue.len()
+                );
+            }
+            Err(error) => println!("Error: {}", error),
+        }
+    }
+    Ok(())
+}
+
+fn create_orbits_table(data: &[&Orbit]) {
+    let mut table = Table::new();
+    let converters: Vec<Converter<Orbit>> = vec![
+        Box::new(|item: &Orbit| item.designation.to_string()),
+        Box::new(|item: &Orbit| format!("{:.2}", item.absolute_magnitude)),
+        Box::new(|item: &Orbit| item.epoch_datetime().format("%Y-%m-%d").to_string()),
+        Box::new(|item: &Orbit| format!("{:.4}", item.semi_major_axis)),
+        Box::new(|item: &Orbit| format!("{:.4}", item.eccentricity)),
+        Box::new(|item: &Orbit| format!("{:.2}", item.inclination)),
+        Box::new(|item: &Orbit| format!("{:.4}", item.perihelion_distance())),
+    ];
+    table
+        .set_width(80)
+        .set_header(vec!["Designation", "H", "Epoch", "a", "e", "i", "q"]);
+    for item in data {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+/// Computes the ephemeris of an object of the local orbit catalogue
+fn object_ephemeris() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    println!("\n\n\nObject Ephemeris\n\nStart time is UTC\n");
+    let catalogue = Catalogue::load()?;
+    if catalogue.is_empty() {
+        println!("The orbit catalogue is empty, import MPCORB.DAT or NEA.txt first");
+        return Ok(());
+    }
+    let query: String = Readline::default()
+        .title("Number, name or designation: ")
+        .prompt()?
+        .run()?;
+    let found = catalogue.search(&query);
+    let orbit = match found.len() {
+        0 => {
+            let _ = disable_raw_mode();
+            println!("No orbit found for {}", query);
+            return Ok(());
+        }
+        1 => found[0],
+        _ => {
+            let designations: Vec<String> = found
+                .iter()
+                .map(|orbit| orbit.designation.clone())
+                .collect();
+            let selected: String = Listbox::new(designations)
+                .title("Select an object")
+                .prompt()?
+                .run()?;
+            found
+                .into_iter()
+                .find(|orbit| orbit.designation == selected)
+                .ok_or("Object not found")?
+        }
+    };
+    let date = ask_date("Date (YYYY-MM-DD, empty for today): ")?;
+    let time = ask_time("Time (HH:MM): ")?;
+    let duration = ask_number("Duration in hours (1-240): ", 1..=240)?;
+    let step = ask_number("Step in minutes (1-1440): ", 1..=1440)?;
+    let propagation: String = Listbox::new(vec![
+        "Two-body (Keplerian)",
+        "N-body (planetary perturbations, for NEOs and old epochs)",
+    ])
+    .title("Select the propagation")
+    .prompt()?
+    .run()?;
+    let propagation = if propagation.starts_with("N-body") {
+        Propagation::NBody
+    } else {
+        Propagation::TwoBody
+    };
+    let _ = disable_raw_mode();
+    let settings = Settings::new().unwrap();
+    let start = date.and_time(time).and_utc();
+    match ephemeris::compute_ephemeris(
+        orbit,
+        start,
+        start + Duration::hours(duration.into()),
+        Duration::minutes(step.into()),
+        &Observer::from_settings(&settings),
+        propagation,
+    ) {
+        Ok(entries) => {
+            println!("\n{}", orbit.designation);
+            create_ephemeris_table(&entries, &settings);
+        }
+        Err(error) => println!("Error: {}", error),
+    }
+    Ok(())
+}
+
+fn create_ephemeris_table(data: &[EphemerisEntry], settings: &Settings) {
+    let mut table = Table::new();
+    let time_header = format!("Time ({})", zone_name(settings));
+    let settings = settings.clone();
+    let converters: Vec<Converter<EphemerisEntry>> = vec![
+        Box::new(move |item: &EphemerisEntry| format_time(item.time, "%Y-%m-%d %H:%M", &settings)),
+        Box::new(|item: &EphemerisEntry| coordinates::format_ra(item.ra)),
+        Box::new(|item: &EphemerisEntry| coordinates::format_dec(item.dec)),
+        Box::new(|item: &EphemerisEntry| format!("{:.4}", item.earth_distance)),
+        Box::new(|item: &EphemerisEntry| format!("{:.4}", item.sun_distance)),
+        Box::new(|item: &EphemerisEntry| format!("{:.1}", item.phase_angle)),
+        Box::new(|item: &EphemerisEntry| format!("{:.1}", item.solar_elongation)),
+        Box::new(|item: &EphemerisEntry| format!("{:.2}", item.rate)),
+        Box::new(|item: &EphemerisEntry| format!("{:.0}", item.position_angle)),
+        Box::new(|item: &EphemerisEntry| format!("{:.1}", item.magnitude)),
+        Box::new(|item: &EphemerisEntry| format!("{:.0}", item.altitude)),
+        Box::new(|item: &EphemerisEntry| format!("{:.0}", item.azimuth)),
+    ];
+    table.set_header(vec![
+        time_header.as_str(),
+        "RA",
+        "DEC",
+        "Delta (AU)",
+        "r (AU)",
+        "Phase",
+        "Elong",
+        "Rate (\"/min)",
+        "PA",
+        "V",
+        "Alt",
+        "Az",
+    ]);
+    for item in data {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+/// Shows the NEO Confirmation Page, sorted by score or by visibility tonight
+fn neocp_listing() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    println!("\n\n\nNEOCP Listing\n");
+    let order: String = Listbox::new(vec!["Score", "Visibility tonight"])
+        .title("Sort by")
+        .prompt()?
+        .run()?;
+    let _ = disable_raw_mode();
+    let mut objects = match neocp::prepare_data() {
+        Ok(objects) => objects,
+        Err(error) => {
+            println!("Error: {}", error);
+            return Ok(());
+        }
+    };
+    let settings = Settings::new().unwrap();
+    let observer = Observer::from_settings(&settings);
+    let profile = settings.get_horizon();
+    let tonight = sun_moon_times::prepare_darkness(Utc::now().date_naive(), 1)?
+        .into_iter()
+        .next()
+        .and_then(|night| night.begin.zip(night.end));
+    match tonight {
+        Some((begin, end)) => {
+            println!(
+                "Darkness from {} to {} ({})",
+                format_time(begin, "%H:%M", &settings),
+                format_time(end, "%H:%M", &settings),
+                zone_name(&settings)
+            );
+            if order == "Visibility tonight" {
+                neocp::sort_by_visibility(&mut objects, begin, end, &observer, &profile);
+            }
+        }
+        None => println!("No astronomical darkness tonight"),
+    }
+    create_neocp_table(&objects, tonight, &observer, &profile);
+    let designation: String = Readline::default()
+        .title("Designation for ephemeris and pointings (empty to go back): ")
+        .prompt()?
+        .run()?;
+    if !designation.trim().is_empty() {
+        neocp_pointings(designation.trim(), &settings)?;
+    }
+    Ok(())
+}
+
+/// Shows the NEOCP ephemeris of an object and the fields covering its variant orbits
+///
+/// * `designation`: NEOCP temporary designation
+/// * `settings`: settings with observatory and time display
+fn neocp_pointings(
+    designation: &str,
+    settings: &Settings,
+) -> Result<(), Box<dyn std::error::Error>> {
+    let (fov_width, fov_height) = match settings.get_equipment() {
+        Some(setup) => {
+            println!("Field of view from {}", setup.name);
+            setup.field_of_view()
+        }
+        None => (
+            ask_number("Field of view along RA (arcmin): ", 1..=600)? as f64,
+            ask_number("Field of view along Dec (arcmin): ", 1..=600)? as f64,
+        ),
+    };
+    let _ = disable_raw_mode();
+    let entries = match neocp_ephemeris::prepare_ephemeris(designation) {
+        Ok(entries) => entries,
+        Err(error) => {
+            println!("Error: {}", error);
+            return Ok(());
+        }
+    };
+    create_neocp_ephemeris_table(&entries, settings);
+    let Some(entry) = entries.iter().find(|entry| entry.offsets_url.is_some()) else {
+        println!("The MPC has no variant orbits for {}", designation);
+        return Ok(());
+    };
+    let spread = match neocp_ephemeris::prepare_spread(entry) {
+        Ok(spread) => spread,
+        Err(error) => {
+            println!("Error: {}", error);
+            return Ok(());
+        }
+    };
+    println!(
+        "\nVariant orbits at {} ({}): {} spread over {:.1}' x {:.1}'",
+        format_time(entry.time, "%H:%M", settings),
+        zone_name(settings),
+        spread.offsets().len(),
+        spread.width(),
+        spread.height()
     );
+    if spread.fits(fov_width, fov_height) {
+        println!("One field covers the whole spread");
+    } else {
+        println!("The spread exceeds the field of view, a mosaic is needed");
+    }
+    let pointings =
+        neocp_ephemeris::suggest_pointings(entry.ra, entry.dec, &spread, fov_width, fov_height);
+    let mut table = Table::new();
+    table.set_header(vec![
+        "Field",
+        "RA",
+        "DEC",
+        "Offset RA (')",
+        "Offset DEC (')",
+        "Variants",
+    ]);
+    for (index, pointing) in pointings.iter().enumerate() {
+        table.add_row(vec![
+            (index + 1).to_string(),
+            coordinates::format_ra(pointing.ra),
+            coordinates::format_dec(pointing.dec),
+            format!("{:+.1}", pointing.ra_offset),
+            format!("{:+.1}", pointing.dec_offset),
+            pointing.variants.to_string(),
+        ]);
+    }
+    println!("{table}");
+    Ok(())
 }
 
-const SCHEDULING: [&str; 5] = ["1", "2", "3", "9", "0"];
+fn create_neocp_ephemeris_table(data: &[NeocpEphemerisEntry], settings: &Settings) {
+    let mut table = Table::new();
+    let time_header = format!("Time ({})", zone_name(settings));
+    let settings = settings.clone();
+    let converters: Vec<Converter<NeocpEphemerisEntry>> = vec![
+        Box::new(move |item: &NeocpEphemerisEntry| {
+            format_time(item.time, "%Y-%m-%d %H:%M", &settings)
+        }),
+        Box::new(|item: &NeocpEphemerisEntry| coordinates::format_ra(item.ra)),
+        Box::new(|item: &NeocpEphemerisEntry| coordinates::format_dec(item.dec)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.1}", item.magnitude)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.2}", item.rate)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.0}", item.position_angle)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.0}", item.altitude)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.0}", item.azimuth)),
+        Box::new(|item: &NeocpEphemerisEntry| format!("{:.0}", item.moon_distance)),
+    ];
+    table.set_header(vec![
+        time_header.as_str(),
+        "RA",
+        "DEC",
+        "V",
+        "Rate (\"/min)",
+        "PA",
+        "Alt",
+        "Az",
+        "Moon dist",
+    ]);
+    for item in data {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+fn create_neocp_table(
+    data: &[NeocpObject],
+    tonight: Option<(DateTime<Utc>, DateTime<Utc>)>,
+    observer: &Observer,
+    profile: &HorizonProfile,
+) {
+    let mut table = Table::new();
+    let observer = *observer;
+    let profile = profile.clone();
+    let converters: Vec<Converter<NeocpObject>> = vec![
+        Box::new(|item: &NeocpObject| item.designation.clone()),
+        Box::new(|item: &NeocpObject| item.score.to_string()),
+        Box::new(|item: &NeocpObject| item.discovery.format("%Y-%m-%d %H:%M").to_string()),
+        Box::new(|item: &NeocpObject| coordinates::format_ra(item.ra)),
+        Box::new(|item: &NeocpObject| coordinates::format_dec(item.dec)),
+        Box::new(|item: &NeocpObject| format!("{:.1}", item.magnitude)),
+        Box::new(|item: &NeocpObject| item.updated.clone()),
+        Box::new(|item: &NeocpObject| item.observations.to_string()),
+        Box::new(|item: &NeocpObject| format!("{:.2}", item.arc)),
+        Box::new(|item: &NeocpObject| format!("{:.1}", item.absolute_magnitude)),
+        Box::new(|item: &NeocpObject| format!("{:.3}", item.not_seen)),
+        Box::new(move |item: &NeocpObject| match tonight {
+            Some((begin, end)) => {
+                format_duration(item.visible_time(begin, end, &observer, &profile))
+            }
+            None => "N/A".to_string(),
+        }),
+    ];
+    table.set_header(vec![
+        "Designation",
+        "Score",
+        "Discovery (UTC)",
+        "RA",
+        "DEC",
+        "V",
+        "Updated",
+        "NObs",
+        "Arc",
+        "H",
+        "Not seen",
+        "Visible tonight",
+    ]);
+    for item in data {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+const SCHEDULING: [&str; 10] = ["1", "2", "3", "4", "5", "6", "7", "8", "9", "0"];
 
 // Funzione di validazione
 fn validate_scheduling_menu_option(option: &str) -> bool {
@@ -137,6 +848,11 @@ pub fn scheduling_menu() -> Result<(), Box<dyn std::error::Error>> {
 1. Weather Forecast
 2. Sun and moon times
 3. Observing target list
+4. Darkness over the next nights
+5. Orbit catalogue
+6. Object ephemeris
+7. NEOCP listing
+8. Check astrometry file
 9. Back
 0. Quit"
     );
@@ -149,9 +865,14 @@ pub fn scheduling_menu() -> Result<(), Box<dyn std::error::Error>> {
         .prompt()?;
     let result = p.run()?;
     match result.as_str() {
-        "1" => create_weather_table(),
-        "2" => generate_sun_moon_times_table(),
+        "1" => weather_menu()?,
+        "2" => generate_sun_moon_times_table()?,
         "3" => observing_target_list()?,
+        "4" => generate_darkness_table()?,
+        "5" => orbit_catalogue()?,
+        "6" => object_ephemeris()?,
+        "7" => neocp_listing()?,
+        "8" => check_astrometry()?,
         "9" => tui::settings_menu()?,
         _ => (),
     }
@@ -160,6 +881,9 @@ pub fn scheduling_menu() -> Result<(), Box<dyn std::error::Error>> {
 
 const WEATHER_FORECAST: [&str; 2] = ["9", "0"];
 
+/// Objects requested when the limiting magnitude of the equipment sets the list length
+const EQUIPMENT_MAX_OBJECTS: u32 = 1000;
+
 // Funzione di validazione
 fn validate_weather_forecast_option(option: &str) -> bool {
     WEATHER_FORECAST.contains(&option)
@@ -194,185 +918,126 @@ pub fn weather_forecast() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Checks an MPC1992 or ADES file before submission, showing the problems found
+pub fn check_astrometry() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    println!("\n\n\nCheck Astrometry File\n");
+    let settings = Settings::new().unwrap();
+    let path: String = Readline::default()
+        .title("MPC1992 or ADES file: ")
+        .prompt()?
+        .run()?;
+    let _ = disable_raw_mode();
+    match astrometry_import::import_file(std::path::Path::new(path.trim()), settings.get_mpc_code())
+    {
+        Ok(astrometry) => {
+            println!(
+                "{} observations read in {} format, {} problems found",
+                astrometry.observations.len(),
+                astrometry.format,
+                astrometry.issues.len()
+            );
+            if astrometry.is_valid() {
+                println!("The file is ready for submission");
+            } else {
+                create_issue_table(&astrometry.issues);
+            }
+        }
+        Err(error) => println!("Error: {}", error),
+    }
+    Ok(())
+}
+
+fn create_issue_table(data: &[Issue]) {
+    let mut table = Table::new();
+    let converters: Vec<Converter<Issue>> = vec![
+        Box::new(|item: &Issue| item.line.to_string()),
+        Box::new(|item: &Issue| item.field.clone()),
+        Box::new(|item: &Issue| item.message.clone()),
+    ];
+    table.set_header(vec!["Line", "Field", "Problem"]);
+    for item in data {
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
 /// Creates the observing target list
 pub fn observing_target_list() -> Result<(), Box<dyn std::error::Error>> {
     let _ = disable_raw_mode();
     execute!(std::io::stdout(), Clear(ClearType::All))?;
-    println!("\n\n\nObserving Target List\n\n");
-    let year: String = Readline::default()
-        .title("Year (YYYY): ")
-        .validator(
-            |x| {
-                let rex = regex::Regex::new(r"^\d{4}$").unwrap();
-                rex.is_match(x)
-            },
-            |x| format!("{} is not a valid year", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let month: String = Readline::default()
-        .title("Month (MM): ")
-        .validator(
-            |x| {
-                let rex = regex::Regex::new(r"^\d{1,2}$").unwrap();
-                rex.is_match(x)
-            },
-            |x| format!("{} is not a valid month", x),
-        )
-        .validator(
-            |x| {
-                let accepted_values = [
-                    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12",
-                ];
-                accepted_values.contains(&x)
-            },
-            |x| format!("{} is not a valid month", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let day: String = Readline::default()
-        .title("Day (DD): ")
-        .validator(
-            |x| {
-                let rex = regex::Regex::new(r"^\d{1,2}$").unwrap();
-                rex.is_match(x)
-            },
-            |x| format!("{} is not a valid day", x),
-        )
-        .validator(
-            |x| {
-                let accepted_values = [
-                    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14",
-                    "15", "16", "17", "18", "19", "20", "21", "22", "23", "24", "25", "26", "27",
-                    "28", "29", "30", "31",
-                ];
-                accepted_values.contains(&x)
-            },
-            |x| format!("{} is not a valid day", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let hour: String = Readline::default()
-        .title("Hour (HH): ")
-        .validator(
-            |x| {
-                let rex = regex::Regex::new(r"^\d{1,2}$").unwrap();
-                rex.is_match(x)
-            },
-            |x| format!("{} is not a valid day", x),
-        )
-        .validator(
-            |x| {
-                let accepted_values = [
-                    "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14",
-                    "15", "16", "17", "18", "19", "20", "21", "22", "23",
-                ];
-                accepted_values.contains(&x)
-            },
-            |x| format!("{} is not a valid hour", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let minute: String = Readline::default()
-        .title("Minute (MM): ")
-        .validator(
-            |x| {
-                let rex = regex::Regex::new(r"^\d{1,2}$").unwrap();
-                rex.is_match(x)
-            },
-            |x| format!("{} is not a valid minute", x),
-        )
-        .validator(
-            |x| {
-                let accepted_values = [
-                    "0", "1", "2", "3", "4", "5", "6", "7", "8", "9", "10", "11", "12", "13", "14",
-                    "15", "16", "17", "18", "19", "20", "21", "22", "23", "24", "25", "26", "27",
-                    "28", "29", "30", "31", "32", "33", "34", "35", "36", "37", "38", "39", "40",
-                    "41", "42", "43", "44", "45", "46", "47", "48", "49", "50", "51", "52", "53",
-                    "54", "55", "56", "57", "58", "59",
-                ];
-                accepted_values.contains(&x)
-            },
-            |x| format!("{} is not a valid minute", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let duration: String = Readline::default()
-        .title("Duration in hours (H or HH): ")
-        .validator(
-            |x| x.parse::<u32>().is_ok(),
-            |x| format!("{} is not a valid number", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let max_objects: String = Readline::default()
-        .title("Maximum number of objects: ")
-        .validator(
-            |x| x.parse::<u32>().is_ok(),
-            |x| format!("{} is not a valid number", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let min_alt: String = Readline::default()
-        .title("Minimum Altitude (deg): ")
-        .validator(
-            |x| x.parse::<u32>().is_ok(),
-            |x| format!("{} is not a valid number", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let solar_elong: String = Readline::default()
-        .title("Maximum Solar elongation (deg): ")
-        .validator(
-            |x| x.parse::<u32>().is_ok(),
-            |x| format!("{} is not a valid number", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
-    let lunar_elong: String = Readline::default()
-        .title("Maximum Lunar elongation (deg): ")
-        .validator(
-            |x| x.parse::<u32>().is_ok(),
-            |x| format!("{} is not a valid number", x),
-        )
-        .prompt()
-        .unwrap()
-        .run()?;
+    println!("\n\n\nObserving Target List\n\nObserving time is UTC\n");
+    let date = ask_date("Date (YYYY-MM-DD, empty for today): ")?;
+    let time = ask_time("Time (HH:MM): ")?;
+    let duration = ask_number("Duration in hours (1-24): ", 1..=24)?;
+    let settings = Settings::new().unwrap();
+    let equipment = settings.get_equipment().cloned();
+    let max_objects = match &equipment {
+        Some(setup) => {
+            println!(
+                "Limiting magnitude {:.1} from {}",
+                setup.limiting_magnitude(),
+                setup.name
+            );
+            EQUIPMENT_MAX_OBJECTS
+        }
+        None => ask_number("Maximum number of objects: ", 1..=u32::MAX)?,
+    };
+    let min_alt = ask_number("Minimum Altitude (deg): ", 0..=90)?;
+    let solar_elong = ask_number("Minimum Solar elongation (deg): ", 0..=180)?;
+    let lunar_elong = ask_number("Minimum Lunar elongation (deg): ", 0..=180)?;
     let object_type: String = Listbox::new(vec!["Asteroid", "NEO", "Comet"])
         .title("Select object type")
-        .prompt()
-        .unwrap()
+        .prompt()?
         .run()?;
-    let object_type_code: &str = match object_type {
-        object_type if object_type.as_str() == "Asteroid" => "mp",
-        object_type if object_type.as_str() == "NEO" => "neo",
-        object_type if object_type.as_str() == "Comet" => "cmt",
-        _ => "mp",
+    let object_type = match object_type.as_str() {
+        "NEO" => ObjectType::Neo,
+        "Comet" => ObjectType::Comet,
+        _ => ObjectType::MinorPlanet,
     };
-    let whats_up_params: WhatsUpParams = WhatsUpParams {
-        year,
-        month,
-        day,
-        hour,
-        minute,
-        max_objects,
-        duration,
-        min_alt,
-        solar_elong,
-        lunar_elong,
-        object_type: object_type_code.to_string(),
+    let mut builder = WhatsUpParams::builder();
+    if let Some(setup) = &equipment {
+        builder = builder.max_magnitude(setup.limiting_magnitude() as f32);
+    }
+    let whats_up_params: WhatsUpParams = builder
+        .start(date.and_time(time).and_utc())
+        .duration(duration)
+        .max_objects(max_objects)
+        .min_alt(min_alt)
+        .solar_elong(solar_elong)
+        .lunar_elong(lunar_elong)
+        .object_type(object_type)
+        .build()?;
+    let start = whats_up_params.start();
+    println!(
+        "Targets from {} ({})",
+        format_time(start, "%Y-%m-%d %H:%M", &settings),
+        zone_name(&settings)
+    );
+    let source: String = Listbox::new(vec!["MPC website", "Local MPCORB.DAT"])
+        .title("Select targets source")
+        .prompt()?
+        .run()?;
+    let response = if source == "MPC website" {
+        parse_whats_up_response(&whats_up_params)
+    } else {
+        whats_up::local_whats_up_response(&whats_up_params)
     };
-    let data: Vec<PossibleTarget> = parse_whats_up_response(&whats_up_params);
-    create_whats_up_list_table(data);
+    match response {
+        Ok(data) => {
+            create_whats_up_list_table(&data);
+            let show_curves = Listbox::new(vec!["Yes", "No"])
+                .title("Show altitude curves for the night?")
+                .prompt()?
+                .run()?;
+            if show_curves == "Yes" {
+                create_altitude_curves_table(&data, start);
+            }
+        }
+        Err(error) => println!("Error: {}", error),
+    }
     let mut p = Readline::default()
         .title("\n9 to go back, 0 to quit:")
         .validator(
@@ -387,25 +1052,117 @@ pub fn observing_target_list() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
-fn create_whats_up_list_table(data: Vec<PossibleTarget>) {
+fn create_whats_up_list_table(data: &[PossibleTarget]) {
     let _ = disable_raw_mode();
     let mut table = Table::new();
-    let converters: Vec<Box<dyn Fn(&PossibleTarget) -> String>> = vec![
+    let settings = Settings::new().unwrap();
+    let max_time_header = format!("Max Time ({})", zone_name(&settings));
+    let converters: Vec<Converter<PossibleTarget>> = vec![
         Box::new(|item: &PossibleTarget| item.designation.to_string()),
         Box::new(|item: &PossibleTarget| item.magnitude.to_string()),
-        Box::new(|item: &PossibleTarget| item.ra.to_string()),
-        Box::new(|item: &PossibleTarget| item.dec.to_string()),
-        Box::new(|item: &PossibleTarget| item.altitude.to_string()),
+        Box::new(|item: &PossibleTarget| coordinates::format_ra(item.begin.ra)),
+        Box::new(|item: &PossibleTarget| coordinates::format_dec(item.begin.dec)),
+        Box::new(|item: &PossibleTarget| format!("{:.1}", item.begin.altitude)),
+        Box::new(|item: &PossibleTarget| format!("{:.1}", item.max.altitude)),
+        Box::new(move |item: &PossibleTarget| format_time(item.max.time, "%H:%M", &settings)),
+        Box::new(|item: &PossibleTarget| format!("{:.0}", item.azimuth)),
+        Box::new(|item: &PossibleTarget| item.blocked.clone().unwrap_or_default()),
     ];
-    table
-        .set_width(80)
-        .set_header(vec!["Designation", "Magnitude", "RA", "DEC", "Altitude"]);
+    table.set_width(80).set_header(vec![
+        "Designation".to_string(),
+        "Magnitude".to_string(),
+        "RA".to_string(),
+        "DEC".to_string(),
+        "Altitude".to_string(),
+        "Max Alt".to_string(),
+        max_time_header,
+        "Azimuth".to_string(),
+        "Blocked".to_string(),
+    ]);
     for item in data {
-        let row: Vec<String> = converters
+        let row: Vec<String> = converters.iter().map(|converter| converter(item)).collect();
+        table.add_row(row);
+    }
+    println!("{table}");
+}
+
+/// Characters used to draw altitude curves, from the horizon to the zenith
+const CURVE_LEVELS: [char; 8] = ['▁', '▂', '▃', '▄', '▅', '▆', '▇', '█'];
+
+/// Prints the altitude curve of each target over the night containing `start`
+///
+/// The night is astronomical darkness, or twelve hours from `start` if the sky never gets dark.
+/// The curve is drawn only above the horizon profile of the observatory.
+///
+/// * `data`: targets to draw
+/// * `start`: observing time
+fn create_altitude_curves_table(data: &[PossibleTarget], start: DateTime<Utc>) {
+    let _ = disable_raw_mode();
+    let settings = Settings::new().unwrap();
+    let observer = Observer::from_settings(&settings);
+    let horizon = settings.get_horizon();
+    let nights =
+        sun_moon_times::prepare_darkness(start.date_naive() - Duration::days(1), 2).unwrap();
+    let (begin, end) = nights
+        .iter()
+        .filter_map(|night| night.begin.zip(night.end))
+        .find(|(_, end)| *end > start)
+        .unwrap_or((start, start + Duration::hours(12)));
+    let step = Duration::minutes(30);
+    let mut table = Table::new();
+    table.set_width(80).set_header(vec![
+        "Designation".to_string(),
+        format!(
+            "Altitude {} - {} ({})",
+            format_time(begin, "%H:%M", &settings),
+            format_time(end, "%H:%M", &settings),
+            zone_name(&settings)
+        ),
+        "Max Alt".to_string(),
+        "At".to_string(),
+        "Above horizon".to_string(),
+    ]);
+    for item in data {
+        let (ra, dec) = (item.begin.ra, item.begin.dec);
+        let curve = coordinates::altitude_curve(ra, dec, begin, end, step, &observer);
+        let line: String = curve
             .iter()
-            .map(|converter| converter(&item))
+            .map(|(time, altitude)| {
+                let azimuth = coordinates::apparent_horizontal(ra, dec, *time, &observer).azimuth;
+                match *altitude {
+                    altitude if !horizon.is_visible(altitude, azimuth) => ' ',
+                    altitude => CURVE_LEVELS[((altitude / 90.0 * 8.0) as usize).min(7)],
+                }
+            })
             .collect();
-        table.add_row(row);
+        let windows: Vec<String> =
+            horizon::visibility_windows(ra, dec, begin, end, step, &observer, &horizon)
+                .iter()
+                .map(|(rise, set)| {
+                    format!(
+                        "{}-{}",
+                        format_time(*rise, "%H:%M", &settings),
+                        format_time(*set, "%H:%M", &settings)
+                    )
+                })
+                .collect();
+        let Some((time, altitude)) = curve
+            .iter()
+            .max_by(|(_, first), (_, second)| first.total_cmp(second))
+        else {
+            continue;
+        };
+        table.add_row(vec![
+            item.designation.to_string(),
+            line,
+            format!("{:.1}", altitude),
+            format_time(*time, "%H:%M", &settings),
+            if windows.is_empty() {
+                "Never".to_string()
+            } else {
+                windows.join(", ")
+            },
+        ]);
     }
     println!("{table}");
 }
@@ -1,18 +1,31 @@
+use crate::horizon::{HorizonPoint, HorizonProfile};
+use chrono_tz::Tz;
 use config::{Config, ConfigError, File};
 use rand::Rng;
 use std::fs;
 use std::io::prelude::*;
-use std::path::PathBuf;
+use std::sync::Mutex;
 
-//TODO: Add minimum altitude on different directions
+/// Observatory profile selected for this invocation, overriding the active one
+///
+/// It follows renames of the profile and is cleared when another profile is made active.
+static OBSERVATORY_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
 
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug, Clone)]
 /// General option structure
 ///
 /// * `lang`: language
+/// * `local_time`: show times in the observatory timezone instead of UTC
+/// * `active_observatory`: name of the active observatory profile
 pub struct General {
     /// Language
     pub lang: String,
+    /// Show times in the observatory timezone instead of UTC
+    #[serde(default)]
+    pub local_time: bool,
+    /// Name of the active observatory profile, the observatory table if empty
+    #[serde(default)]
+    pub active_observatory: String,
 }
 
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug, Clone)]
@@ -29,6 +42,8 @@ pub struct General {
 /// * `south_altitude`: south altitude to limit only visible objects
 /// * `east_altitude`: east altitude to limit only visible objects
 /// * `west_altitude`: west altitude to limit only visible objects
+/// * `timezone`: IANA timezone name
+/// * `horizon`: horizon profile, replacing the four directions when not empty
 pub struct Observatory {
     /// Place name
     pub place: String,
@@ -52,40 +67,132 @@ pub struct Observatory {
     pub east_altitude: i32,
     /// West altitude to limit only visible objects
     pub west_altitude: i32,
+    /// IANA timezone name, suggested from longitude if empty
+    #[serde(default)]
+    pub timezone: String,
+    /// Horizon profile points, replacing the four directions when not empty
+    #[serde(default)]
+    pub horizon: Vec<HorizonPoint>,
 }
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug, Clone, PartialEq)]
+/// Equipment setup structure, a telescope with its camera
+///
+/// * `name`: setup name
+/// * `aperture`: telescope aperture in millimeters
+/// * `focal_length`: effective focal length in millimeters, reducers included
+/// * `pixel_size`: camera pixel size in micrometers
+/// * `sensor_width`: sensor width in pixels
+/// * `sensor_height`: sensor height in pixels
+/// * `design`: optical design for MPC reports, i.e. "reflector" or "refractor"
+/// * `detector`: detector for MPC reports, i.e. "CCD" or "CMOS"
+pub struct Equipment {
+    /// Setup name
+    pub name: String,
+    /// Telescope aperture in millimeters
+    pub aperture: f32,
+    /// Effective focal length in millimeters, reducers included
+    pub focal_length: f32,
+    /// Camera pixel size in micrometers
+    pub pixel_size: f32,
+    /// Sensor width in pixels
+    pub sensor_width: u32,
+    /// Sensor height in pixels
+    pub sensor_height: u32,
+    /// Optical design for MPC reports, i.e. "reflector" or "refractor"
+    #[serde(default)]
+    pub design: String,
+    /// Detector for MPC reports, i.e. "CCD" or "CMOS"
+    #[serde(default)]
+    pub detector: String,
+}
+
+impl Equipment {
+    /// Returns the pixel scale in arcseconds per pixel
+    pub fn pixel_scale(&self) -> f64 {
+        206.265 * self.pixel_size as f64 / self.focal_length as f64
+    }
+
+    /// Returns the field of view along sensor width and height in arcminutes
+    pub fn field_of_view(&self) -> (f64, f64) {
+        let scale = self.pixel_scale() / 60.0;
+        (
+            self.sensor_width as f64 * scale,
+            self.sensor_height as f64 * scale,
+        )
+    }
+
+    /// Returns the estimated limiting magnitude
+    ///
+    /// Rough estimate for an unfiltered 60 s exposure under a dark sky at signal to noise 5,
+    /// scaling with the collecting area of the aperture.
+    pub fn limiting_magnitude(&self) -> f64 {
+        8.0 + 5.0 * (self.aperture as f64).log10()
+    }
+
+    /// Checks that all values are positive
+    fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
+        if self.name.trim().is_empty() {
+            return Err("Equipment name must not be empty".into());
+        }
+        if !(self.aperture > 0.0 && self.focal_length > 0.0 && self.pixel_size > 0.0) {
+            return Err("Aperture, focal length and pixel size must be positive".into());
+        }
+        if self.sensor_width == 0 || self.sensor_height == 0 {
+            return Err("Sensor dimensions must be at least 1 pixel".into());
+        }
+        Ok(())
+    }
+}
+
+#[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug, Clone, Default)]
+/// Equipment option structure
+///
+/// * `active`: name of the active setup, the first one if empty or unknown
+/// * `setups`: named equipment setups
+pub struct EquipmentSettings {
+    /// Name of the active setup, the first one if empty or unknown
+    #[serde(default)]
+    pub active: String,
+    /// Named equipment setups
+    #[serde(default)]
+    pub setups: Vec<Equipment>,
+}
+
 #[derive(serde_derive::Deserialize, serde_derive::Serialize, Debug, Clone)]
 /// Setting structure
 ///
 /// * `general`: General settings structure
-/// * `observatory`: Observatory settings structure
+/// * `observatory`: Observatory settings structure, the selected profile once loaded
+/// * `observatories`: named observatory profiles, by observatory name
+/// * `equipment`: Equipment settings structure
 pub struct Settings {
     /// General settings structure
     pub general: General,
-    /// Observatory settings structure
+    /// Observatory settings structure, the selected profile once loaded
     pub observatory: Observatory,
+    /// Named observatory profiles, by observatory name
+    #[serde(default)]
+    pub observatories: Vec<Observatory>,
+    /// Equipment settings structure
+    #[serde(default)]
+    pub equipment: EquipmentSettings,
+    /// Index of the loaded profile in `observatories`, updated by observatory changes
+    #[serde(skip)]
+    profile: Option<usize>,
 }
 
-/// Finds if config file exists or create it
-fn file_exists_or_create() -> Result<(), Box<dyn std::error::Error>> {
-    let config_dir: PathBuf = dirs::config_local_dir().ok_or("Failed to get config local dir")?;
-    let asteroid_app_path: PathBuf = config_dir.join("asteroid_tui");
-
-    // Ensure the directory exists
-    if asteroid_app_path.exists() {
-        fs::create_dir_all(&asteroid_app_path)?;
-    }
-
-    // Create or update the config file
-    let config_file_path = asteroid_app_path.join("config.toml");
-    if config_file_path.exists() {
-        let default_settings: Settings = default_settings();
-        let default: String = toml::to_string(&default_settings)?;
-        fs::write(config_file_path.clone(), default)?;
-    }
-
-    // Open the file for external use
-
-    Ok(())
+/// Suggests a timezone from longitude, i.e. the Etc/GMT zone with the nearest mean solar time
+///
+/// * `longitude`: longitude in degrees, east positive
+pub fn suggest_timezone(longitude: f32) -> Tz {
+    let hours = (longitude / 15.0).round().clamp(-12.0, 14.0) as i32;
+    let name = match hours {
+        0 => "Etc/GMT".to_string(),
+        // Etc zones have the sign inverted, i.e. Etc/GMT-1 is UTC+1
+        _ => format!("Etc/GMT{:+}", -hours),
+    };
+    name.parse::<Tz>().unwrap_or(Tz::UTC)
 }
 
 /// Creates default settings for file creation
@@ -93,11 +200,14 @@ fn default_settings() -> Settings {
     let mut rng = rand::rng();
     let default_general: General = General {
         lang: "en".to_string(),
+        local_time: false,
+        active_observatory: String::new(),
     };
+    let longitude = rng.random_range(0.1..179.9) as f32;
     let default_observatory: Observatory = Observatory {
         place: "default".to_string(),
         latitude: rng.random_range(0.1..89.9) as f32,
-        longitude: rng.random_range(0.1..179.9) as f32,
+        longitude,
         altitude: rng.random_range(0.1..100.0) as f32,
         observatory_name: "default".to_string(),
         observer_name: "default".to_string(),
@@ -106,10 +216,45 @@ fn default_settings() -> Settings {
         east_altitude: 1,
         south_altitude: 1,
         west_altitude: 1,
+        timezone: suggest_timezone(longitude).name().to_string(),
+        horizon: Vec::new(),
     };
     Settings {
         general: default_general,
         observatory: default_observatory,
+        observatories: Vec::new(),
+        equipment: EquipmentSettings::default(),
+        profile: None,
+    }
+}
+
+/// Selects an observatory profile for this invocation, i.e. from the command line
+///
+/// Must be called before the first `Settings::new`, returns false if already set.
+///
+/// * `name`: observatory name of the profile
+pub fn set_observatory_override(name: &str) -> bool {
+    let mut observatory = OBSERVATORY_OVERRIDE.lock().unwrap();
+    if observatory.is_some() {
+        return false;
+    }
+    *observatory = Some(name.to_string());
+    true
+}
+
+/// Returns the observatory profile selected for this invocation, if any
+fn observatory_override() -> Option<String> {
+    OBSERVATORY_OVERRIDE.lock().unwrap().clone()
+}
+
+/// Renames a selected observatory profile
+///
+/// * `selected`: observatory name of the selected profile, if any
+/// * `old`: previous observatory name of the renamed profile
+/// * `new`: new observatory name of the renamed profile
+fn follow_rename(selected: &mut Option<String>, old: &str, new: &str) {
+    if selected.as_deref() == Some(old) {
+        *selected = Some(new.to_string());
     }
 }
 
@@ -274,7 +419,30 @@ impl Settings {
                     .unwrap(),
             ))
             .build()?;
-        s.try_deserialize()
+        let mut settings: Settings = s.try_deserialize()?;
+        settings.select_observatory(observatory_override().as_deref())?;
+        Ok(settings)
+    }
+
+    /// Loads the observatory profile in `observatory`
+    ///
+    /// Without override the active profile is used, the observatory table is kept if no
+    /// profile is active.
+    ///
+    /// * `name`: observatory name of the profile overriding the active one
+    fn select_observatory(&mut self, name: Option<&str>) -> Result<(), ConfigError> {
+        let name = name.unwrap_or(&self.general.active_observatory);
+        if name.is_empty() {
+            return Ok(());
+        }
+        let index = self
+            .observatories
+            .iter()
+            .position(|observatory| observatory.observatory_name == name)
+            .ok_or_else(|| ConfigError::Message(format!("Unknown observatory \"{}\"", name)))?;
+        self.observatory = self.observatories[index].clone();
+        self.profile = Some(index);
+        Ok(())
     }
 
     /// Get lang value from settings
@@ -297,7 +465,29 @@ impl Settings {
     pub fn set_settings(&mut self, settings: Settings) -> Result<(), Box<dyn std::error::Error>> {
         // Update all fields directly
         self.observatory = settings.observatory;
+        self.write()
+    }
 
+    /// Sets time display in config.toml
+    ///
+    /// * `local_time`: true to show times in the observatory timezone, false for UTC
+    pub fn set_local_time(&mut self, local_time: bool) -> Result<(), Box<dyn std::error::Error>> {
+        self.general.local_time = local_time;
+        self.write()
+    }
+
+    /// Sets observatory timezone in config.toml
+    ///
+    /// * `timezone`: IANA timezone name
+    pub fn set_timezone(&mut self, timezone: String) -> Result<(), Box<dyn std::error::Error>> {
+        timezone.parse::<Tz>()?;
+        self.observatory.timezone = timezone;
+        self.write()
+    }
+
+    /// Writes all settings to config.toml, saving observatory changes to the loaded profile
+    fn write(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let renamed = self.sync_profile()?;
         // Write to config file using serde directly
         let config_path = dirs::config_local_dir()
             .ok_or("Failed to get config dir")?
@@ -307,9 +497,43 @@ impl Settings {
         let toml = toml::to_string(&self)?;
         std::fs::write(config_path, toml)?;
 
+        if let Some(old) = renamed {
+            follow_rename(
+                &mut OBSERVATORY_OVERRIDE.lock().unwrap(),
+                &old,
+                &self.observatory.observatory_name,
+            );
+        }
         Ok(())
     }
 
+    /// Copies the loaded observatory to its profile, returning the previous name if renamed
+    ///
+    /// Fails if another profile already has the observatory name.
+    fn sync_profile(&mut self) -> Result<Option<String>, Box<dyn std::error::Error>> {
+        let Some(index) = self.profile else {
+            return Ok(None);
+        };
+        let name = &self.observatory.observatory_name;
+        if self
+            .observatories
+            .iter()
+            .enumerate()
+            .any(|(other, observatory)| other != index && observatory.observatory_name == *name)
+        {
+            return Err(format!("Observatory \"{}\" already exists", name).into());
+        }
+        let old = std::mem::replace(&mut self.observatories[index], self.observatory.clone())
+            .observatory_name;
+        if old == *name {
+            return Ok(None);
+        }
+        if self.general.active_observatory == old {
+            self.general.active_observatory = name.clone();
+        }
+        Ok(Some(old))
+    }
+
     /// Get place value from settings
     pub fn get_place(&self) -> &String {
         &self.observatory.place
@@ -365,6 +589,178 @@ impl Settings {
         &self.observatory.west_altitude
     }
 
+    /// Get horizon profile, built from the four directions if no profile is set
+    pub fn get_horizon(&self) -> HorizonProfile {
+        if self.observatory.horizon.is_empty() {
+            HorizonProfile::from_cardinal(
+                self.observatory.north_altitude as f64,
+                self.observatory.east_altitude as f64,
+                self.observatory.south_altitude as f64,
+                self.observatory.west_altitude as f64,
+            )
+        } else {
+            HorizonProfile::new(self.observatory.horizon.clone())
+        }
+    }
+
+    /// Sets horizon profile in config.toml
+    ///
+    /// * `horizon`: horizon profile, empty to use the four directions
+    pub fn set_horizon(
+        &mut self,
+        horizon: &HorizonProfile,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.observatory.horizon = horizon.points().to_vec();
+        self.write()
+    }
+
+    /// Get time display value from settings, true for the observatory timezone
+    pub fn get_local_time(&self) -> &bool {
+        &self.general.local_time
+    }
+
+    /// Get observatory timezone, suggested from longitude if not set or invalid
+    pub fn get_timezone(&self) -> Tz {
+        self.observatory
+            .timezone
+            .parse::<Tz>()
+            .unwrap_or_else(|_| suggest_timezone(self.observatory.longitude))
+    }
+
+    /// Get active equipment setup, the first one if no valid setup is selected
+    pub fn get_equipment(&self) -> Option<&Equipment> {
+        self.equipment
+            .setups
+            .iter()
+            .find(|setup| setup.name == self.equipment.active)
+            .or_else(|| self.equipment.setups.first())
+    }
+
+    /// Get all equipment setups
+    pub fn get_equipment_list(&self) -> &Vec<Equipment> {
+        &self.equipment.setups
+    }
+
+    /// Sets an equipment setup in config.toml, replacing the one with the same name
+    ///
+    /// The setup becomes the active one.
+    ///
+    /// * `equipment`: equipment setup
+    pub fn set_equipment(
+        &mut self,
+        equipment: Equipment,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        equipment.validate()?;
+        self.equipment.active = equipment.name.clone();
+        match self
+            .equipment
+            .setups
+            .iter_mut()
+            .find(|setup| setup.name == equipment.name)
+        {
+            Some(setup) => *setup = equipment,
+            None => self.equipment.setups.push(equipment),
+        }
+        self.write()
+    }
+
+    /// Sets active equipment setup in config.toml
+    ///
+    /// * `name`: name of an existing setup
+    pub fn set_active_equipment(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if !self.equipment.setups.iter().any(|setup| setup.name == name) {
+            return Err(format!("Unknown equipment \"{}\"", name).into());
+        }
+        self.equipment.active = name.to_string();
+        self.write()
+    }
+
+    /// Removes an equipment setup from config.toml
+    ///
+    /// * `name`: name of the setup
+    pub fn remove_equipment(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.equipment.setups.retain(|setup| setup.name != name);
+        if self.equipment.active == name {
+            self.equipment.active.clear();
+        }
+        self.write()
+    }
+
+    /// Get observatory profiles
+    pub fn get_observatories(&self) -> &Vec<Observatory> {
+        &self.observatories
+    }
+
+    /// Saves the loaded observatory as a new profile in config.toml, making it active
+    ///
+    /// The loaded observatory is saved as a profile too when there are no profiles yet.
+    ///
+    /// * `name`: observatory name of the new profile
+    pub fn add_observatory(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name.trim().is_empty() {
+            return Err("Observatory name must not be empty".into());
+        }
+        if name == self.observatory.observatory_name
+            || self
+                .observatories
+                .iter()
+                .any(|observatory| observatory.observatory_name == name)
+        {
+            return Err(format!("Observatory \"{}\" already exists", name).into());
+        }
+        // Restored if the config file can not be written
+        let previous = (
+            self.observatory.clone(),
+            self.observatories.clone(),
+            self.profile,
+            self.general.active_observatory.clone(),
+        );
+        if self.observatories.is_empty() {
+            self.observatories.push(self.observatory.clone());
+        }
+        self.observatory.observatory_name = name.to_string();
+        self.observatories.push(self.observatory.clone());
+        self.profile = Some(self.observatories.len() - 1);
+        self.general.active_observatory = name.to_string();
+        if let Err(error) = self.write() {
+            (
+                self.observatory,
+                self.observatories,
+                self.profile,
+                self.general.active_observatory,
+            ) = previous;
+            return Err(error);
+        }
+        Ok(())
+    }
+
+    /// Sets active observatory profile in config.toml
+    ///
+    /// * `name`: observatory name of an existing profile
+    pub fn set_active_observatory(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        self.select_observatory(Some(name))?;
+        self.general.active_observatory = name.to_string();
+        self.write()?;
+        // The chosen profile replaces the one selected for this invocation
+        *OBSERVATORY_OVERRIDE.lock().unwrap() = None;
+        Ok(())
+    }
+
+    /// Removes an observatory profile from config.toml, the active one can not be removed
+    ///
+    /// * `name`: observatory name of the profile
+    pub fn remove_observatory(&mut self, name: &str) -> Result<(), Box<dyn std::error::Error>> {
+        if name == self.general.active_observatory || name == self.observatory.observatory_name {
+            return Err("The active observatory can not be removed".into());
+        }
+        self.observatories
+            .retain(|observatory| observatory.observatory_name != name);
+        self.profile = self.observatories.iter().position(|observatory| {
+            observatory.observatory_name == self.observatory.observatory_name
+        });
+        self.write()
+    }
+
     /// Gets all settings in one
     pub fn get_all_settings(&self) -> Settings {
         self.clone()
@@ -396,5 +792,147 @@ mod test {
         assert!(s.get_south_altitude().is_positive());
         assert!(s.get_east_altitude().is_positive());
         assert!(s.get_west_altitude().is_positive());
+        assert!(!s.get_timezone().name().is_empty());
+    }
+
+    #[test]
+    fn test_suggest_timezone() {
+        assert_eq!(suggest_timezone(9.77).name(), "Etc/GMT-1");
+        assert_eq!(suggest_timezone(-71.1).name(), "Etc/GMT+5");
+        assert_eq!(suggest_timezone(3.0).name(), "Etc/GMT");
+    }
+
+    #[test]
+    fn test_get_horizon() {
+        let mut s = default_settings();
+        s.observatory.north_altitude = 15;
+        assert_eq!(s.get_horizon().altitude_at(0.0), 15.0);
+        s.observatory.horizon = vec![
+            HorizonPoint {
+                azimuth: 0.0,
+                altitude: 10.0,
+            },
+            HorizonPoint {
+                azimuth: 180.0,
+                altitude: 30.0,
+            },
+        ];
+        assert_eq!(s.get_horizon().altitude_at(90.0), 20.0);
+        let saved: Settings = toml::from_str(&toml::to_string(&s).unwrap()).unwrap();
+        assert_eq!(saved.observatory.horizon, s.observatory.horizon);
+    }
+
+    #[test]
+    fn test_select_observatory() {
+        let mut s = default_settings();
+        s.select_observatory(None).unwrap();
+        assert_eq!(s.observatory.observatory_name, "default");
+        let club = Observatory {
+            observatory_name: "Club".to_string(),
+            latitude: 44.09727,
+            ..s.observatory.clone()
+        };
+        let portable = Observatory {
+            observatory_name: "Portable".to_string(),
+            latitude: -30.0,
+            ..s.observatory.clone()
+        };
+        s.observatories = vec![club, portable];
+        s.general.active_observatory = "Club".to_string();
+        s.select_observatory(None).unwrap();
+        assert_eq!(*s.get_latitude(), 44.09727);
+        s.select_observatory(Some("Portable")).unwrap();
+        assert_eq!(*s.get_latitude(), -30.0);
+        assert_eq!(s.profile, Some(1));
+        assert!(s.select_observatory(Some("Unknown")).is_err());
+        let saved: Settings = toml::from_str(&toml::to_string(&s).unwrap()).unwrap();
+        assert_eq!(saved.get_observatories().len(), 2);
+        assert_eq!(saved.general.active_observatory, "Club");
+    }
+
+    #[test]
+    fn test_rename_selected_observatory() {
+        let mut s = default_settings();
+        let club = Observatory {
+            observatory_name: "Club".to_string(),
+            latitude: 44.09727,
+            ..s.observatory.clone()
+        };
+        let portable = Observatory {
+            observatory_name: "Portable".to_string(),
+            ..s.observatory.clone()
+        };
+        s.observatories = vec![portable, club];
+        s.general.active_observatory = "Portable".to_string();
+        let mut selected = Some("Club".to_string());
+        s.select_observatory(selected.as_deref()).unwrap();
+
+        s.observatory.observatory_name = "Portable".to_string();
+        assert!(s.sync_profile().is_err());
+        s.observatory.observatory_name = "Club2".to_string();
+        let old = s.sync_profile().unwrap().unwrap();
+        assert_eq!(old, "Club");
+        follow_rename(&mut selected, &old, "Club2");
+        assert_eq!(s.sync_profile().unwrap(), None);
+
+        let mut reloaded: Settings = toml::from_str(&toml::to_string(&s).unwrap()).unwrap();
+        reloaded.select_observatory(selected.as_deref()).unwrap();
+        assert_eq!(reloaded.get_observatory_name(), "Club2");
+        assert_eq!(*reloaded.get_latitude(), 44.09727);
+        assert_eq!(reloaded.general.active_observatory, "Portable");
+    }
+
+    #[test]
+    fn test_equipment() {
+        let reflector = Equipment {
+            name: "Reflector".to_string(),
+            aperture: 200.0,
+            focal_length: 800.0,
+            pixel_size: 3.76,
+            sensor_width: 6248,
+            sensor_height: 4176,
+            design: "reflector".to_string(),
+            detector: "CMOS".to_string(),
+        };
+        assert!((reflector.pixel_scale() - 0.9694).abs() < 1e-3);
+        let (width, height) = reflector.field_of_view();
+        assert!((width - 100.95).abs() < 0.1);
+        assert!((height - 67.47).abs() < 0.1);
+        assert!((reflector.limiting_magnitude() - 19.5).abs() < 0.1);
+        assert!(Equipment {
+            aperture: 0.0,
+            ..reflector.clone()
+        }
+        .validate()
+        .is_err());
+
+        let mut s = default_settings();
+        assert!(s.get_equipment().is_none());
+        let refractor = Equipment {
+            name: "Refractor".to_string(),
+            aperture: 80.0,
+            ..reflector.clone()
+        };
+        s.equipment.setups = vec![reflector, refractor];
+        assert_eq!(s.get_equipment().unwrap().name, "Reflector");
+        s.equipment.active = "Refractor".to_string();
+        assert_eq!(s.get_equipment().unwrap().aperture, 80.0);
+        let saved: Settings = toml::from_str(&toml::to_string(&s).unwrap()).unwrap();
+        assert_eq!(saved.equipment.setups, s.equipment.setups);
+        assert_eq!(saved.get_equipment().unwrap().name, "Refractor");
+        let saved = toml::to_string(&s).unwrap();
+        let old: Settings = toml::from_str(saved.split("[equipment]").next().unwrap()).unwrap();
+        assert!(old.get_equipment_list().is_empty());
+    }
+
+    #[test]
+    fn test_add_existing_observatory() {
+        let mut s = default_settings();
+        let name = s.observatory.observatory_name.clone();
+        assert!(s.add_observatory(&name).is_err());
+        assert!(s.add_observatory(" ").is_err());
+        assert!(s.observatories.is_empty());
+        assert_eq!(s.profile, None);
+        assert_eq!(s.general.active_observatory, "");
     }
 }
@@ -1,4 +1,7 @@
-use crate::{settings::General, settings::Observatory, settings::Settings, tui};
+use crate::{
+    horizon::HorizonProfile, settings::suggest_timezone, settings::Equipment, settings::General,
+    settings::Observatory, settings::Settings, tui,
+};
 use promkit::{
     crossterm::{
         execute,
@@ -7,6 +10,7 @@ use promkit::{
     },
     preset::form::Form,
     preset::listbox::Listbox,
+    preset::query_selector::QuerySelector,
     preset::readline::Readline,
     style::StyleBuilder,
     text_editor,
@@ -14,7 +18,7 @@ use promkit::{
 use std::convert::TryFrom;
 use std::num::ParseIntError;
 
-const OPTIONS_GENERAL_SETTINGS: [&str; 3] = ["1", "9", "0"];
+const OPTIONS_GENERAL_SETTINGS: [&str; 4] = ["1", "2", "9", "0"];
 
 // Funzione di validazione
 fn validate_settings_menu_option(option: &str) -> bool {
@@ -37,6 +41,7 @@ pub fn general_settings_menu() -> Result<(), Box<dyn std::error::Error>> {
     println!(
         "\n\n\nGeneral Settings
 1. Language
+2. Time display
 9. Back
 0. Quit"
     );
@@ -50,6 +55,7 @@ pub fn general_settings_menu() -> Result<(), Box<dyn std::error::Error>> {
     let result = p.run()?;
     match result.as_str() {
         "1" => language_menu()?,
+        "2" => time_display_menu()?,
         "9" => tui::settings_menu()?,
         _ => (),
     }
@@ -71,6 +77,339 @@ fn language_menu() -> Result<(), Box<dyn std::error::Error>> {
     Ok(())
 }
 
+/// Creates and prints time display menu, asking for option
+fn time_display_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    println!("\n\n\nTime Display Settings");
+    let mut p = Listbox::new(vec!["UTC", "Local"])
+        .title("Show times in:")
+        .listbox_lines(5)
+        .prompt()?;
+    let mut set: Settings = Settings::new().unwrap();
+    set.set_local_time(p.run()? == "Local")
+        .unwrap_or_else(|error| println!("Error: {}", error));
+    Ok(())
+}
+
+/// Creates and prints timezone menu, asking for the observatory timezone
+///
+/// The timezone suggested from longitude is the first option, the list can be filtered typing
+pub fn timezone_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    let mut set: Settings = Settings::new().unwrap();
+    println!(
+        "\n\n\nTimezone Settings (actual: {})",
+        set.get_timezone().name()
+    );
+    let suggested = suggest_timezone(*set.get_longitude());
+    let mut timezones: Vec<&str> = vec![suggested.name()];
+    timezones.extend(
+        chrono_tz::TZ_VARIANTS
+            .iter()
+            .map(|timezone| timezone.name()),
+    );
+    let mut p = QuerySelector::new(timezones, |text, items| {
+        items
+            .iter()
+            .filter(|item| item.to_lowercase().contains(&text.to_lowercase()))
+            .cloned()
+            .collect()
+    })
+    .title("Select timezone (type to filter):")
+    .listbox_lines(10)
+    .prompt()?;
+    set.set_timezone(p.run()?)
+        .unwrap_or_else(|error| println!("Error: {}", error));
+    Ok(())
+}
+
+/// Creates and prints horizon profile menu, asking for the file to import
+///
+/// The file can be a CSV, a Stellarium horizon list or a N.I.N.A. `.hrz` file, an empty path
+/// goes back to the four directions of the observatory settings
+pub fn horizon_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    let mut set: Settings = Settings::new().unwrap();
+    if set.observatory.horizon.is_empty() {
+        println!(
+            "\n\n\nHorizon Profile (actual: N {}°, E {}°, S {}°, W {}°)",
+            set.get_north_altitude(),
+            set.get_east_altitude(),
+            set.get_south_altitude(),
+            set.get_west_altitude()
+        );
+    } else {
+        println!(
+            "\n\n\nHorizon Profile (actual: {} points)",
+            set.observatory.horizon.len()
+        );
+    }
+    let mut p = Readline::default()
+        .title("Horizon file (CSV, Stellarium or N.I.N.A., empty for four directions):")
+        .prompt()?;
+    let path = p.run()?;
+    let profile = if path.trim().is_empty() {
+        Ok(HorizonProfile::default())
+    } else {
+        HorizonProfile::from_file(std::path::Path::new(path.trim()))
+    };
+    match profile {
+        Ok(profile) => set
+            .set_horizon(&profile)
+            .unwrap_or_else(|error| println!("Error: {}", error)),
+        Err(error) => println!("Error: {}", error),
+    }
+    Ok(())
+}
+
+/// Returns a form field with the observatory settings style
+///
+/// * `prefix`: field label, with the actual value
+fn form_field(prefix: String) -> text_editor::State {
+    text_editor::State {
+        texteditor: Default::default(),
+        history: Default::default(),
+        prefix,
+        mask: Default::default(),
+        prefix_style: StyleBuilder::new().fgc(Color::DarkRed).build(),
+        active_char_style: StyleBuilder::new().fgc(Color::Red).build(),
+        inactive_char_style: StyleBuilder::new().build(),
+        edit_mode: Default::default(),
+        word_break_chars: Default::default(),
+        lines: Default::default(),
+    }
+}
+
+/// Parses a form value, keeping `actual` if the value is empty
+///
+/// * `value`: form value
+/// * `actual`: actual value
+/// * `name`: name of the field, for errors
+fn parse_form_value<T: std::str::FromStr>(
+    value: &str,
+    actual: T,
+    name: &str,
+) -> Result<T, Box<dyn std::error::Error>> {
+    if value.trim().is_empty() {
+        Ok(actual)
+    } else {
+        value
+            .trim()
+            .parse::<T>()
+            .map_err(|_| format!("Invalid {} \"{}\"", name, value.trim()).into())
+    }
+}
+
+/// Creates and prints equipment menu, listing the setups with their derived values
+///
+/// Setups can be added, edited, selected or removed, the active one sets the limiting
+/// magnitude of the target list and the field of view of NEOCP pointings
+pub fn equipment_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    let mut set: Settings = Settings::new().unwrap();
+    println!("\n\n\nEquipment Settings\n");
+    let active = set.get_equipment().map(|setup| setup.name.clone());
+    for setup in set.get_equipment_list() {
+        let (width, height) = setup.field_of_view();
+        println!(
+            "{} {}: {} mm f/{:.1}, {:.2}\"/px, FOV {:.1}' x {:.1}', limiting magnitude {:.1}",
+            if active.as_ref() == Some(&setup.name) {
+                "*"
+            } else {
+                " "
+            },
+            setup.name,
+            setup.aperture,
+            setup.focal_length / setup.aperture,
+            setup.pixel_scale(),
+            width,
+            height,
+            setup.limiting_magnitude()
+        );
+    }
+    if set.get_equipment_list().is_empty() {
+        println!("No equipment set");
+    }
+    let mut p = Listbox::new(vec![
+        "Add or edit setup",
+        "Select active setup",
+        "Remove setup",
+        "Back",
+    ])
+    .title("\nSelect an option:")
+    .listbox_lines(5)
+    .prompt()?;
+    let result = match p.run()?.as_str() {
+        "Add or edit setup" => edit_equipment(&mut set),
+        "Select active setup" | "Remove setup" if set.get_equipment_list().is_empty() => {
+            Err("No equipment set".into())
+        }
+        "Select active setup" => {
+            let name = select_equipment("Select active setup:", &set)?;
+            set.set_active_equipment(&name)
+        }
+        "Remove setup" => {
+            let name = select_equipment("Select setup to remove:", &set)?;
+            set.remove_equipment(&name)
+        }
+        _ => Ok(()),
+    };
+    result.unwrap_or_else(|error| println!("Error: {}", error));
+    Ok(())
+}
+
+/// Asks for one of the equipment setups, returning its name
+///
+/// * `title`: prompt title
+/// * `set`: settings with the setups
+fn select_equipment(title: &str, set: &Settings) -> Result<String, Box<dyn std::error::Error>> {
+    let names: Vec<String> = set
+        .get_equipment_list()
+        .iter()
+        .map(|setup| setup.name.clone())
+        .collect();
+    Ok(Listbox::new(names)
+        .title(title)
+        .listbox_lines(5)
+        .prompt()?
+        .run()?)
+}
+
+/// Asks for the values of an equipment setup, empty values keep the actual ones
+///
+/// * `set`: settings to be updated
+fn edit_equipment(set: &mut Settings) -> Result<(), Box<dyn std::error::Error>> {
+    let name = Readline::default()
+        .title("Setup name (existing to edit):")
+        .prompt()?
+        .run()?;
+    let actual = set
+        .get_equipment_list()
+        .iter()
+        .find(|setup| setup.name == name.trim())
+        .cloned()
+        .unwrap_or(Equipment {
+            name: name.trim().to_string(),
+            aperture: 0.0,
+            focal_length: 0.0,
+            pixel_size: 0.0,
+            sensor_width: 0,
+            sensor_height: 0,
+            design: String::new(),
+            detector: String::new(),
+        });
+    let mut p = Form::new([
+        form_field(format!("Aperture in mm ({}): ", actual.aperture)),
+        form_field(format!("Focal length in mm ({}): ", actual.focal_length)),
+        form_field(format!("Pixel size in µm ({}): ", actual.pixel_size)),
+        form_field(format!("Sensor width in px ({}): ", actual.sensor_width)),
+        form_field(format!("Sensor height in px ({}): ", actual.sensor_height)),
+        form_field(format!(
+            "Optical design, i.e. reflector or refractor ({}): ",
+            actual.design
+        )),
+        form_field(format!(
+            "Detector, i.e. CCD or CMOS ({}): ",
+            actual.detector
+        )),
+    ])
+    .prompt()?;
+    let response = p.run()?;
+    let equipment = Equipment {
+        aperture: parse_form_value(&response[0], actual.aperture, "aperture")?,
+        focal_length: parse_form_value(&response[1], actual.focal_length, "focal length")?,
+        pixel_size: parse_form_value(&response[2], actual.pixel_size, "pixel size")?,
+        sensor_width: parse_form_value(&response[3], actual.sensor_width, "sensor width")?,
+        sensor_height: parse_form_value(&response[4], actual.sensor_height, "sensor height")?,
+        design: parse_form_value(&response[5], actual.design.clone(), "optical design")?,
+        detector: parse_form_value(&response[6], actual.detector.clone(), "detector")?,
+        ..actual
+    };
+    set.set_equipment(equipment)
+}
+
+/// Creates and prints observatory profiles menu, asking for the profile to select
+///
+/// A new profile is a copy of the active observatory, to be edited in the observatory menu
+pub fn observatory_profiles_menu() -> Result<(), Box<dyn std::error::Error>> {
+    let _ = disable_raw_mode();
+    execute!(std::io::stdout(), Clear(ClearType::All))?;
+    let mut set: Settings = Settings::new().unwrap();
+    println!(
+        "\n\n\nObservatory Profiles (actual: {})\n",
+        set.get_observatory_name()
+    );
+    for observatory in set.get_observatories() {
+        println!(
+            "{} {}: {}, {:.4}° {:.4}° {} m, MPC code {}",
+            if observatory.observatory_name == *set.get_observatory_name() {
+                "*"
+            } else {
+                " "
+            },
+            observatory.observatory_name,
+            observatory.place,
+            observatory.latitude,
+            observatory.longitude,
+            observatory.altitude,
+            observatory.mpc_code
+        );
+    }
+    let mut p = Listbox::new(vec![
+        "Select active profile",
+        "Add profile from actual observatory",
+        "Remove profile",
+        "Back",
+    ])
+    .title("\nSelect an option:")
+    .listbox_lines(5)
+    .prompt()?;
+    let result = match p.run()?.as_str() {
+        "Add profile from actual observatory" => {
+            let name = Readline::default()
+                .title("New observatory name:")
+                .prompt()?
+                .run()?;
+            set.add_observatory(name.trim())
+        }
+        "Select active profile" | "Remove profile" if set.get_observatories().is_empty() => {
+            Err("No observatory profiles set".into())
+        }
+        "Select active profile" => {
+            let name = select_observatory("Select active profile:", &set)?;
+            set.set_active_observatory(&name)
+        }
+        "Remove profile" => {
+            let name = select_observatory("Select profile to remove:", &set)?;
+            set.remove_observatory(&name)
+        }
+        _ => Ok(()),
+    };
+    result.unwrap_or_else(|error| println!("Error: {}", error));
+    Ok(())
+}
+
+/// Asks for one of the observatory profiles, returning its name
+///
+/// * `title`: prompt title
+/// * `set`: settings with the profiles
+fn select_observatory(title: &str, set: &Settings) -> Result<String, Box<dyn std::error::Error>> {
+    let names: Vec<String> = set
+        .get_observatories()
+        .iter()
+        .map(|observatory| observatory.observatory_name.clone())
+        .collect();
+    Ok(Listbox::new(names)
+        .title(title)
+        .listbox_lines(5)
+        .prompt()?
+        .run()?)
+}
+
 impl TryFrom<Vec<&str>> for Settings {
     type Error = ParseIntError;
 
@@ -82,7 +421,9 @@ impl TryFrom<Vec<&str>> for Settings {
         let actual_settings: Settings = Settings::new().unwrap();
 
         let general = General {
-            lang: "".to_string(),
+            lang: actual_settings.get_lang().to_string(),
+            local_time: *actual_settings.get_local_time(),
+            active_observatory: actual_settings.general.active_observatory.clone(),
         };
 
         let observatory = Observatory {
@@ -141,12 +482,14 @@ impl TryFrom<Vec<&str>> for Settings {
             } else {
                 value[10].parse::<i32>()?
             },
+            timezone: actual_settings.observatory.timezone.clone(),
+            horizon: actual_settings.observatory.horizon.clone(),
         };
 
-        Ok(Settings {
-            general,
-            observatory,
-        })
+        let mut settings = actual_settings;
+        settings.general = general;
+        settings.observatory = observatory;
+        Ok(settings)
     }
 }
 
@@ -306,7 +649,10 @@ pub fn observatory_settings_menu() -> Result<(), Box<dyn std::error::Error>> {
         new_vec.push(s);
     }
     let mut settings: Settings = Settings::try_from(new_vec)?;
-    let _ = settings.set_settings(settings.clone());
+    if let Err(error) = settings.set_settings(settings.clone()) {
+        println!("Error: {}", error);
+        return Ok(());
+    }
 
     println!("Parsed settings: {:?}", settings);
 
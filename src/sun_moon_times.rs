@@ -2,24 +2,31 @@
 //!
 //! Library for getting Sunrise, Moonrise, etc
 //!
-//! It gets data from api.sunrise-sunset.org and returns a structure to be parsed in some way
+//! Times are computed locally from the observatory coordinates in `Settings`, using the
+//! low precision solar position formulas by NOAA (Meeus, Astronomical Algorithms), so no
+//! network connection is needed. The results are accurate to about a minute.
+//! Moon times and phase come from the MiniMoon series by Montenbruck and Pfleger.
 //!
-//! Here is an example of the response:
+//! All times are `chrono` UTC date-times, that can be converted to the observatory local
+//! zone with `SunMoonTimes::local`.
+//!
+//! The same data can still be fetched from api.sunrise-sunset.org, to cross-check the
+//! local computation. Here is an example of the response (with `formatted=0`):
 //!
 //! ```json
 //! {
 //!     "results": {
-//!         "sunrise": "6:34:37 AM",
-//!         "sunset": "5:35:35 PM",
-//!         "solar_noon": "12:00:00 AM",
-//!         "day_length": "05:02:00",
-//!         "civil_twilight_begin": "6:07:00 AM",
-//!         "civil_twilight_end": "5:32:00 PM",
-//!         "nautical_twilight_begin": "6:12:00 AM",
-//!         "nautical_twilight_end": "5:27:00 PM",
-//!         "astronomical_twilight_begin": "6:17:00 AM",
-//!         "astronomical_twilight_end": "5:22:00 PM"
-//!     }
+//!         "sunrise": "2025-01-15T06:52:41+00:00",
+//!         "sunset": "2025-01-15T16:08:26+00:00",
+//!         "solar_noon": "2025-01-15T11:30:33+00:00",
+//!         "day_length": 33345,
+//!         "civil_twilight_begin": "2025-01-15T06:20:33+00:00",
+//!         "civil_twilight_end": "2025-01-15T16:40:34+00:00",
+//!         "nautical_twilight_begin": "2025-01-15T05:44:49+00:00",
+//!         "nautical_twilight_end": "2025-01-15T17:16:18+00:00",
+//!         "astronomical_twilight_begin": "2025-01-15T05:10:13+00:00",
+//!         "astronomical_twilight_end": "2025-01-15T17:50:55+00:00"
+//!     },
 //!     "status": "OK",
 //!     "tzid": "UTC"
 //! }
@@ -29,22 +36,46 @@
 //!
 //! ```rust
 //! use asteroid_tui::sun_moon_times;
-//! let data = sun_moon_times::prepare_data().unwrap();
+//! let data = sun_moon_times::prepare_data(chrono::Utc::now().date_naive()).unwrap();
 //! ```
 
 #![warn(missing_docs)]
 
-use crate::settings::Settings;
+use crate::coordinates::{self, julian_day, Observer};
+use crate::settings::{suggest_timezone, Settings};
+use chrono::{DateTime, Duration, NaiveDate, NaiveTime, Utc};
+use chrono_tz::Tz;
 use reqwest;
 use serde::Deserialize;
 use serde_json::Result;
 
-#[derive(Debug, Deserialize, serde::Serialize)]
+/// Altitude of the Sun center at sunrise and sunset, refraction and semidiameter included
+const SUNRISE_ALTITUDE: f64 = -0.833;
+/// Altitude of the Sun center at the begin and end of civil twilight
+const CIVIL_TWILIGHT_ALTITUDE: f64 = -6.0;
+/// Altitude of the Sun center at the begin and end of nautical twilight
+const NAUTICAL_TWILIGHT_ALTITUDE: f64 = -12.0;
+/// Altitude of the Sun center at the begin and end of astronomical twilight
+const ASTRONOMICAL_TWILIGHT_ALTITUDE: f64 = -18.0;
+/// Altitude of the Moon center at moonrise and moonset, parallax, refraction and semidiameter included
+const MOONRISE_ALTITUDE: f64 = 0.133;
+/// Step used when searching Moon events, in minutes
+const MOON_SEARCH_STEP: f64 = 10.0;
+/// Mean length of the synodic month in days
+const SYNODIC_MONTH: f64 = 29.530589;
+/// Mean distance of the Sun in km
+const SUN_DISTANCE: f64 = 149597870.7;
+
+#[derive(Debug, Clone)]
 /// Structure with data for Sun, Moon, etc
 ///
+/// Events that do not happen on the date (i.e. during polar day or night, or the Moon
+/// rising once every about 25 hours) are None.
+///
 /// * `sunrise`: Sunrise time
 /// * `sunset`: Sunset time
 /// * `solar_noon`: Solar noon time
+/// * `noon_altitude`: Sun altitude at solar noon
 /// * `day_length`: Day length
 /// * `civil_twilight_begin`: Civil twilight begin time
 /// * `civil_twilight_end`: Civil twilight end time
@@ -52,31 +83,106 @@ use serde_json::Result;
 /// * `nautical_twilight_end`: Nautical twilight end time
 /// * `astronomical_twilight_begin`: Astronomical twilight begin time
 /// * `astronomical_twilight_end`: Astronomical twilight end time
+/// * `moonrise`: Moonrise time
+/// * `moonset`: Moonset time
+/// * `moon_transit`: Moon transit time
+/// * `moon_phase_angle`: Moon phase angle in degrees
+/// * `moon_illumination`: Moon illuminated fraction (0-1)
+/// * `moon_age`: Moon age in days since new moon
+/// * `timezone`: observatory timezone
 pub struct SunMoonTimes {
     /// Sunrise time
-    pub sunrise: String,
+    pub sunrise: Option<DateTime<Utc>>,
     /// Sunset time
-    pub sunset: String,
+    pub sunset: Option<DateTime<Utc>>,
     /// Solar noon time
-    pub solar_noon: String,
+    pub solar_noon: DateTime<Utc>,
+    /// Sun altitude at solar noon in degrees, without refraction
+    pub noon_altitude: f64,
     /// Day length
-    pub day_length: String,
+    pub day_length: Duration,
     /// Civil twilight begin time
-    pub civil_twilight_begin: String,
+    pub civil_twilight_begin: Option<DateTime<Utc>>,
     /// Civil twilight end time
-    pub civil_twilight_end: String,
+    pub civil_twilight_end: Option<DateTime<Utc>>,
     /// Nautical twilight begin time
-    pub nautical_twilight_begin: String,
+    pub nautical_twilight_begin: Option<DateTime<Utc>>,
     /// Nautical twilight end time
-    pub nautical_twilight_end: String,
+    pub nautical_twilight_end: Option<DateTime<Utc>>,
     /// Astronomical twilight begin time
-    pub astronomical_twilight_begin: String,
+    pub astronomical_twilight_begin: Option<DateTime<Utc>>,
     /// Astronomical twilight end time
-    pub astronomical_twilight_end: String,
+    pub astronomical_twilight_end: Option<DateTime<Utc>>,
+    /// Moonrise time
+    pub moonrise: Option<DateTime<Utc>>,
+    /// Moonset time
+    pub moonset: Option<DateTime<Utc>>,
+    /// Moon transit time
+    pub moon_transit: Option<DateTime<Utc>>,
+    /// Moon phase angle in degrees, 0 at full moon and 180 at new moon
+    pub moon_phase_angle: f64,
+    /// Moon illuminated fraction, from 0 to 1
+    pub moon_illumination: f64,
+    /// Moon age in days since new moon
+    pub moon_age: f64,
+    /// Observatory timezone
+    pub timezone: Tz,
 }
 
-#[derive(Debug, Deserialize, serde::Serialize)]
+impl SunMoonTimes {
+    /// Converts a time to the observatory local zone
+    ///
+    /// * `time`: UTC time to convert
+    pub fn local(&self, time: DateTime<Utc>) -> DateTime<Tz> {
+        time.with_timezone(&self.timezone)
+    }
+
+    /// Returns true if the Sun stays below -18° for the whole day, even at solar noon
+    pub fn is_dark_all_day(&self) -> bool {
+        self.noon_altitude < ASTRONOMICAL_TWILIGHT_ALTITUDE
+    }
+
+    /// Returns the length of astronomical night around the date
+    ///
+    /// It is the part of the day with the Sun below -18°, i.e. the whole day during polar
+    /// night and zero when the sky never gets fully dark.
+    pub fn astronomical_night_length(&self) -> Duration {
+        match (
+            self.astronomical_twilight_begin,
+            self.astronomical_twilight_end,
+        ) {
+            (Some(begin), Some(end)) => Duration::days(1) - (end - begin),
+            _ if self.is_dark_all_day() => Duration::days(1),
+            _ => Duration::zero(),
+        }
+    }
+}
+
+#[derive(Debug, Deserialize)]
+/// Results structure from sunrise-sunset.org, requested with formatted=0
+struct OnlineSunTimes {
+    sunrise: DateTime<Utc>,
+    sunset: DateTime<Utc>,
+    solar_noon: DateTime<Utc>,
+    day_length: i64,
+    civil_twilight_begin: DateTime<Utc>,
+    civil_twilight_end: DateTime<Utc>,
+    nautical_twilight_begin: DateTime<Utc>,
+    nautical_twilight_end: DateTime<Utc>,
+    astronomical_twilight_begin: DateTime<Utc>,
+    astronomical_twilight_end: DateTime<Utc>,
+}
+
+#[derive(Debug, Deserialize)]
 /// Response structure from sunrise-sunset.org
+struct OnlineResponse {
+    results: OnlineSunTimes,
+    status: String,
+    tzid: String,
+}
+
+#[derive(Debug)]
+/// Response structure with Sun and Moon data
 ///
 /// * `results`: results of type SunMoonTimes
 /// * `status`: status of response
@@ -84,19 +190,414 @@ pub struct SunMoonTimes {
 pub struct SunMoonTimesResponse {
     /// Results with data
     pub results: SunMoonTimes,
-    status: String,
+    /// Status of response
+    pub status: String,
     /// Timezone specified
     pub tzid: String,
 }
 
+/// Astronomical darkness of a night
+///
+/// When a day is dark from noon to noon, its solar noon is used as darkness begin or end.
+///
+/// * `date`: date of the evening the night begins
+/// * `begin`: darkness begin time, i.e. end of the evening astronomical twilight
+/// * `end`: darkness end time, i.e. begin of the next morning astronomical twilight
+/// * `polar_night`: true if the Sun stays below -18° on both days
+/// * `moon_illumination`: Moon illuminated fraction at local midnight
+#[derive(Debug)]
+pub struct Darkness {
+    /// Date of the evening the night begins
+    pub date: NaiveDate,
+    /// Darkness begin time, solar noon of `date` during polar night
+    pub begin: Option<DateTime<Utc>>,
+    /// Darkness end time on the day after `date`, its solar noon during polar night
+    pub end: Option<DateTime<Utc>>,
+    /// True if the Sun stays below -18° on both days, so darkness lasts from noon to noon
+    pub polar_night: bool,
+    /// Moon illuminated fraction at local midnight
+    pub moon_illumination: f64,
+}
+
+impl Darkness {
+    /// Returns darkness duration, zero if the sky never gets fully dark
+    pub fn duration(&self) -> Duration {
+        match (self.begin, self.end) {
+            (Some(begin), Some(end)) => end - begin,
+            _ => Duration::zero(),
+        }
+    }
+
+    /// Returns true if `time` falls within darkness
+    ///
+    /// * `time`: time to check
+    pub fn contains(&self, time: DateTime<Utc>) -> bool {
+        match (self.begin, self.end) {
+            (Some(begin), Some(end)) => begin <= time && time <= end,
+            _ => false,
+        }
+    }
+}
+
+/// Apparent position of the Sun
+///
+/// * `right_ascension`: right ascension in degrees
+/// * `declination`: declination in degrees
+/// * `longitude`: ecliptic longitude in degrees
+/// * `equation_of_time`: equation of time in minutes
+pub(crate) struct SunPosition {
+    /// Right ascension in degrees
+    pub right_ascension: f64,
+    /// Declination in degrees
+    pub declination: f64,
+    /// Ecliptic longitude in degrees
+    pub longitude: f64,
+    /// Equation of time in minutes
+    pub equation_of_time: f64,
+}
+
+/// Returns Sun apparent position
+///
+/// * `jd`: Julian Day
+pub(crate) fn sun_position(jd: f64) -> SunPosition {
+    let t = (jd - 2451545.0) / 36525.0;
+    let mean_longitude = (280.46646 + t * (36000.76983 + t * 0.0003032)).rem_euclid(360.0);
+    let mean_anomaly = 357.52911 + t * (35999.05029 - 0.0001537 * t);
+    let eccentricity = 0.016708634 - t * (0.000042037 + 0.0000001267 * t);
+    let m = mean_anomaly.to_radians();
+    let center = m.sin() * (1.914602 - t * (0.004817 + 0.000014 * t))
+        + (2.0 * m).sin() * (0.019993 - 0.000101 * t)
+        + (3.0 * m).sin() * 0.000289;
+    let omega = (125.04 - 1934.136 * t).to_radians();
+    let apparent_longitude =
+        (mean_longitude + center - 0.00569 - 0.00478 * omega.sin()).to_radians();
+    let mean_obliquity =
+        23.0 + (26.0 + (21.448 - t * (46.815 + t * (0.00059 - t * 0.001813))) / 60.0) / 60.0;
+    let obliquity = (mean_obliquity + 0.00256 * omega.cos()).to_radians();
+    let declination = (obliquity.sin() * apparent_longitude.sin()).asin();
+    let right_ascension =
+        (obliquity.cos() * apparent_longitude.sin()).atan2(apparent_longitude.cos());
+
+    let y = (obliquity / 2.0).tan().powi(2);
+    let l0 = mean_longitude.to_radians();
+    let equation_of_time = y * (2.0 * l0).sin() - 2.0 * eccentricity * m.sin()
+        + 4.0 * eccentricity * y * m.sin() * (2.0 * l0).cos()
+        - 0.5 * y * y * (4.0 * l0).sin()
+        - 1.25 * eccentricity * eccentricity * (2.0 * m).sin();
+    SunPosition {
+        right_ascension: right_ascension.to_degrees().rem_euclid(360.0),
+        declination: declination.to_degrees(),
+        longitude: apparent_longitude.to_degrees().rem_euclid(360.0),
+        equation_of_time: 4.0 * equation_of_time.to_degrees(),
+    }
+}
+
+/// Apparent geocentric position of the Moon
+///
+/// * `right_ascension`: right ascension in degrees
+/// * `declination`: declination in degrees
+/// * `longitude`: ecliptic longitude in degrees
+/// * `distance`: distance from Earth center in km
+pub(crate) struct MoonPosition {
+    /// Right ascension in degrees
+    pub right_ascension: f64,
+    /// Declination in degrees
+    pub declination: f64,
+    /// Ecliptic longitude in degrees
+    pub longitude: f64,
+    /// Distance from Earth center in km
+    pub distance: f64,
+}
+
+/// Returns Moon geocentric position, accurate to about one arcminute
+///
+/// It uses the MiniMoon series by Montenbruck and Pfleger, with the main distance terms by Meeus
+///
+/// * `jd`: Julian Day
+pub(crate) fn moon_position(jd: f64) -> MoonPosition {
+    const ARCSEC: f64 = 206264.8062;
+    let t = (jd - 2451545.0) / 36525.0;
+    let turn = |value: f64| std::f64::consts::TAU * value.rem_euclid(1.0);
+    let mean_longitude = (0.606433 + 1336.855225 * t).rem_euclid(1.0);
+    let l = turn(0.374897 + 1325.552410 * t);
+    let ls = turn(0.993133 + 99.997361 * t);
+    let d = turn(0.827361 + 1236.853086 * t);
+    let f = turn(0.259086 + 1342.227825 * t);
+
+    let delta_longitude = 22640.0 * l.sin() - 4586.0 * (l - 2.0 * d).sin()
+        + 2370.0 * (2.0 * d).sin()
+        + 769.0 * (2.0 * l).sin()
+        - 668.0 * ls.sin()
+        - 412.0 * (2.0 * f).sin()
+        - 212.0 * (2.0 * l - 2.0 * d).sin()
+        - 206.0 * (l + ls - 2.0 * d).sin()
+        + 192.0 * (l + 2.0 * d).sin()
+        - 165.0 * (ls - 2.0 * d).sin()
+        - 125.0 * d.sin()
+        - 110.0 * (l + ls).sin()
+        + 148.0 * (l - ls).sin()
+        - 55.0 * (2.0 * f - 2.0 * d).sin();
+    let s = f + (delta_longitude + 412.0 * (2.0 * f).sin() + 541.0 * ls.sin()) / ARCSEC;
+    let h = f - 2.0 * d;
+    let n = -526.0 * h.sin() + 44.0 * (l + h).sin() - 31.0 * (-l + h).sin() - 23.0 * (ls + h).sin()
+        + 11.0 * (-ls + h).sin()
+        - 25.0 * (-2.0 * l + f).sin()
+        + 21.0 * (-l + f).sin();
+    let longitude = turn(mean_longitude + delta_longitude / 1296.0e3);
+    let latitude = (18520.0 * s.sin() + n) / ARCSEC;
+    let distance = 385000.56
+        - 20905.355 * l.cos()
+        - 3699.111 * (2.0 * d - l).cos()
+        - 2955.968 * (2.0 * d).cos()
+        - 569.925 * (2.0 * l).cos();
+
+    let obliquity = (23.43929111 - 0.0130042 * t).to_radians();
+    let declination = (latitude.sin() * obliquity.cos()
+        + latitude.cos() * obliquity.sin() * longitude.sin())
+    .asin();
+    let right_ascension = (longitude.sin() * obliquity.cos() - latitude.tan() * obliquity.sin())
+        .atan2(longitude.cos());
+    MoonPosition {
+        right_ascension: right_ascension.to_degrees().rem_euclid(360.0),
+        declination: declination.to_degrees(),
+        longitude: longitude.to_degrees(),
+        distance,
+    }
+}
+
+/// Returns the UTC date-time of a number of minutes after midnight of `date`
+///
+/// * `date`: reference date
+/// * `minutes`: minutes after 0h UTC
+fn minutes_to_datetime(date: NaiveDate, minutes: f64) -> DateTime<Utc> {
+    date.and_time(NaiveTime::MIN).and_utc() + Duration::milliseconds((minutes * 60000.0) as i64)
+}
+
+/// Returns solar noon as minutes after 0h UTC of `date`
+///
+/// * `date`: date of the event
+/// * `longitude`: observer longitude, east positive
+fn solar_noon_minutes(date: NaiveDate, longitude: f64) -> f64 {
+    let mut noon = 720.0 - 4.0 * longitude;
+    for _ in 0..2 {
+        let sun = sun_position(julian_day(minutes_to_datetime(date, noon)));
+        noon = 720.0 - 4.0 * longitude - sun.equation_of_time;
+    }
+    noon
+}
+
+/// Returns the time when the Sun center crosses `altitude`, as minutes after 0h UTC of `date`
+///
+/// Returns None if the Sun never reaches that altitude on that day.
+///
+/// * `date`: date of the event
+/// * `latitude`: observer latitude
+/// * `longitude`: observer longitude, east positive
+/// * `altitude`: altitude of the Sun center in degrees
+/// * `rising`: true for the morning event, false for the evening one
+fn sun_event_minutes(
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+    rising: bool,
+) -> Option<f64> {
+    let sign = if rising { -1.0 } else { 1.0 };
+    let noon = solar_noon_minutes(date, longitude);
+    let mut event = noon;
+    for _ in 0..3 {
+        let sun = sun_position(julian_day(minutes_to_datetime(date, event)));
+        let (phi, delta) = (latitude.to_radians(), sun.declination.to_radians());
+        let cos_hour_angle =
+            (altitude.to_radians().sin() - phi.sin() * delta.sin()) / (phi.cos() * delta.cos());
+        if !(-1.0..=1.0).contains(&cos_hour_angle) {
+            return None;
+        }
+        let hour_angle = cos_hour_angle.acos().to_degrees();
+        event = 720.0 - 4.0 * longitude - sun.equation_of_time + sign * 4.0 * hour_angle;
+    }
+    Some(event)
+}
+
+/// Returns Moon altitude corrected for `horizon` and Moon hour angle, both in degrees
+///
+/// * `date`: reference date
+/// * `minutes`: minutes after 0h UTC
+/// * `latitude`: observer latitude
+/// * `longitude`: observer longitude, east positive
+/// * `horizon`: altitude of the Moon center at moonrise
+fn moon_altitude_and_hour_angle(
+    date: NaiveDate,
+    minutes: f64,
+    latitude: f64,
+    longitude: f64,
+    horizon: f64,
+) -> (f64, f64) {
+    let time = minutes_to_datetime(date, minutes);
+    let moon = moon_position(julian_day(time));
+    let observer = Observer::new(latitude, longitude, 0.0);
+    let position = coordinates::equatorial_to_horizontal(
+        moon.right_ascension,
+        moon.declination,
+        time,
+        &observer,
+    );
+    (
+        position.altitude - horizon,
+        coordinates::hour_angle(moon.right_ascension, time, longitude),
+    )
+}
+
+/// Returns moonrise, moonset and Moon transit as minutes after 0h UTC of `date`
+///
+/// Each event is None if it does not happen on that day, which occurs about once a month.
+///
+/// * `date`: date of the events
+/// * `latitude`: observer latitude
+/// * `longitude`: observer longitude, east positive
+/// * `horizon`: altitude of the Moon center at moonrise
+fn moon_events_minutes(
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+    horizon: f64,
+) -> (Option<f64>, Option<f64>, Option<f64>) {
+    let (mut rise, mut set, mut transit) = (None, None, None);
+    let mut previous_minutes = 0.0;
+    let (mut previous_altitude, mut previous_hour_angle) =
+        moon_altitude_and_hour_angle(date, 0.0, latitude, longitude, horizon);
+    while previous_minutes < 1440.0 {
+        let minutes = previous_minutes + MOON_SEARCH_STEP;
+        let (altitude, hour_angle) =
+            moon_altitude_and_hour_angle(date, minutes, latitude, longitude, horizon);
+        let crossing =
+            |from: f64, to: f64| previous_minutes + MOON_SEARCH_STEP * from / (from - to);
+        if previous_altitude < 0.0 && altitude >= 0.0 && rise.is_none() {
+            rise = Some(crossing(previous_altitude, altitude));
+        }
+        if previous_altitude >= 0.0 && altitude < 0.0 && set.is_none() {
+            set = Some(crossing(previous_altitude, altitude));
+        }
+        if previous_hour_angle < 0.0 && hour_angle >= 0.0 && transit.is_none() {
+            transit = Some(crossing(previous_hour_angle, hour_angle));
+        }
+        previous_minutes = minutes;
+        previous_altitude = altitude;
+        previous_hour_angle = hour_angle;
+    }
+    let within_day = |event: Option<f64>| event.filter(|minutes| *minutes < 1440.0);
+    (within_day(rise), within_day(set), within_day(transit))
+}
+
+/// Returns Moon phase angle (degrees), illuminated fraction and age (days)
+///
+/// * `jd`: Julian Day
+fn moon_phase(jd: f64) -> (f64, f64, f64) {
+    let sun = sun_position(jd);
+    let moon = moon_position(jd);
+    let (sun_ra, sun_dec) = (
+        sun.right_ascension.to_radians(),
+        sun.declination.to_radians(),
+    );
+    let (moon_ra, moon_dec) = (
+        moon.right_ascension.to_radians(),
+        moon.declination.to_radians(),
+    );
+    let elongation = (sun_dec.sin() * moon_dec.sin()
+        + sun_dec.cos() * moon_dec.cos() * (sun_ra - moon_ra).cos())
+    .clamp(-1.0, 1.0)
+    .acos();
+    let phase_angle =
+        (SUN_DISTANCE * elongation.sin()).atan2(moon.distance - SUN_DISTANCE * elongation.cos());
+    let illumination = (1.0 + phase_angle.cos()) / 2.0;
+    let age = (moon.longitude - sun.longitude).rem_euclid(360.0) / 360.0 * SYNODIC_MONTH;
+    (phase_angle.to_degrees(), illumination, age)
+}
+
+/// Computes Sun and Moon times for a date and a place, without network access
+///
+/// Moon phase, illumination and age refer to the local midnight following `date`.
+///
+/// * `date`: date of computation (UTC)
+/// * `latitude`: observer latitude in degrees
+/// * `longitude`: observer longitude in degrees, east positive
+/// * `altitude`: observer altitude above sea level in meters, used for horizon dip
+pub fn compute_sun_moon_times(
+    date: NaiveDate,
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> SunMoonTimes {
+    let dip = 0.0347 * altitude.max(0.0).sqrt();
+    let horizon = SUNRISE_ALTITUDE - dip;
+    let event = |altitude: f64, rising: bool| {
+        sun_event_minutes(date, latitude, longitude, altitude, rising)
+            .map(|minutes| minutes_to_datetime(date, minutes))
+    };
+    let noon = solar_noon_minutes(date, longitude);
+    let solar_noon = minutes_to_datetime(date, noon);
+    let sunrise = event(horizon, true);
+    let sunset = event(horizon, false);
+    let noon_altitude = noon_altitude(solar_noon, latitude);
+    let day_length = match (sunrise, sunset) {
+        (Some(sunrise), Some(sunset)) => sunset - sunrise,
+        _ => {
+            if noon_altitude > horizon {
+                Duration::days(1)
+            } else {
+                Duration::zero()
+            }
+        }
+    };
+    let (moonrise, moonset, moon_transit) =
+        moon_events_minutes(date, latitude, longitude, MOONRISE_ALTITUDE - dip);
+    let to_datetime =
+        |minutes: Option<f64>| minutes.map(|minutes| minutes_to_datetime(date, minutes));
+    let midnight = minutes_to_datetime(date, noon + 720.0);
+    let (moon_phase_angle, moon_illumination, moon_age) = moon_phase(julian_day(midnight));
+    SunMoonTimes {
+        sunrise,
+        sunset,
+        solar_noon,
+        noon_altitude,
+        day_length,
+        civil_twilight_begin: event(CIVIL_TWILIGHT_ALTITUDE, true),
+        civil_twilight_end: event(CIVIL_TWILIGHT_ALTITUDE, false),
+        nautical_twilight_begin: event(NAUTICAL_TWILIGHT_ALTITUDE, true),
+        nautical_twilight_end: event(NAUTICAL_TWILIGHT_ALTITUDE, false),
+        astronomical_twilight_begin: event(ASTRONOMICAL_TWILIGHT_ALTITUDE, true),
+        astronomical_twilight_end: event(ASTRONOMICAL_TWILIGHT_ALTITUDE, false),
+        moonrise: to_datetime(moonrise),
+        moonset: to_datetime(moonset),
+        moon_transit: to_datetime(moon_transit),
+        moon_phase_angle,
+        moon_illumination,
+        moon_age,
+        timezone: suggest_timezone(longitude as f32),
+    }
+}
+
+/// Returns the Sun altitude at solar noon in degrees, without refraction
+///
+/// * `solar_noon`: solar noon time
+/// * `latitude`: observer latitude in degrees
+fn noon_altitude(solar_noon: DateTime<Utc>, latitude: f64) -> f64 {
+    90.0 - (latitude - sun_position(julian_day(solar_noon)).declination).abs()
+}
+
 /// Returns a text string with reponse from sunrise-sunset.org
-fn get_sun_moon_times() -> String {
+///
+/// * `date`: date of the requested times
+fn get_sun_moon_times(date: NaiveDate) -> String {
     let settings = Settings::new().unwrap();
     let url: reqwest::Url = reqwest::Url::parse_with_params(
         "https://api.sunrise-sunset.org/json",
         [
             ("lat", settings.observatory.latitude.to_string()),
             ("lng", settings.observatory.longitude.to_string()),
+            ("date", date.format("%Y-%m-%d").to_string()),
+            ("formatted", "0".to_string()),
+            ("tzid", settings.get_timezone().name().to_string()),
         ],
     )
     .unwrap();
@@ -105,23 +606,277 @@ fn get_sun_moon_times() -> String {
 }
 
 /// Returns a json with data for Sunset, sunrise, etc
-pub fn prepare_data() -> Result<SunMoonTimesResponse> {
-    let response: String = get_sun_moon_times();
-    serde_json::from_str(&response)
+///
+/// Data are computed locally for the observatory in `Settings`
+///
+/// * `date`: date of computation (UTC)
+pub fn prepare_data(date: NaiveDate) -> Result<SunMoonTimesResponse> {
+    let settings = Settings::new().unwrap();
+    let mut results = compute_sun_moon_times(
+        date,
+        *settings.get_latitude() as f64,
+        *settings.get_longitude() as f64,
+        *settings.get_altitude() as f64,
+    );
+    results.timezone = settings.get_timezone();
+    Ok(SunMoonTimesResponse {
+        results,
+        status: "OK".to_string(),
+        tzid: "UTC".to_string(),
+    })
+}
+
+/// Returns a json with data for Sunset, sunrise, etc fetched from sunrise-sunset.org
+///
+/// It needs network access and it is meant to cross-check the local computation
+///
+/// Moon data are not provided by the service, so they are left empty.
+///
+/// * `date`: date of the requested times
+pub fn prepare_online_data(date: NaiveDate) -> Result<SunMoonTimesResponse> {
+    let settings = Settings::new().unwrap();
+    let response: OnlineResponse = serde_json::from_str(&get_sun_moon_times(date))?;
+    // The service returns the Unix epoch for events that do not happen
+    let event = |time: DateTime<Utc>| Some(time).filter(|time| time.timestamp() > 86400);
+    let results = response.results;
+    Ok(SunMoonTimesResponse {
+        results: SunMoonTimes {
+            sunrise: event(results.sunrise),
+            sunset: event(results.sunset),
+            solar_noon: results.solar_noon,
+            noon_altitude: noon_altitude(results.solar_noon, *settings.get_latitude() as f64),
+            day_length: Duration::seconds(results.day_length),
+            civil_twilight_begin: event(results.civil_twilight_begin),
+            civil_twilight_end: event(results.civil_twilight_end),
+            nautical_twilight_begin: event(results.nautical_twilight_begin),
+            nautical_twilight_end: event(results.nautical_twilight_end),
+            astronomical_twilight_begin: event(results.astronomical_twilight_begin),
+            astronomical_twilight_end: event(results.astronomical_twilight_end),
+            moonrise: None,
+            moonset: None,
+            moon_transit: None,
+            moon_phase_angle: 0.0,
+            moon_illumination: 0.0,
+            moon_age: 0.0,
+            timezone: settings.get_timezone(),
+        },
+        status: response.status,
+        tzid: response.tzid,
+    })
+}
+
+/// Computes astronomical darkness for a number of consecutive nights and a place
+///
+/// * `start`: date of the evening of the first night
+/// * `nights`: number of nights
+/// * `latitude`: observer latitude in degrees
+/// * `longitude`: observer longitude in degrees, east positive
+/// * `altitude`: observer altitude above sea level in meters
+pub fn compute_darkness(
+    start: NaiveDate,
+    nights: u32,
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> Vec<Darkness> {
+    let mut darkness: Vec<Darkness> = Vec::new();
+    let mut evening = compute_sun_moon_times(start, latitude, longitude, altitude);
+    for date in start.iter_days().take(nights as usize) {
+        let morning =
+            compute_sun_moon_times(date + Duration::days(1), latitude, longitude, altitude);
+        darkness.push(Darkness {
+            date,
+            begin: if evening.is_dark_all_day() {
+                Some(evening.solar_noon)
+            } else {
+                evening.astronomical_twilight_end
+            },
+            end: if morning.is_dark_all_day() {
+                Some(morning.solar_noon)
+            } else {
+                morning.astronomical_twilight_begin
+            },
+            polar_night: evening.is_dark_all_day() && morning.is_dark_all_day(),
+            moon_illumination: evening.moon_illumination,
+        });
+        evening = morning;
+    }
+    darkness
+}
+
+/// Returns astronomical darkness for a number of consecutive nights
+///
+/// Data are computed locally for the observatory in `Settings`
+///
+/// * `start`: date of the evening of the first night
+/// * `nights`: number of nights
+pub fn prepare_darkness(start: NaiveDate, nights: u32) -> Result<Vec<Darkness>> {
+    let settings = Settings::new().unwrap();
+    Ok(compute_darkness(
+        start,
+        nights,
+        *settings.get_latitude() as f64,
+        *settings.get_longitude() as f64,
+        *settings.get_altitude() as f64,
+    ))
 }
 
 #[cfg(test)]
 mod test {
     use super::*;
 
+    /// Returns minutes after midnight of an event
+    fn to_minutes(time: Option<DateTime<Utc>>) -> f64 {
+        let time = time.unwrap().time();
+        time.signed_duration_since(NaiveTime::MIN).num_seconds() as f64 / 60.0
+    }
+
     #[test]
     fn test_get_sun_moon_times() {
-        assert!(get_sun_moon_times().contains("solar_noon"));
+        assert!(get_sun_moon_times(Utc::now().date_naive()).contains("solar_noon"));
     }
 
     #[test]
     fn test_prepare_data() {
-        let data = prepare_data().unwrap();
+        let data = prepare_data(Utc::now().date_naive()).unwrap();
         assert_eq!(data.status, "OK");
     }
+
+    #[test]
+    fn test_compute_darkness() {
+        let start = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let darkness = compute_darkness(start, 3, 44.09727, 9.7737, 100.0);
+        assert_eq!(darkness.len(), 3);
+        assert_eq!(
+            darkness[2].date,
+            NaiveDate::from_ymd_opt(2025, 1, 17).unwrap()
+        );
+        assert_ne!(darkness[0].end, darkness[1].end);
+        // About 12 hours of astronomical darkness in mid January at latitude 44°
+        let hours = darkness[0].duration().num_minutes() as f64 / 60.0;
+        assert!((11.0..13.0).contains(&hours), "{}", hours);
+        assert!(darkness[0].contains(darkness[0].begin.unwrap() + Duration::hours(1)));
+    }
+
+    #[test]
+    fn test_compute_sun_times_greenwich_solstice() {
+        let date = NaiveDate::from_ymd_opt(2024, 6, 21).unwrap();
+        let times = compute_sun_moon_times(date, 51.4769, -0.0005, 0.0);
+        // 03:43 and 20:21 UTC according to the Royal Observatory
+        assert!((to_minutes(times.sunrise) - (3.0 * 60.0 + 43.0)).abs() < 2.0);
+        assert!((to_minutes(times.sunset) - (20.0 * 60.0 + 21.0)).abs() < 2.0);
+        assert!(times.astronomical_twilight_begin.is_none());
+        assert!(times.astronomical_twilight_end.is_none());
+        assert!(times.astronomical_night_length().is_zero());
+    }
+
+    #[test]
+    fn test_compute_sun_times_polar() {
+        // Around 88° N the Sun stays between -18° and -6° in late October
+        let twilight = compute_sun_moon_times(
+            NaiveDate::from_ymd_opt(2025, 10, 20).unwrap(),
+            88.0,
+            0.0,
+            0.0,
+        );
+        assert!(twilight.astronomical_twilight_end.is_none());
+        assert!(!twilight.is_dark_all_day());
+        assert!(twilight.astronomical_night_length().is_zero());
+        let night = compute_sun_moon_times(
+            NaiveDate::from_ymd_opt(2025, 12, 21).unwrap(),
+            88.0,
+            0.0,
+            0.0,
+        );
+        assert!(night.is_dark_all_day());
+        assert_eq!(night.astronomical_night_length(), Duration::days(1));
+    }
+
+    #[test]
+    fn test_compute_darkness_polar() {
+        let start = NaiveDate::from_ymd_opt(2025, 12, 20).unwrap();
+        let darkness = compute_darkness(start, 2, 88.0, 0.0, 0.0);
+        assert!(darkness[0].polar_night);
+        let hours = darkness[0].duration().num_minutes() as f64 / 60.0;
+        assert!((hours - 24.0).abs() < 0.1, "{}", hours);
+        assert!(darkness[0].contains(darkness[0].begin.unwrap() + Duration::hours(12)));
+        let twilight = compute_darkness(
+            NaiveDate::from_ymd_opt(2025, 10, 20).unwrap(),
+            1,
+            88.0,
+            0.0,
+            0.0,
+        );
+        assert!(!twilight[0].polar_night);
+        assert!(twilight[0].duration().is_zero());
+        let spezia = compute_darkness(start, 1, 44.09727, 9.7737, 100.0);
+        assert!(!spezia[0].polar_night);
+    }
+
+    #[test]
+    fn test_compute_sun_times_twilight_order() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let times = compute_sun_moon_times(date, 44.09727, 9.7737, 100.0);
+        assert!(
+            to_minutes(times.astronomical_twilight_begin)
+                < to_minutes(times.nautical_twilight_begin)
+        );
+        assert!(to_minutes(times.nautical_twilight_begin) < to_minutes(times.civil_twilight_begin));
+        assert!(to_minutes(times.civil_twilight_begin) < to_minutes(times.sunrise));
+        assert!(to_minutes(times.sunset) < to_minutes(times.civil_twilight_end));
+        assert!(to_minutes(times.civil_twilight_end) < to_minutes(times.nautical_twilight_end));
+        assert!(
+            to_minutes(times.nautical_twilight_end) < to_minutes(times.astronomical_twilight_end)
+        );
+        // Solar noon in La Spezia is about 11:30 UTC in mid January
+        assert!((to_minutes(Some(times.solar_noon)) - 690.0).abs() < 3.0);
+    }
+
+    #[test]
+    fn test_compute_moon_phase() {
+        // Full moon on 2025-01-13 at 22:27 UTC, new moon on 2025-01-29 at 12:36 UTC
+        let full = julian_day(
+            NaiveDate::from_ymd_opt(2025, 1, 13)
+                .unwrap()
+                .and_hms_opt(22, 27, 0)
+                .unwrap()
+                .and_utc(),
+        );
+        let (phase_angle, illumination, age) = moon_phase(full);
+        assert!(phase_angle < 5.0);
+        assert!(illumination > 0.99);
+        assert!((age - SYNODIC_MONTH / 2.0).abs() < 0.5);
+        let new = julian_day(
+            NaiveDate::from_ymd_opt(2025, 1, 29)
+                .unwrap()
+                .and_hms_opt(12, 36, 0)
+                .unwrap()
+                .and_utc(),
+        );
+        let (phase_angle, illumination, age) = moon_phase(new);
+        assert!(phase_angle > 175.0);
+        assert!(illumination < 0.01);
+        assert!(!(0.5..SYNODIC_MONTH - 0.5).contains(&age));
+    }
+
+    #[test]
+    fn test_compute_moon_events() {
+        let date = NaiveDate::from_ymd_opt(2025, 1, 15).unwrap();
+        let times = compute_sun_moon_times(date, 44.09727, 9.7737, 0.0);
+        // Waning gibbous Moon: it transits after midnight, sets in the morning and rises in
+        // the evening (17:52 and 08:11 UTC according to timeanddate.com)
+        assert!(to_minutes(times.moon_transit) < to_minutes(times.moonset));
+        assert!((to_minutes(times.moonset) - (8.0 * 60.0 + 11.0)).abs() < 5.0);
+        assert!((to_minutes(times.moonrise) - (17.0 * 60.0 + 52.0)).abs() < 5.0);
+        assert!(times.moon_illumination > 0.9);
+    }
+
+    #[test]
+    fn test_compute_sun_times_polar_night() {
+        let date = NaiveDate::from_ymd_opt(2024, 12, 21).unwrap();
+        let times = compute_sun_moon_times(date, 78.22, 15.65, 0.0);
+        assert!(times.sunrise.is_none());
+        assert!(times.day_length.is_zero());
+        assert_eq!(times.timezone.name(), "Etc/GMT-1");
+    }
 }
@@ -10,7 +10,7 @@ use crate::scheduling_tui;
 use crate::settings_tui;
 
 const OPTIONS_MAIN_MENU: [&str; 3] = ["1", "2", "0"];
-const OPTIONS_SETTINGS_MENU: [&str; 4] = ["1", "2", "9", "0"];
+const OPTIONS_SETTINGS_MENU: [&str; 8] = ["1", "2", "3", "4", "5", "6", "9", "0"];
 
 // Funzione di validazione
 fn validate_main_menu_option(option: &str) -> bool {
@@ -70,6 +70,10 @@ pub fn settings_menu() -> Result<(), Box<dyn std::error::Error>> {
         "\n\n\nSettings Menu
 1. General
 2. Observatory
+3. Timezone
+4. Horizon profile
+5. Equipment
+6. Observatory profiles
 9. Back
 0. Quit"
     );
@@ -90,6 +94,22 @@ pub fn settings_menu() -> Result<(), Box<dyn std::error::Error>> {
             settings_tui::observatory_settings_menu()?;
             settings_menu()?
         }
+        "3" => {
+            settings_tui::timezone_menu()?;
+            settings_menu()?
+        }
+        "4" => {
+            settings_tui::horizon_menu()?;
+            settings_menu()?
+        }
+        "5" => {
+            settings_tui::equipment_menu()?;
+            settings_menu()?
+        }
+        "6" => {
+            settings_tui::observatory_profiles_menu()?;
+            settings_menu()?
+        }
         "9" => main_menu()?,
         _ => (),
     }
@@ -1,7 +1,8 @@
 use crate::settings::Settings;
+use anyhow::Result;
+use chrono::{DateTime, Duration, NaiveDate, Utc};
 use reqwest;
 use serde::{Deserialize, Serialize};
-use serde_json::Result;
 use serde_repr::{Deserialize_repr, Serialize_repr};
 use std::fmt;
 use std::fmt::Display;
@@ -67,6 +68,22 @@ pub struct ForecastResponse {
     pub dataseries: Vec<Forecast>,
 }
 
+impl ForecastResponse {
+    /// Returns the UTC time of the forecast run, parsed from `init` as YYYYMMDDHH
+    pub fn init_time(&self) -> Option<DateTime<Utc>> {
+        let date = NaiveDate::parse_from_str(self.init.get(..8)?, "%Y%m%d").ok()?;
+        let hour = self.init.get(8..10)?.parse::<u32>().ok()?;
+        Some(date.and_hms_opt(hour, 0, 0)?.and_utc())
+    }
+
+    /// Returns the UTC time of a forecast of the series
+    ///
+    /// * `forecast`: forecast of `dataseries`
+    pub fn time_of(&self, forecast: &Forecast) -> Option<DateTime<Utc>> {
+        Some(self.init_time()? + Duration::hours(forecast.timepoint as i64))
+    }
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize_repr, Serialize_repr)]
 #[repr(u8)]
 /// CloudCover enum
@@ -373,27 +390,402 @@ impl Display for Wind10mVelocity {
     }
 }
 
-/// Returns the string with full response
-fn get_forecast() -> String {
-    let settings = Settings::new().unwrap();
+/// 7timer products
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Product {
+    /// ASTRO, 3 days of 3-hour astronomical forecasts
+    Astro,
+    /// CIVIL, 8 days of 3-hour forecasts with precipitation amount
+    Civil,
+    /// CIVIL Light, 7 days of daily summaries
+    CivilLight,
+    /// TWO, 2 weeks of daily overviews
+    Two,
+    /// METEO, 3 days of 3-hour forecasts with vertical profiles
+    Meteo,
+}
+
+impl Product {
+    /// Returns the `product` parameter of the 7timer API
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            Product::Astro => "astro",
+            Product::Civil => "civil",
+            Product::CivilLight => "civillight",
+            Product::Two => "two",
+            Product::Meteo => "meteo",
+        }
+    }
+}
+
+impl Display for Product {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Deserialize_repr, Serialize_repr)]
+#[repr(u8)]
+/// PrecAmount enum, precipitation rate
+pub enum PrecAmount {
+    /// None
+    None = 0,
+    /// 0-0.25 mm/h
+    ZeroTwentyFive = 1,
+    /// 0.25-1 mm/h
+    One = 2,
+    /// 1-4 mm/h
+    Four = 3,
+    /// 4-10 mm/h
+    Ten = 4,
+    /// 10-16 mm/h
+    Sixteen = 5,
+    /// 16-30 mm/h
+    Thirty = 6,
+    /// 30-50 mm/h
+    Fifty = 7,
+    /// 50-75 mm/h
+    SeventyFive = 8,
+    /// Over 75 mm/h
+    OverSeventyFive = 9,
+}
+
+impl PrecAmount {
+    /// Returns a string representation of PrecAmount
+    pub const fn to_str(self) -> &'static str {
+        match self {
+            PrecAmount::None => "None",
+            PrecAmount::ZeroTwentyFive => "0-0.25 mm/h",
+            PrecAmount::One => "0.25-1 mm/h",
+            PrecAmount::Four => "1-4 mm/h",
+            PrecAmount::Ten => "4-10 mm/h",
+            PrecAmount::Sixteen => "10-16 mm/h",
+            PrecAmount::Thirty => "16-30 mm/h",
+            PrecAmount::Fifty => "30-50 mm/h",
+            PrecAmount::SeventyFive => "50-75 mm/h",
+            PrecAmount::OverSeventyFive => "Over 75 mm/h",
+        }
+    }
+}
+
+impl Display for PrecAmount {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(self.to_str())
+    }
+}
+
+/// Returns a readable description of a 7timer weather type, i.e. "Light rain" for
+/// "lightrainday"
+///
+/// * `weather`: weather type, with or without the day/night suffix of the civil product
+pub fn weather_description(weather: &str) -> String {
+    let code = weather
+        .strip_suffix("day")
+        .or_else(|| weather.strip_suffix("night"))
+        .unwrap_or(weather);
+    match code {
+        "clear" => "Clear",
+        "pcloudy" => "Partly cloudy",
+        "mcloudy" => "Cloudy",
+        "cloudy" => "Very cloudy",
+        "humid" => "Foggy",
+        "lightrain" => "Light rain",
+        "oshower" => "Occasional showers",
+        "ishower" => "Isolated showers",
+        "lightsnow" => "Light snow",
+        "rain" => "Rain",
+        "snow" => "Snow",
+        "rainsnow" => "Mixed",
+        "ts" => "Thunderstorm possible",
+        "tsrain" => "Thunderstorm",
+        _ => code,
+    }
+    .to_string()
+}
+
+/// Deserializes a relative humidity given as "87%" or as a number
+fn deserialize_percent<'de, D>(deserializer: D) -> std::result::Result<i32, D::Error>
+where
+    D: serde::Deserializer<'de>,
+{
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Percent {
+        Number(i32),
+        Text(String),
+    }
+    match Percent::deserialize(deserializer)? {
+        Percent::Number(value) => Ok(value),
+        Percent::Text(text) => text
+            .trim()
+            .trim_end_matches('%')
+            .parse()
+            .map_err(serde::de::Error::custom),
+    }
+}
+
+/// Returns the date of a 7timer daily forecast, given as YYYYMMDD
+fn parse_date(date: u32) -> Option<NaiveDate> {
+    NaiveDate::from_ymd_opt((date / 10000) as i32, date / 100 % 100, date % 100)
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// Generic 7timer response data structure
+///
+/// * `product`: product type
+/// * `init`: Initial reference time
+/// * `dataseries`: an array of forecasts of the product
+pub struct ProductResponse<T> {
+    /// Product type
+    pub product: String,
+    /// Initial reference time
+    pub init: String,
+    /// Data array with forecast values
+    pub dataseries: Vec<T>,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// CIVIL forecast data structure
+///
+/// * `timepoint`: time of the forecast
+/// * `cloud_cover`: Cloud coverage
+/// * `lifted_index`: Lifted Index
+/// * `prec_type`: Precipitation type
+/// * `prec_amount`: Precipitation rate
+/// * `temp2m`: Temperature at 2 m of altitude
+/// * `rh2m`: Relative humidity at 2 m of altitude
+/// * `wind10m`: Wind at 10 m of altitude
+/// * `weather`: Weather type
+pub struct CivilForecast {
+    /// Time of the forecast (in hours from init)
+    pub timepoint: i32,
+    #[serde(rename = "cloudcover")]
+    /// Cloud coverage as CloudCover enum
+    pub cloud_cover: CloudCover,
+    /// Lifted Index as LiftedIndex enum
+    pub lifted_index: LiftedIndex,
+    /// Precipitation type
+    pub prec_type: String,
+    /// Precipitation rate as PrecAmount enum
+    pub prec_amount: PrecAmount,
+    /// Temperature at 2 m
+    pub temp2m: i8,
+    #[serde(deserialize_with = "deserialize_percent")]
+    /// Relative humidity at 2 m in %
+    pub rh2m: i32,
+    /// Wind at 10 m as Wind10m data structure
+    pub wind10m: Wind10m,
+    /// Weather type, i.e. "lightrainday"
+    pub weather: String,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// Daily temperature range
+///
+/// * `max`: maximum temperature
+/// * `min`: minimum temperature
+pub struct TemperatureRange {
+    /// Maximum temperature
+    pub max: i8,
+    /// Minimum temperature
+    pub min: i8,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// CIVIL Light daily forecast data structure
+///
+/// * `date`: date as YYYYMMDD
+/// * `weather`: Weather type
+/// * `temp2m`: Temperature range at 2 m of altitude
+/// * `wind10m_max`: Maximum wind at 10 m of altitude
+pub struct CivilLightForecast {
+    /// Date as YYYYMMDD
+    pub date: u32,
+    /// Weather type, i.e. "lightrain"
+    pub weather: String,
+    /// Temperature range at 2 m
+    pub temp2m: TemperatureRange,
+    /// Maximum wind speed at 10 m as Wind10mVelocity enum
+    pub wind10m_max: Wind10mVelocity,
+}
+
+impl CivilLightForecast {
+    /// Returns the date of the forecast
+    pub fn date(&self) -> Option<NaiveDate> {
+        parse_date(self.date)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// TWO daily overview data structure
+///
+/// * `date`: date as YYYYMMDD
+/// * `cloud_cover`: Cloud coverage
+/// * `temp2m`: Temperature range at 2 m of altitude
+/// * `prec_type`: Precipitation type
+/// * `prec_amount`: Precipitation rate
+/// * `wind10m_max`: Maximum wind at 10 m of altitude
+/// * `weather`: Weather type
+pub struct TwoForecast {
+    /// Date as YYYYMMDD
+    pub date: u32,
+    #[serde(rename = "cloudcover")]
+    /// Cloud coverage as CloudCover enum
+    pub cloud_cover: CloudCover,
+    /// Temperature range at 2 m
+    pub temp2m: TemperatureRange,
+    /// Precipitation type
+    pub prec_type: String,
+    /// Precipitation rate as PrecAmount enum
+    pub prec_amount: PrecAmount,
+    /// Maximum wind speed at 10 m as Wind10mVelocity enum
+    pub wind10m_max: Wind10mVelocity,
+    /// Weather type, i.e. "pcloudy"
+    pub weather: String,
+}
+
+impl TwoForecast {
+    /// Returns the date of the forecast
+    pub fn date(&self) -> Option<NaiveDate> {
+        parse_date(self.date)
+    }
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// Relative humidity at a pressure level
+///
+/// * `layer`: pressure level, i.e. "850mb"
+/// * `rh`: relative humidity
+pub struct HumidityLayer {
+    /// Pressure level, i.e. "850mb"
+    pub layer: String,
+    /// Relative humidity class, as RH2m
+    pub rh: i8,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// Wind at a pressure level
+///
+/// * `layer`: pressure level, i.e. "850mb"
+/// * `direction`: direction
+/// * `speed`: speed
+pub struct WindLayer {
+    /// Pressure level, i.e. "850mb"
+    pub layer: String,
+    /// Direction as cardinal point, i.e. NW, E...
+    pub direction: String,
+    /// Speed in m/s
+    pub speed: i32,
+}
+
+#[derive(Debug, Deserialize, Serialize)]
+/// METEO forecast data structure
+///
+/// Cloud layers are percentages, -9999 when not available.
+///
+/// * `timepoint`: time of the forecast
+/// * `cloud_cover`: Cloud coverage
+/// * `high_cloud`: High cloud coverage
+/// * `mid_cloud`: Mid cloud coverage
+/// * `low_cloud`: Low cloud coverage
+/// * `rh_profile`: Relative humidity at pressure levels
+/// * `wind_profile`: Wind at pressure levels
+/// * `temp2m`: Temperature at 2 m of altitude
+/// * `lifted_index`: Lifted Index
+/// * `rh2m`: Relative humidity at 2 m of altitude
+/// * `msl_pressure`: Mean sea level pressure
+/// * `prec_type`: Precipitation type
+/// * `prec_amount`: Precipitation rate
+/// * `snow_depth`: Snow depth
+/// * `wind10m`: Wind at 10 m of altitude
+pub struct MeteoForecast {
+    /// Time of the forecast (in hours from init)
+    pub timepoint: i32,
+    #[serde(rename = "cloudcover")]
+    /// Cloud coverage as CloudCover enum
+    pub cloud_cover: CloudCover,
+    #[serde(rename = "highcloud")]
+    /// High cloud coverage in %
+    pub high_cloud: i32,
+    #[serde(rename = "midcloud")]
+    /// Mid cloud coverage in %
+    pub mid_cloud: i32,
+    #[serde(rename = "lowcloud")]
+    /// Low cloud coverage in %
+    pub low_cloud: i32,
+    /// Relative humidity from the lowest pressure level up
+    pub rh_profile: Vec<HumidityLayer>,
+    /// Wind from the lowest pressure level up
+    pub wind_profile: Vec<WindLayer>,
+    /// Temperature at 2 m
+    pub temp2m: i8,
+    /// Lifted Index as LiftedIndex enum
+    pub lifted_index: LiftedIndex,
+    #[serde(deserialize_with = "deserialize_percent")]
+    /// Relative humidity at 2 m in %
+    pub rh2m: i32,
+    /// Mean sea level pressure in hPa
+    pub msl_pressure: i32,
+    /// Precipitation type
+    pub prec_type: String,
+    /// Precipitation rate as PrecAmount enum
+    pub prec_amount: PrecAmount,
+    /// Snow depth class
+    pub snow_depth: i32,
+    /// Wind at 10 m as Wind10m data structure
+    pub wind10m: Wind10m,
+}
+
+/// CIVIL response, 8 days of 3-hour forecasts
+pub type CivilResponse = ProductResponse<CivilForecast>;
+/// CIVIL Light response, 7 days of daily summaries
+pub type CivilLightResponse = ProductResponse<CivilLightForecast>;
+/// TWO response, 2 weeks of daily overviews
+pub type TwoResponse = ProductResponse<TwoForecast>;
+/// METEO response, 3 days of 3-hour forecasts with vertical profiles
+pub type MeteoResponse = ProductResponse<MeteoForecast>;
+
+/// Returns the string with full response of a 7timer product
+///
+/// * `product`: 7timer product
+fn get_forecast(product: Product) -> Result<String> {
+    let settings = Settings::new()?;
     let url: reqwest::Url = reqwest::Url::parse_with_params(
         "http://www.7timer.info/bin/api.pl",
         [
             ("lat", settings.get_latitude().to_string()),
             ("lon", settings.get_longitude().to_string()),
-            ("product", "astro".to_string()),
+            ("product", product.to_string()),
             ("output", "json".to_string()),
         ],
-    )
-    .unwrap();
-    let result = reqwest::blocking::get(url).unwrap().text();
-    result.unwrap()
+    )?;
+    Ok(reqwest::blocking::get(url)?.error_for_status()?.text()?)
 }
 
 /// Returns the ForecastResponse struct with data
 pub fn prepare_data() -> Result<ForecastResponse> {
-    let response: String = get_forecast();
-    serde_json::from_str(&response)
+    let response: String = get_forecast(Product::Astro)?;
+    Ok(serde_json::from_str(&response)?)
+}
+
+/// Returns the CIVIL forecast
+pub fn prepare_civil_data() -> Result<CivilResponse> {
+    Ok(serde_json::from_str(&get_forecast(Product::Civil)?)?)
+}
+
+/// Returns the CIVIL Light 7-day outlook
+pub fn prepare_civillight_data() -> Result<CivilLightResponse> {
+    Ok(serde_json::from_str(&get_forecast(Product::CivilLight)?)?)
+}
+
+/// Returns the TWO 2-week overview
+pub fn prepare_two_data() -> Result<TwoResponse> {
+    Ok(serde_json::from_str(&get_forecast(Product::Two)?)?)
+}
+
+/// Returns the METEO forecast with vertical profiles
+pub fn prepare_meteo_data() -> Result<MeteoResponse> {
+    Ok(serde_json::from_str(&get_forecast(Product::Meteo)?)?)
 }
 
 #[cfg(test)]
@@ -402,7 +794,7 @@ mod test {
 
     #[test]
     fn test_get_forecast() {
-        assert!(get_forecast().contains("astro"));
+        assert!(get_forecast(Product::Astro).unwrap().contains("astro"));
     }
 
     #[test]
@@ -410,4 +802,44 @@ mod test {
         let data = prepare_data().unwrap();
         assert_eq!(data.product, "astro");
     }
+
+    fn read_example<T: serde::de::DeserializeOwned>(product: Product) -> T {
+        let path = format!("response_examples/7timer_{}.json", product);
+        serde_json::from_str(&std::fs::read_to_string(path).unwrap()).unwrap()
+    }
+
+    #[test]
+    fn test_civil_products() {
+        let civil: CivilResponse = read_example(Product::Civil);
+        assert_eq!(civil.product, "civil");
+        assert_eq!(civil.dataseries[0].prec_amount, PrecAmount::One);
+        assert_eq!(civil.dataseries[0].rh2m, 87);
+        assert_eq!(
+            weather_description(&civil.dataseries[0].weather),
+            "Light rain"
+        );
+        let outlook: CivilLightResponse = read_example(Product::CivilLight);
+        assert_eq!(outlook.dataseries.len(), 7);
+        assert_eq!(
+            outlook.dataseries[0].date(),
+            NaiveDate::from_ymd_opt(2024, 3, 28)
+        );
+        assert_eq!(outlook.dataseries[6].temp2m.min, 11);
+        assert_eq!(outlook.dataseries[6].wind10m_max, Wind10mVelocity::Ten);
+        let two: TwoResponse = read_example(Product::Two);
+        assert_eq!(two.dataseries[1].cloud_cover, CloudCover::ThirtyOne);
+        assert_eq!(two.dataseries[0].prec_amount.to_str(), "1-4 mm/h");
+    }
+
+    #[test]
+    fn test_meteo_product() {
+        let meteo: MeteoResponse = read_example(Product::Meteo);
+        let forecast = &meteo.dataseries[1];
+        assert_eq!(forecast.mid_cloud, -9999);
+        assert_eq!(forecast.rh2m, 75);
+        assert_eq!(forecast.rh_profile.len(), 16);
+        assert_eq!(forecast.rh_profile[0].layer, "950mb");
+        assert_eq!(forecast.wind_profile[2].speed, 7);
+        assert_eq!(forecast.wind10m.speed, Wind10mVelocity::Three);
+    }
 }
@@ -0,0 +1,504 @@
+//! # Weather Providers
+//!
+//! Library for weather forecasts from several providers, normalized to hourly values
+//!
+//! Every provider implements `WeatherProvider`, producing `HourlyForecast` values with cloud
+//! cover and humidity in percent, wind in m/s, temperatures in °C and precipitation in mm/h.
+//! `hourly_forecast` tries the providers in order, so a provider down does not leave us
+//! without forecast.
+//!
+//! * `SevenTimer`: 7timer astro product, 3-hour bins
+//! * `OpenMeteo`: Open-Meteo forecast API
+//! * `MetNorway`: MET Norway locationforecast API
+//!
+//! ```rust
+//! use asteroid_tui::weather_providers::{OpenMeteo, WeatherProvider};
+//! let response = std::fs::read_to_string("response_examples/open_meteo.json").unwrap();
+//! let forecast = OpenMeteo.parse(&response).unwrap();
+//! assert_eq!(forecast[0].cloud_cover, 12.0);
+//! assert_eq!(forecast[0].wind_speed, 2.4);
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::weather::ForecastResponse;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, NaiveDateTime, Utc};
+use serde::{Deserialize, Serialize};
+
+/// User agent sent to the providers, MET Norway refuses anonymous requests
+const USER_AGENT: &str = concat!(
+    "asteroid_tui/",
+    env!("CARGO_PKG_VERSION"),
+    " github.com/ziriuz84/asteroid_tui"
+);
+
+/// Normalized hourly forecast structure
+///
+/// * `time`: UTC time of the forecast
+/// * `cloud_cover`: total cloud cover in percent
+/// * `humidity`: relative humidity at 2 m in percent
+/// * `wind_speed`: wind speed at 10 m in m/s
+/// * `temperature`: temperature at 2 m in °C
+/// * `dew_point`: dew point at 2 m in °C
+/// * `precipitation`: precipitation in mm/h, none if the provider gives no amount
+#[derive(Debug, Clone, Copy, PartialEq, Deserialize, Serialize)]
+pub struct HourlyForecast {
+    /// UTC time of the forecast
+    pub time: DateTime<Utc>,
+    /// Total cloud cover in percent
+    pub cloud_cover: f64,
+    /// Relative humidity at 2 m in percent
+    pub humidity: f64,
+    /// Wind speed at 10 m in m/s
+    pub wind_speed: f64,
+    /// Temperature at 2 m in °C
+    pub temperature: f64,
+    /// Dew point at 2 m in °C
+    pub dew_point: f64,
+    /// Precipitation in mm/h, none if the provider gives no amount
+    pub precipitation: Option<f64>,
+}
+
+/// Weather forecast provider
+pub trait WeatherProvider {
+    /// Returns the provider name
+    fn name(&self) -> &'static str;
+
+    /// Returns the request URL for a location
+    ///
+    /// * `latitude`: latitude in degrees
+    /// * `longitude`: longitude in degrees, east positive
+    /// * `altitude`: altitude in meters
+    fn url(&self, latitude: f64, longitude: f64, altitude: f64) -> Result<reqwest::Url>;
+
+    /// Parses the provider response into hourly forecasts
+    ///
+    /// * `response`: response body
+    fn parse(&self, response: &str) -> Result<Vec<HourlyForecast>>;
+
+    /// Downloads and parses the forecast for a location
+    ///
+    /// * `latitude`: latitude in degrees
+    /// * `longitude`: longitude in degrees, east positive
+    /// * `altitude`: altitude in meters
+    fn forecast(
+        &self,
+        latitude: f64,
+        longitude: f64,
+        altitude: f64,
+    ) -> Result<Vec<HourlyForecast>> {
+        let response = reqwest::blocking::Client::builder()
+            .user_agent(USER_AGENT)
+            .build()?
+            .get(self.url(latitude, longitude, altitude)?)
+            .send()?
+            .error_for_status()?
+            .text()?;
+        self.parse(&response)
+    }
+}
+
+/// Returns the dew point from temperature and relative humidity, with the Magnus formula
+///
+/// * `temperature`: temperature in °C
+/// * `humidity`: relative humidity in percent
+pub fn dew_point(temperature: f64, humidity: f64) -> f64 {
+    let (b, c) = (17.62, 243.12);
+    let gamma = (humidity.max(1.0) / 100.0).ln() + b * temperature / (c + temperature);
+    c * gamma / (b - gamma)
+}
+
+/// 7timer astro product provider
+///
+/// Cloud cover, humidity and wind classes are converted to the middle of their range, each
+/// 3-hour bin is repeated for its three hours. Only the precipitation type is given, so the
+/// amount is zero without precipitation and unknown otherwise.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct SevenTimer;
+
+impl SevenTimer {
+    /// Returns the middle of a cloud cover class in percent
+    ///
+    /// * `class`: 7timer class, from 1 to 9
+    fn cloud_cover(class: u8) -> f64 {
+        [3.0, 12.5, 25.0, 37.5, 50.0, 62.5, 75.0, 87.5, 97.0][(class.clamp(1, 9) - 1) as usize]
+    }
+
+    /// Returns the middle of a relative humidity class in percent
+    ///
+    /// * `class`: 7timer class, from -4 to 16
+    fn humidity(class: i8) -> f64 {
+        match class {
+            16.. => 100.0,
+            15 => 97.0,
+            class => (class.max(-4) + 4) as f64 * 5.0 + 2.5,
+        }
+    }
+
+    /// Returns the middle of a wind speed class in m/s
+    ///
+    /// * `class`: 7timer class, from 1 to 8
+    fn wind_speed(class: u8) -> f64 {
+        [0.15, 1.85, 5.7, 9.4, 14.0, 20.85, 28.55, 35.0][(class.clamp(1, 8) - 1) as usize]
+    }
+}
+
+impl WeatherProvider for SevenTimer {
+    fn name(&self) -> &'static str {
+        "7timer"
+    }
+
+    fn url(&self, latitude: f64, longitude: f64, _altitude: f64) -> Result<reqwest::Url> {
+        Ok(reqwest::Url::parse_with_params(
+            "http://www.7timer.info/bin/api.pl",
+            [
+                ("lat", latitude.to_string()),
+                ("lon", longitude.to_string()),
+                ("product", "astro".to_string()),
+                ("output", "json".to_string()),
+            ],
+        )?)
+    }
+
+    fn parse(&self, response: &str) -> Result<Vec<HourlyForecast>> {
+        let response: ForecastResponse = serde_json::from_str(response)?;
+        let init = response
+            .init_time()
+            .ok_or_else(|| anyhow!("Invalid init time \"{}\"", response.init))?;
+        Ok(response
+            .dataseries
+            .iter()
+            .flat_map(|forecast| {
+                let temperature = forecast.temp2m as f64;
+                let humidity = Self::humidity(forecast.rh2m as i8);
+                let hourly = HourlyForecast {
+                    time: init + Duration::hours(forecast.timepoint as i64),
+                    cloud_cover: Self::cloud_cover(forecast.cloud_cover as u8),
+                    humidity,
+                    wind_speed: Self::wind_speed(forecast.wind10m.speed as u8),
+                    temperature,
+                    dew_point: dew_point(temperature, humidity),
+                    precipitation: (forecast.prec_type == "none").then_some(0.0),
+                };
+                (0..3).map(move |hour| HourlyForecast {
+                    time: hourly.time + Duration::hours(hour),
+                    ..hourly
+                })
+            })
+            .collect())
+    }
+}
+
+/// Open-Meteo forecast API provider
+///
+/// Hours with a missing value are skipped.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct OpenMeteo;
+
+/// Hourly values of an Open-Meteo response, missing values are null
+#[derive(Debug, Deserialize)]
+struct OpenMeteoHourly {
+    time: Vec<String>,
+    cloud_cover: Vec<Option<f64>>,
+    relative_humidity_2m: Vec<Option<f64>>,
+    wind_speed_10m: Vec<Option<f64>>,
+    temperature_2m: Vec<Option<f64>>,
+    dew_point_2m: Vec<Option<f64>>,
+    precipitation: Vec<Option<f64>>,
+}
+
+/// Open-Meteo response
+#[derive(Debug, Deserialize)]
+struct OpenMeteoResponse {
+    hourly: OpenMeteoHourly,
+}
+
+impl WeatherProvider for OpenMeteo {
+    fn name(&self) -> &'static str {
+        "Open-Meteo"
+    }
+
+    fn url(&self, latitude: f64, longitude: f64, altitude: f64) -> Result<reqwest::Url> {
+        Ok(reqwest::Url::parse_with_params(
+            "https://api.open-meteo.com/v1/forecast",
+            [
+                ("latitude", latitude.to_string()),
+                ("longitude", longitude.to_string()),
+                ("elevation", altitude.to_string()),
+                (
+                    "hourly",
+                    "cloud_cover,relative_humidity_2m,wind_speed_10m,temperature_2m,dew_point_2m,precipitation"
+                        .to_string(),
+                ),
+                ("wind_speed_unit", "ms".to_string()),
+                ("timezone", "UTC".to_string()),
+                ("forecast_days", "3".to_string()),
+            ],
+        )?)
+    }
+
+    fn parse(&self, response: &str) -> Result<Vec<HourlyForecast>> {
+        let hourly = serde_json::from_str::<OpenMeteoResponse>(response)?.hourly;
+        let mut forecast = Vec::new();
+        for (index, time) in hourly.time.iter().enumerate() {
+            let value = |values: &[Option<f64>]| values.get(index).copied().flatten();
+            let time = NaiveDateTime::parse_from_str(time, "%Y-%m-%dT%H:%M")
+                .map_err(|_| anyhow!("Invalid time \"{}\"", time))?
+                .and_utc();
+            if let (
+                Some(cloud_cover),
+                Some(humidity),
+                Some(wind_speed),
+                Some(temperature),
+                Some(dew_point),
+            ) = (
+                value(&hourly.cloud_cover),
+                value(&hourly.relative_humidity_2m),
+                value(&hourly.wind_speed_10m),
+                value(&hourly.temperature_2m),
+                value(&hourly.dew_point_2m),
+            ) {
+                forecast.push(HourlyForecast {
+                    time,
+                    cloud_cover,
+                    humidity,
+                    wind_speed,
+                    temperature,
+                    dew_point,
+                    precipitation: value(&hourly.precipitation),
+                });
+            }
+        }
+        Ok(forecast)
+    }
+}
+
+/// MET Norway locationforecast API provider
+///
+/// Forecasts are hourly for about 60 hours, then every 6 hours; 6-hour precipitation is
+/// spread evenly.
+#[derive(Debug, Clone, Copy, Default)]
+pub struct MetNorway;
+
+/// Instant values of a MET Norway time step
+#[derive(Debug, Deserialize)]
+struct MetNorwayDetails {
+    air_temperature: f64,
+    cloud_area_fraction: f64,
+    dew_point_temperature: f64,
+    relative_humidity: f64,
+    wind_speed: f64,
+}
+
+/// Precipitation of a MET Norway period
+#[derive(Debug, Deserialize)]
+struct MetNorwayPeriodDetails {
+    precipitation_amount: Option<f64>,
+}
+
+/// MET Norway forecast period
+#[derive(Debug, Deserialize)]
+struct MetNorwayPeriod {
+    details: MetNorwayPeriodDetails,
+}
+
+/// MET Norway instant forecast
+#[derive(Debug, Deserialize)]
+struct MetNorwayInstant {
+    details: MetNorwayDetails,
+}
+
+/// MET Norway time step data
+#[derive(Debug, Deserialize)]
+struct MetNorwayData {
+    instant: MetNorwayInstant,
+    next_1_hours: Option<MetNorwayPeriod>,
+    next_6_hours: Option<MetNorwayPeriod>,
+}
+
+/// MET Norway time step
+#[derive(Debug, Deserialize)]
+struct MetNorwayStep {
+    time: DateTime<Utc>,
+    data: MetNorwayData,
+}
+
+/// MET Norway properties
+#[derive(Debug, Deserialize)]
+struct MetNorwayProperties {
+    timeseries: Vec<MetNorwayStep>,
+}
+
+/// MET Norway response
+#[derive(Debug, Deserialize)]
+struct MetNorwayResponse {
+    properties: MetNorwayProperties,
+}
+
+impl WeatherProvider for MetNorway {
+    fn name(&self) -> &'static str {
+        "MET Norway"
+    }
+
+    /// Coordinates are rounded to 4 decimals, as required by the terms of service
+    fn url(&self, latitude: f64, longitude: f64, altitude: f64) -> Result<reqwest::Url> {
+        Ok(reqwest::Url::parse_with_params(
+            "https://api.met.no/weatherapi/locationforecast/2.0/complete",
+            [
+                ("lat", format!("{:.4}", latitude)),
+                ("lon", format!("{:.4}", longitude)),
+                ("altitude", format!("{:.0}", altitude)),
+            ],
+        )?)
+    }
+
+    fn parse(&self, response: &str) -> Result<Vec<HourlyForecast>> {
+        let response: MetNorwayResponse = serde_json::from_str(response)?;
+        Ok(response
+            .properties
+            .timeseries
+            .iter()
+            .map(|step| {
+                let details = &step.data.instant.details;
+                let precipitation = match (&step.data.next_1_hours, &step.data.next_6_hours) {
+                    (Some(period), _) => period.details.precipitation_amount,
+                    (None, Some(period)) => period
+                        .details
+                        .precipitation_amount
+                        .map(|amount| amount / 6.0),
+                    (None, None) => None,
+                };
+                HourlyForecast {
+                    time: step.time,
+                    cloud_cover: details.cloud_area_fraction,
+                    humidity: details.relative_humidity,
+                    wind_speed: details.wind_speed,
+                    temperature: details.air_temperature,
+                    dew_point: details.dew_point_temperature,
+                    precipitation,
+                }
+            })
+            .collect())
+    }
+}
+
+/// Returns all providers, in the order they are tried
+pub fn providers() -> Vec<Box<dyn WeatherProvider>> {
+    vec![
+        Box::new(SevenTimer),
+        Box::new(OpenMeteo),
+        Box::new(MetNorway),
+    ]
+}
+
+/// Returns the hourly forecast of the first provider answering, with its name
+///
+/// * `latitude`: latitude in degrees
+/// * `longitude`: longitude in degrees, east positive
+/// * `altitude`: altitude in meters
+pub fn hourly_forecast(
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> Result<(&'static str, Vec<HourlyForecast>)> {
+    first_forecast(providers(), latitude, longitude, altitude)
+}
+
+/// Returns the hourly forecast of the first provider answering other than 7timer, with its
+/// name, for when the 7timer astro forecast is not available
+///
+/// * `latitude`: latitude in degrees
+/// * `longitude`: longitude in degrees, east positive
+/// * `altitude`: altitude in meters
+pub fn fallback_forecast(
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> Result<(&'static str, Vec<HourlyForecast>)> {
+    let providers = providers()
+        .into_iter()
+        .filter(|provider| provider.name() != SevenTimer.name())
+        .collect();
+    first_forecast(providers, latitude, longitude, altitude)
+}
+
+/// Returns the hourly forecast of the first of the providers answering, with its name
+///
+/// * `providers`: providers, in the order they are tried
+/// * `latitude`: latitude in degrees
+/// * `longitude`: longitude in degrees, east positive
+/// * `altitude`: altitude in meters
+fn first_forecast(
+    providers: Vec<Box<dyn WeatherProvider>>,
+    latitude: f64,
+    longitude: f64,
+    altitude: f64,
+) -> Result<(&'static str, Vec<HourlyForecast>)> {
+    let mut errors = Vec::new();
+    for provider in providers {
+        match provider.forecast(latitude, longitude, altitude) {
+            Ok(forecast) if !forecast.is_empty() => return Ok((provider.name(), forecast)),
+            Ok(_) => errors.push(format!("{}: empty forecast", provider.name())),
+            Err(error) => errors.push(format!("{}: {}", provider.name(), error)),
+        }
+    }
+    Err(anyhow!(
+        "No weather provider answered ({})",
+        errors.join("; ")
+    ))
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    fn fixture(name: &str) -> String {
+        std::fs::read_to_string(format!("response_examples/{}", name)).unwrap()
+    }
+
+    #[test]
+    fn test_seven_timer() {
+        let forecast = SevenTimer.parse(&fixture("7timer.json")).unwrap();
+        let first = &forecast[0];
+        assert_eq!(first.time.to_rfc3339(), "2024-03-27T21:00:00+00:00");
+        assert_eq!(first.cloud_cover, 97.0);
+        assert_eq!(first.humidity, 77.5);
+        assert_eq!(first.wind_speed, 1.85);
+        assert_eq!(first.temperature, 29.0);
+        assert!((first.dew_point - 24.7).abs() < 0.1);
+        assert_eq!(first.precipitation, Some(0.0));
+        assert_eq!(forecast[2].time, first.time + Duration::hours(2));
+        assert_eq!(forecast[3].precipitation, None);
+        assert!(SevenTimer.parse("{}").is_err());
+    }
+
+    #[test]
+    fn test_open_meteo() {
+        let forecast = OpenMeteo.parse(&fixture("open_meteo.json")).unwrap();
+        // The hour without cloud cover is skipped
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast[2].time.to_rfc3339(), "2025-01-15T03:00:00+00:00");
+        assert_eq!(forecast[2].precipitation, Some(1.2));
+        assert_eq!(forecast[1].dew_point, 1.7);
+        let url = OpenMeteo.url(44.1, 9.78, 12.0).unwrap();
+        assert!(url.as_str().contains("wind_speed_unit=ms"));
+    }
+
+    #[test]
+    fn test_met_norway() {
+        let forecast = MetNorway.parse(&fixture("met_no.json")).unwrap();
+        assert_eq!(forecast.len(), 3);
+        assert_eq!(forecast[0].cloud_cover, 3.1);
+        assert_eq!(forecast[1].humidity, 72.0);
+        assert!((forecast[2].precipitation.unwrap() - 0.8).abs() < 1e-9);
+        let url = MetNorway.url(44.097271, 9.773712, 12.3).unwrap();
+        assert!(url.as_str().ends_with("lat=44.0973&lon=9.7737&altitude=12"));
+    }
+
+    #[test]
+    fn test_dew_point() {
+        assert!((dew_point(20.0, 50.0) - 9.3).abs() < 0.1);
+        assert!((dew_point(10.0, 100.0) - 10.0).abs() < 1e-9);
+    }
+}
@@ -0,0 +1,286 @@
+//! # What's Up
+//!
+//! Library computing the MPC "What's Up" list locally, from the orbits in the local catalogue
+//!
+//! The result is the same list of `PossibleTarget` returned by the MPC web form: the
+//! brightest objects that, during the requested interval, reach the minimum altitude and
+//! respect the solar and lunar elongation limits. Positions are geocentric J2000, without
+//! light-time correction, so they match the MPC ones to about an arcminute. MPCORB.DAT has
+//! no comets, so comets are still available only from the web form.
+//!
+//! ```rust,no_run
+//! use asteroid_tui::observing_target_list::WhatsUpParams;
+//! use asteroid_tui::whats_up;
+//! let targets = whats_up::local_whats_up_response(&WhatsUpParams::default()).unwrap();
+//! ```
+
+#![warn(missing_docs)]
+
+use crate::catalogue::Catalogue;
+use crate::coordinates::{self, julian_day, Observer};
+use crate::designation::Designation;
+use crate::observing_target_list::{
+    apply_horizon_limits, ObjectType, PossibleTarget, TargetPosition, WhatsUpParams,
+};
+use crate::orbit::{self, Orbit, Vector};
+use crate::settings::Settings;
+use crate::sun_moon_times::moon_position;
+use anyhow::{anyhow, Result};
+use chrono::{DateTime, Duration, Utc};
+
+/// Time between two altitude samples in minutes
+const ALTITUDE_STEP: i64 = 10;
+
+/// Geocentric data of an object at a time
+///
+/// * `ra`: right ascension in degrees
+/// * `dec`: declination in degrees
+/// * `magnitude`: visual magnitude
+/// * `solar_elongation`: solar elongation in degrees
+/// * `lunar_elongation`: lunar elongation in degrees
+struct Apparition {
+    ra: f64,
+    dec: f64,
+    magnitude: f64,
+    solar_elongation: f64,
+    lunar_elongation: f64,
+}
+
+/// Returns the geocentric J2000 unit vector of the Moon
+///
+/// * `jd`: Julian Day
+fn moon_direction(jd: f64) -> Vector {
+    let moon = moon_position(jd);
+    let (ra, dec) = (
+        moon.right_ascension.to_radians(),
+        moon.declination.to_radians(),
+    );
+    [dec.cos() * ra.cos(), dec.cos() * ra.sin(), dec.sin()]
+}
+
+/// Returns the geocentric data of an orbit at `jd`
+///
+/// * `orbit`: object orbit
+/// * `jd`: Julian Day
+/// * `earth`: heliocentric ecliptic position of the Earth at `jd`
+/// * `moon`: geocentric equatorial direction of the Moon at `jd`
+fn apparition(orbit: &Orbit, jd: f64, earth: Vector, moon: Vector) -> Apparition {
+    let heliocentric = orbit.heliocentric_position(jd);
+    let geocentric = [
+        heliocentric[0] - earth[0],
+        heliocentric[1] - earth[1],
+        heliocentric[2] - earth[2],
+    ];
+    let sun = [-earth[0], -earth[1], -earth[2]];
+    let equatorial = orbit::ecliptic_to_equatorial(geocentric);
+    let (ra, dec, earth_distance) = orbit::to_spherical(equatorial);
+    let phase_angle = orbit::angle(heliocentric, geocentric);
+    Apparition {
+        ra,
+        dec,
+        magnitude: orbit.magnitude(orbit::norm(heliocentric), earth_distance, phase_angle),
+        solar_elongation: orbit::angle(geocentric, sun),
+        lunar_elongation: orbit::angle(equatorial, moon),
+    }
+}
+
+/// Returns the target position at `time`, interpolating RA and Dec between begin and end
+///
+/// * `begin`: data at the start of the interval
+/// * `end`: data at the end of the interval
+/// * `fraction`: fraction of the interval, from 0 to 1
+/// * `time`: UTC time
+/// * `observer`: observer location
+fn interpolate(
+    begin: &Apparition,
+    end: &Apparition,
+    fraction: f64,
+    time: DateTime<Utc>,
+    observer: &Observer,
+) -> TargetPosition {
+    let delta_ra = (end.ra - begin.ra + 180.0).rem_euclid(360.0) - 180.0;
+    let ra = (begin.ra + delta_ra * fraction).rem_euclid(360.0);
+    let dec = begin.dec + (end.dec - begin.dec) * fraction;
+    TargetPosition {
+        time,
+        ra,
+        dec,
+        altitude: coordinates::apparent_horizontal(ra, dec, time, observer).altitude,
+    }
+}
+
+/// Computes the what's up list from a set of orbits
+///
+/// * `orbits`: orbits of the candidate objects
+/// * `params`: WhatsupParams struct with all requested parameters
+/// * `observer`: observer location
+pub fn compute_whats_up(
+    orbits: &[Orbit],
+    params: &WhatsUpParams,
+    observer: &Observer,
+) -> Result<Vec<PossibleTarget>> {
+    if params.object_type() == ObjectType::Comet {
+        return Err(anyhow!(
+            "Comets are not in MPCORB.DAT, use the MPC website to list them"
+        ));
+    }
+    let start = params.start();
+    let end = start + Duration::hours(params.duration().into());
+    let (start_jd, end_jd) = (julian_day(start), julian_day(end));
+    let (earth_start, earth_end) = (
+        orbit::earth_position(start_jd),
+        orbit::earth_position(end_jd),
+    );
+    let (moon_start, moon_end) = (moon_direction(start_jd), moon_direction(end_jd));
+    let steps = params.duration() as i64 * 60 / ALTITUDE_STEP;
+    let mut targets: Vec<(f64, PossibleTarget)> = orbits
+        .iter()
+        .filter(|orbit| params.object_type() != ObjectType::Neo || orbit.is_neo())
+        .filter_map(|orbit| {
+            let begin = apparition(orbit, start_jd, earth_start, moon_start);
+            if !params.is_bright_enough(begin.magnitude as f32) {
+                return None;
+            }
+            if begin.solar_elongation < params.solar_elong() as f64
+                || begin.lunar_elongation < params.lunar_elong() as f64
+            {
+                return None;
+            }
+            let last = apparition(orbit, end_jd, earth_end, moon_end);
+            let positions: Vec<TargetPosition> = (0..=steps)
+                .map(|step| {
+                    interpolate(
+                        &begin,
+                        &last,
+                        step as f64 / steps as f64,
+                        start + Duration::minutes(step * ALTITUDE_STEP),
+                        observer,
+                    )
+                })
+                .collect();
+            let max = *positions
+                .iter()
+                .max_by(|first, second| first.altitude.total_cmp(&second.altitude))?;
+            if max.altitude < params.min_alt() as f64 {
+                return None;
+            }
+            Some((
+                begin.magnitude,
+                PossibleTarget {
+                    designation: Designation::parse(&orbit.designation)
+                        .or_else(|_| Designation::from_packed(&orbit.packed_designation))
+                        .ok()?,
+                    magnitude: ((begin.magnitude * 10.0).round() / 10.0) as f32,
+                    solar_elongation: begin.solar_elongation.round() as f32,
+                    lunar_elongation: begin.lunar_elongation.round() as f32,
+                    begin: positions[0],
+                    max,
+                    end: *positions.last()?,
+                    ..PossibleTarget::default()
+                },
+            ))
+        })
+        .collect();
+    targets.sort_by(|(first, _), (second, _)| first.total_cmp(second));
+    Ok(targets
+        .into_iter()
+        .take(params.max_objects() as usize)
+        .map(|(_, target)| target)
+        .collect())
+}
+
+/// Returns data like the what's up list of MPC, computed from the local orbit catalogue
+///
+/// MPCORB.DAT next to config.toml is used when the catalogue is empty.
+/// Objects below the horizon limit of their direction are kept, flagged as blocked.
+///
+/// * `params`: WhatsupParams struct with all requested parameters
+pub fn local_whats_up_response(params: &WhatsUpParams) -> Result<Vec<PossibleTarget>> {
+    let settings = Settings::new().unwrap();
+    let catalogue = Catalogue::load()?;
+    let orbits = if catalogue.is_empty() {
+        orbit::load_mpcorb(&orbit::mpcorb_path())?
+    } else {
+        catalogue.orbits()
+    };
+    let mut objects = compute_whats_up(&orbits, params, &Observer::from_settings(&settings))?;
+    apply_horizon_limits(&mut objects, params.start(), &settings);
+    Ok(objects)
+}
+
+#[cfg(test)]
+mod test {
+    use super::*;
+
+    /// Returns a circular orbit in the ecliptic at the heliocentric longitude of the Earth
+    /// plus `offset`, at `jd`
+    fn circular_orbit(designation: &str, jd: f64, radius: f64, offset: f64) -> Orbit {
+        let earth = orbit::earth_position(jd);
+        Orbit {
+            designation: designation.to_string(),
+            packed_designation: designation.to_string(),
+            absolute_magnitude: 10.0,
+            slope: 0.15,
+            epoch: jd,
+            mean_anomaly: earth[1].atan2(earth[0]).to_degrees() + offset,
+            argument_of_perihelion: 0.0,
+            longitude_of_node: 0.0,
+            inclination: 0.0,
+            eccentricity: 0.0,
+            mean_motion: 0.0,
+            semi_major_axis: radius,
+        }
+    }
+
+    #[test]
+    fn test_compute_whats_up() {
+        let params = WhatsUpParams::builder()
+            .date(2025, 1, 15)
+            .time(0, 0)
+            .min_alt(20)
+            .solar_elong(90)
+            .build()
+            .unwrap();
+        let jd = julian_day(params.start());
+        let orbits = vec![
+            circular_orbit("Conjunction", jd, 2.5, 180.0),
+            circular_orbit("Faint", jd, 4.0, 0.0),
+            circular_orbit("Opposition", jd, 2.5, 0.0),
+        ];
+        let observer = Observer::new(45.0, 0.0, 0.0);
+        let targets = compute_whats_up(&orbits, &params, &observer).unwrap();
+        assert_eq!(targets.len(), 2);
+        let opposition = &targets[0];
+        assert_eq!(opposition.designation.to_string(), "Opposition");
+        assert!(opposition.solar_elongation > 179.0);
+        let earth_distance = 2.5 - orbit::norm(orbit::earth_position(jd));
+        let magnitude = 10.0 + 5.0 * (2.5 * earth_distance).log10();
+        assert!((opposition.magnitude as f64 - magnitude).abs() < 0.1);
+        assert!(opposition.max.altitude > 60.0);
+        assert_eq!(opposition.end.time, params.start() + Duration::hours(1));
+        assert_eq!(targets[1].designation.to_string(), "Faint");
+        let limited = WhatsUpParams::builder()
+            .start(params.start())
+            .min_alt(20)
+            .solar_elong(90)
+            .max_magnitude(14.0)
+            .build()
+            .unwrap();
+        let targets = compute_whats_up(&orbits, &limited, &observer).unwrap();
+        assert_eq!(targets.len(), 1);
+        assert_eq!(targets[0].designation.to_string(), "Opposition");
+
+        let neos = WhatsUpParams::builder()
+            .object_type(ObjectType::Neo)
+            .build()
+            .unwrap();
+        assert!(compute_whats_up(&orbits, &neos, &observer)
+            .unwrap()
+            .is_empty());
+        let comets = WhatsUpParams::builder()
+            .object_type(ObjectType::Comet)
+            .build()
+            .unwrap();
+        assert!(compute_whats_up(&orbits, &comets, &observer).is_err());
+    }
+}